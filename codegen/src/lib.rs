@@ -146,6 +146,20 @@ pub fn module(attr: TokenStream, item: TokenStream) -> TokenStream {
         .map(|ident| quote! { self.#ident })
         .collect();
 
+    // `#[new(default)] pub xxx: bool` is a flag, append `_xxx` to module name when it's set
+    let flag_suffixes: Vec<proc_macro2::TokenStream> = user_fields.iter()
+        .filter(|f| is_flag_field(f))
+        .map(|f| {
+            let ident = f.ident.as_ref().unwrap();
+            let suffix = format!("_{}", ident);
+            quote! {
+                if self.#ident {
+                    name.push_str(#suffix);
+                }
+            }
+        })
+        .collect();
+
     quote! {
         pub use derive_new::new;
         #[derive(Debug, new)]
@@ -166,8 +180,10 @@ pub fn module(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
 
             fn module_name(&self) -> crate::circuit::ShrString {
-                use crate::format_shr;
-                format_shr!(#format_lit, #(#self_fields),*)
+                #[allow(unused_mut)]
+                let mut name = format!(#format_lit, #(#self_fields),*);
+                #(#flag_suffixes)*
+                crate::circuit::ShrString::new_string(name)
             }
         }
 
@@ -178,6 +194,17 @@ pub fn module(attr: TokenStream, item: TokenStream) -> TokenStream {
 }
 
 
+fn is_flag_field(field: &syn::Field) -> bool {
+    let is_bool = matches!(&field.ty, syn::Type::Path(p) if p.path.is_ident("bool"));
+    let is_new_default = field.attrs.iter().any(|attr| {
+        match &attr.meta {
+            syn::Meta::List(list) => list.path.is_ident("new") && list.tokens.to_string() == "default",
+            _ => false,
+        }
+    });
+    is_bool && is_new_default
+}
+
 fn extract_placeholders(s: &str) -> Vec<String> {
     let re = Regex::new(r"\{([a-zA-Z_][a-zA-Z0-9_]*)\}").unwrap();
     re.captures_iter(s)
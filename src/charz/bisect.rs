@@ -0,0 +1,42 @@
+use crate::YouRAMResult;
+
+/// Smallest value in `[min, max]` that `pass`, within `resolution`.
+/// Passing is assumed monotonic, return `None` if `max` fails
+pub(crate) fn bisect(min: f64, max: f64, resolution: f64, mut pass: impl FnMut(f64) -> YouRAMResult<bool>) -> YouRAMResult<Option<f64>> {
+    if !pass(max)? {
+        return Ok(None);
+    }
+    if pass(min)? {
+        return Ok(Some(min));
+    }
+
+    let (mut passed, mut failed) = (max, min);
+    while passed - failed > resolution {
+        let value = (passed + failed) / 2.0;
+        if pass(value)? {
+            passed = value;
+        } else {
+            failed = value;
+        }
+    }
+    Ok(Some(passed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bisect() {
+        let mut steps = 0;
+        let time = bisect(-2.5e-9, 5e-9, 1e-12, |time| {
+            steps += 1;
+            Ok(time >= 1.234e-9)
+        }).unwrap().unwrap();
+        assert!(time >= 1.234e-9 && time - 1.234e-9 <= 1e-12);
+        assert!(steps < 16);
+
+        assert_eq!(bisect(-1.0, 1.0, 1e-3, |_| Ok(true)).unwrap(), Some(-1.0));
+        assert_eq!(bisect(-1.0, 1.0, 1e-3, |_| Ok(false)).unwrap(), None);
+    }
+}
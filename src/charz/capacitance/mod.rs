@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, HashMap};
+use reda_unit::{t, Capacitance, Number, Time, Voltage};
+use tracing::{debug, info};
+use crate::{circuit::{Shr, Sram}, pdk::{Enviroment, Pvt}, simulate::{CircuitSimulator, CurrentStatistic, JobContext, SimulateError, SupplyCurrentMeasBuilder}, YouRAMResult};
+use super::{CharzBench, CharzError, CharzSetup};
+
+/// Capacitance seen by the driver of an input pin
+#[derive(Debug, Clone, Copy)]
+pub struct PinCapacitance {
+    /// Charge to drive the pin from 0 to vdd, divided by vdd
+    pub rise: Capacitance,
+    /// Charge to drive the pin from vdd to 0, divided by vdd
+    pub fall: Capacitance,
+}
+
+impl PinCapacitance {
+    pub fn average(&self) -> Capacitance {
+        Capacitance::from((self.rise.value().to_f64() + self.fall.value().to_f64()) / 2.0)
+    }
+}
+
+/// Input pin capacitance charz for Sram
+///
+/// Each input pin is driven by a ramp up and a ramp down while other inputs are held,
+/// the charge through the pin's source is integrated over `window` after each edge.
+/// All bits of `addr` and `din` are characterized one by one
+///
+/// # Deafult:
+/// - input_net_transition: the smallest timing input net transition of pdk
+/// - window: 2ns
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - circuit_path: "./temp/<sram_name>.sp"
+/// - jobs: number of cpu
+///
+/// # Example
+///
+/// ```ignore
+/// let capacitances = CapacitanceCharz::config()
+///     .sram(sram)
+///     .pdk(pdk)
+///     .pvt(pvt)
+///     .analyze()?;
+/// println!("{:?}", capacitances[Sram::clock_pn().as_str()]);
+/// ```
+pub struct CapacitanceCharz {
+    pub setup: CharzSetup,
+    pub pvt: Option<Pvt>,
+    pub input_net_transition: Option<Time>,
+    /// Time to integrate charge after an edge begins
+    pub window: Time,
+
+    /// Max concurrent simulations
+    pub jobs: Option<usize>,
+}
+
+impl CapacitanceCharz {
+    const RISE_MEAS_NAME: &'static str = "charge_rise";
+    const FALL_MEAS_NAME: &'static str = "charge_fall";
+
+    /// Return capacitance of each input pin by port name
+    pub fn analyze(self) -> YouRAMResult<BTreeMap<String, PinCapacitance>> {
+        info!("execute input capacitance charz");
+
+        debug!("extract arguments");
+        let pvt = self.pvt.ok_or(CharzError::LackFunctionTestConfigField("pvt"))?;
+        let bench = self.setup.build()?;
+        let input_net_transition = bench.smallest_input_net_transition(self.input_net_transition)?;
+
+        let input_pins = Self::input_pins(&bench.sram);
+        // no load on ports, its charge would be counted as the pin's
+        let env = bench.env(pvt, input_net_transition, 0.0.into());
+        let capacitances = bench.runner(self.jobs).run("capacitance", input_pins.clone(), |pin, job| {
+            Self::analyze_pin(&bench, &input_pins, &pin, env.clone(), self.window, job)
+        })?;
+
+        Ok(input_pins.into_iter().zip(capacitances).collect())
+    }
+
+    fn input_pins(sram: &Shr<Sram>) -> Vec<String> {
+        let mut pins = vec![
+            Sram::clock_pn().to_string(),
+            Sram::chip_sel_bar_pn().to_string(),
+            Sram::write_enable_pn().to_string(),
+        ];
+        pins.extend((0..sram.read().address_width()).map(|i| Sram::address_pn(i).to_string()));
+        pins.extend((0..sram.read().word_width()).map(|i| Sram::data_input_pn(i).to_string()));
+        pins
+    }
+
+    fn analyze_pin(
+        bench: &CharzBench,
+        input_pins: &[String],
+        pin: &str,
+        env: Enviroment,
+        window: Time,
+        job: &JobContext,
+    ) -> YouRAMResult<PinCapacitance> {
+        let slew = env.input_slew();
+        let mut simulator = CircuitSimulator::create_with_dialect(
+            bench.sram.clone(), env, bench.pdk.clone(), job.simulate_path(), &bench.circuit_path, bench.command.dialect())?;
+        let voltage = simulator.logic1_voltage();
+
+        // deselected, other inputs stay at 0
+        for other in input_pins.iter().filter(|other| other.as_str() != pin) {
+            let selected = other.as_str() == Sram::chip_sel_bar_pn().as_str();
+            let other_voltage = simulator.logic_voltage(selected);
+            simulator.write_dc_stimulate(other, other_voltage)?;
+        }
+
+        let rise_begin = t!(1 n);
+        let fall_begin = rise_begin + window;
+        let end_time = fall_begin + window;
+        simulator.write_pwl_stimulate(pin, [
+            (t!(0), simulator.logic0_voltage()),
+            (rise_begin, simulator.logic0_voltage()),
+            (rise_begin + slew, voltage),
+            (fall_begin, voltage),
+            (fall_begin + slew, simulator.logic0_voltage()),
+        ].into_iter())?;
+
+        for (name, from, to) in [(Self::RISE_MEAS_NAME, rise_begin, fall_begin), (Self::FALL_MEAS_NAME, fall_begin, end_time)] {
+            let meas = SupplyCurrentMeasBuilder::default()
+                .name(name)
+                .probe_name(pin)
+                .statistic(CurrentStatistic::Integral)
+                .from(from)
+                .to(to)
+                .build().unwrap();
+            simulator.write_measurement(Box::new(meas))?;
+        }
+        simulator.write_trans(t!(1 p), 0.0, end_time)?;
+
+        debug!("spice simulate");
+        let result = simulator.simulate(&bench.command, job.work_folder())?;
+        Self::extract_result(&result, voltage)
+    }
+
+    /// The source delivers charge to the pin on rise, so the current into it is negative
+    fn extract_result(result: &HashMap<String, Number>, voltage: Voltage) -> YouRAMResult<PinCapacitance> {
+        let charge = |name: &str| -> YouRAMResult<f64> {
+            result.get(name)
+                .map(|value| value.to_f64())
+                .ok_or_else(|| SimulateError::MeasFailed(name.to_string()).into())
+        };
+        let voltage = voltage.value().to_f64();
+        Ok(PinCapacitance {
+            rise: Capacitance::from(-charge(Self::RISE_MEAS_NAME)? / voltage),
+            fall: Capacitance::from(charge(Self::FALL_MEAS_NAME)? / voltage),
+        })
+    }
+}
+
+impl Default for CapacitanceCharz {
+    fn default() -> Self {
+        Self {
+            setup: CharzSetup::default(),
+            pvt: None,
+            input_net_transition: None,
+            window: t!(2 n),
+            jobs: None,
+        }
+    }
+}
+
+impl_charz_setup!(CapacitanceCharz);
+
+impl CapacitanceCharz {
+    pub fn config() -> Self {
+        Self::default()
+    }
+
+    pub fn pvt(self, pvt: impl Into<Pvt>) -> Self {
+        let mut build = self;
+        build.pvt = Some(pvt.into());
+        build
+    }
+
+    pub fn input_net_transition(self, input_net_transition: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.input_net_transition = Some(input_net_transition.into());
+        build
+    }
+
+    pub fn window(self, window: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.window = window.into();
+        build
+    }
+
+    /// Max concurrent simulations, 0 means number of cpu
+    pub fn jobs(self, jobs: usize) -> Self {
+        let mut build = self;
+        build.jobs = Some(jobs);
+        build
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_unit::v;
+    use super::*;
+
+    #[test]
+    fn test_extract_result() {
+        let result: HashMap<String, Number> = [
+            ("charge_rise", -2.2e-15),
+            ("charge_fall", 1.98e-15),
+        ].into_iter().map(|(name, value)| (name.to_string(), Number::from_f64(value))).collect();
+
+        let capacitance = CapacitanceCharz::extract_result(&result, v!(1.1)).unwrap();
+        assert!((capacitance.rise.value().to_f64() - 2e-15).abs() < 1e-24);
+        assert!((capacitance.fall.value().to_f64() - 1.8e-15).abs() < 1e-24);
+        assert!((capacitance.average().value().to_f64() - 1.9e-15).abs() < 1e-24);
+        assert!(CapacitanceCharz::extract_result(&HashMap::new(), v!(1.1)).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use reda_unit::Time;
+use tracing::{debug, info};
+use crate::{circuit::Sram, pdk::{Enviroment, Pvt}, simulate::{Edge, JobContext}, YouRAMResult};
+use super::{bisect::bisect, CharzBench, CharzError, CharzSetup, FunctionTransactionGenerator};
+
+/// Default precision of setup/hold times
+const DEFAULT_RESOLUTION: f64 = 5e-12;
+
+/// Transaction whose inputs are captured under test, see `ConstraintCharz`
+const CAPTURE_TRANSACTION: usize = 2;
+
+/// Sram input pins constrained by `clk`, a bus is characterized on its bit 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConstraintPin {
+    Address,
+    DataInput,
+    WriteEnable,
+    ChipSelectBar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConstraintKind {
+    Setup,
+    Hold,
+}
+
+/// Setup and hold tables of a pin, indexed by [clk transition][pin transition].
+/// `rise` tables constrain a rising pin
+#[derive(Debug, Clone, Default)]
+pub struct PinConstraint {
+    pub setup_rise: Vec<Vec<Time>>,
+    pub setup_fall: Vec<Vec<Time>>,
+    pub hold_rise: Vec<Vec<Time>>,
+    pub hold_fall: Vec<Vec<Time>>,
+}
+
+/// Setup/hold charz of sram inputs relative to rising `clk`
+///
+/// For each pin, edge and pair of clk/pin transition, the change of the pin is moved
+/// toward the clock edge until the captured transaction goes wrong, the smallest
+/// passing distance is found by bisection. A failure is checked by reading the sram back.
+///
+/// # Deafult:
+/// - pins: all of `ConstraintPin`
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - circuit_path: "./temp/<sram_name>.sp"
+/// - resolution: 5ps
+/// - jobs: number of cpu
+///
+/// # Example
+///
+/// ```ignore
+/// let constraints = ConstraintCharz::config()
+///     .sram(sram)
+///     .pdk(pdk)
+///     .pvt(pvt)
+///     .input_net_transitions(&[t!(10 p), t!(50 p)])
+///     .period(t!(10 n))
+///     .analyze()?;
+/// let address_setup = &constraints[&ConstraintPin::Address].setup_rise;
+/// ```
+pub struct ConstraintCharz<'a> {
+    pub setup: CharzSetup,
+    pub period: Option<Time>,
+    pub pvt: Option<Pvt>,
+    /// Used as both clk and pin transitions
+    pub input_net_transitions: Option<&'a [Time]>,
+    pub pins: Vec<ConstraintPin>,
+
+    /// Bisection stops when the pass and fail times are closer than it
+    pub resolution: Option<Time>,
+    /// Max concurrent bisections
+    pub jobs: Option<usize>,
+}
+
+/// One bisection
+#[derive(Debug, Clone, Copy)]
+struct ConstraintCase {
+    pin: ConstraintPin,
+    kind: ConstraintKind,
+    edge: Edge,
+    clock_slew: Time,
+    pin_slew: Time,
+}
+
+impl<'a> ConstraintCharz<'a> {
+    pub fn analyze(self) -> YouRAMResult<BTreeMap<ConstraintPin, PinConstraint>> {
+        info!("execute setup/hold charz");
+
+        debug!("extract arguments");
+        let period = self.period.ok_or(CharzError::LackFunctionTestConfigField("period"))?;
+        let pvt = self.pvt.ok_or(CharzError::LackFunctionTestConfigField("pvt"))?;
+        let input_net_transitions = self.input_net_transitions.ok_or(CharzError::LackFunctionTestConfigField("input_net_transitions"))?;
+        let resolution = self.resolution.map(|r| r.value().to_f64()).unwrap_or(DEFAULT_RESOLUTION);
+        let bench = self.setup.build()?;
+
+        let mut cases = vec![];
+        for &pin in self.pins.iter() {
+            for kind in [ConstraintKind::Setup, ConstraintKind::Hold] {
+                for edge in [Edge::Rise, Edge::Fall] {
+                    for &clock_slew in input_net_transitions.iter() {
+                        for &pin_slew in input_net_transitions.iter() {
+                            cases.push(ConstraintCase { pin, kind, edge, clock_slew, pin_slew });
+                        }
+                    }
+                }
+            }
+        }
+
+        if cases.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let runner = bench.runner(self.jobs);
+        let times = runner.run("constraint", cases.clone(), |case, job| {
+            let env = bench.env(pvt.clone(), case.pin_slew, bench.output_load());
+            // the pin may change up to a quarter period after clk
+            let constraint = bisect(-period.value().to_f64() / 4.0, Self::nominal(case, period), resolution, |time| {
+                Self::check(&bench, period, case, Time::from(time), env.clone(), job)
+            })?;
+            let constraint = constraint.ok_or_else(|| CharzError::NominalInputTimingFail(format!("{:?}", case)))?;
+            debug!("{:?} is {:e}", case, constraint);
+            Ok(Time::from(constraint))
+        })?;
+
+        // back to [clock_slew][pin_slew] of each table
+        let mut results: BTreeMap<ConstraintPin, PinConstraint> = BTreeMap::new();
+        let size = input_net_transitions.len();
+        let mut times = times.into_iter();
+        for case in cases.chunks(size * size) {
+            let case = case[0];
+            let constraint = results.entry(case.pin).or_default();
+            let table = match (case.kind, case.edge) {
+                (ConstraintKind::Setup, Edge::Rise) => &mut constraint.setup_rise,
+                (ConstraintKind::Setup, Edge::Fall) => &mut constraint.setup_fall,
+                (ConstraintKind::Hold, Edge::Rise) => &mut constraint.hold_rise,
+                (ConstraintKind::Hold, Edge::Fall) => &mut constraint.hold_fall,
+            };
+            for _ in 0..size {
+                table.push(times.by_ref().take(size).collect());
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Setup/hold time when inputs change at the period begin
+    fn nominal(case: ConstraintCase, period: Time) -> f64 {
+        let (period, clock_slew) = (period.value().to_f64(), case.clock_slew.value().to_f64());
+        match case.kind {
+            ConstraintKind::Setup => period / 2.0 + clock_slew / 2.0,
+            ConstraintKind::Hold => period / 2.0 - clock_slew / 2.0,
+        }
+    }
+
+    /// Value of the pin in the captured transaction
+    fn captured_value(case: ConstraintCase) -> bool {
+        matches!((case.kind, case.edge), (ConstraintKind::Setup, Edge::Rise) | (ConstraintKind::Hold, Edge::Fall))
+    }
+
+    /// Simulate `case` with the pin changing `constraint` away from the clock edge,
+    /// and check all reads
+    fn check(
+        bench: &CharzBench,
+        period: Time,
+        case: ConstraintCase,
+        constraint: Time,
+        env: Enviroment,
+        job: &JobContext,
+    ) -> YouRAMResult<bool> {
+        let mut generator = FunctionTransactionGenerator::new(bench.sram.clone(), period);
+        Self::generate_transactions(&mut generator, case);
+
+        // clk crosses half voltage at the middle of its edge
+        let clock_edge = generator.transactions.clock_rise_time(CAPTURE_TRANSACTION) + case.clock_slew / 2.0;
+        let (change_time, transaction_index) = match case.kind {
+            ConstraintKind::Setup => (clock_edge - constraint, CAPTURE_TRANSACTION),
+            ConstraintKind::Hold => (clock_edge + constraint, CAPTURE_TRANSACTION + 1),
+        };
+        let port_name = match case.pin {
+            ConstraintPin::Address => Sram::address_pn(0),
+            ConstraintPin::DataInput => Sram::data_input_pn(0),
+            ConstraintPin::WriteEnable => Sram::write_enable_pn(),
+            ConstraintPin::ChipSelectBar => Sram::chip_sel_bar_pn(),
+        };
+        let skew = change_time - generator.transactions.clock_begin(transaction_index);
+        generator.transactions.skew_input(port_name.to_string(), transaction_index, skew);
+        generator.transactions.set_clock_slew(case.clock_slew);
+
+        let voltage = env.voltage();
+        let result = generator.transactions.simulate(
+            env, bench.pdk.clone(), &bench.command, job.simulate_path(), Some(&bench.circuit_path), job.work_folder()
+        )?;
+        Ok(FunctionTransactionGenerator::check_reads(&generator.target_meas_result, &result, voltage))
+    }
+
+    /// Transactions where the pin in the 3rd transaction differs from both neighbors,
+    /// and capturing a neighbor's value is seen by a read
+    fn generate_transactions(generator: &mut FunctionTransactionGenerator, case: ConstraintCase) {
+        let value = Self::captured_value(case);
+        let ones = generator.transactions.max_word();
+        let word = |bit: bool| if bit { ones } else { 0 };
+        let (a, b) = (1, 0);
+
+        match case.pin {
+            ConstraintPin::Address => {
+                // neighbors access the address with other bit 0
+                let (captured, neighbor) = (value as usize, (!value) as usize);
+                generator.add_write_transaction(captured, 0);
+                generator.add_write_transaction(neighbor, 0);
+                generator.add_write_transaction(captured, ones);
+                generator.add_read_transaction(neighbor);
+                generator.add_read_transaction(captured);
+            }
+            ConstraintPin::DataInput => {
+                generator.add_write_transaction(a, word(!value));
+                generator.add_write_transaction(b, word(!value));
+                generator.add_write_transaction(a, word(value));
+                generator.add_write_transaction(b, word(!value));
+                generator.add_read_transaction(a);
+            }
+            ConstraintPin::WriteEnable if value => {
+                // a write between reads
+                generator.add_write_transaction(a, 0);
+                generator.add_read_transaction(a);
+                generator.add_write_transaction(a, ones);
+                generator.add_read_transaction(a);
+                generator.add_read_transaction(a);
+            }
+            ConstraintPin::WriteEnable => {
+                // a read between writes, would write 0 if it is taken as a write
+                generator.add_write_transaction(a, ones);
+                generator.add_write_transaction(b, 0);
+                generator.add_read_transaction(a);
+                generator.add_write_transaction(b, 0);
+                generator.add_read_transaction(a);
+            }
+            ConstraintPin::ChipSelectBar => {
+                // a deselected write between selected writes, or the opposite
+                let add_write = |generator: &mut FunctionTransactionGenerator, selected: bool, address: usize, word: usize| {
+                    if selected {
+                        generator.add_write_transaction(address, word);
+                    } else {
+                        generator.transactions.add_idle_transaction(address, word);
+                    }
+                };
+                generator.add_write_transaction(a, 0);
+                add_write(generator, value, b, 0);
+                add_write(generator, !value, a, ones);
+                add_write(generator, value, b, 0);
+                generator.add_read_transaction(a);
+            }
+        }
+    }
+}
+
+impl<'a> Default for ConstraintCharz<'a> {
+    fn default() -> Self {
+        Self {
+            setup: CharzSetup::default(),
+            period: None,
+            pvt: None,
+            input_net_transitions: None,
+            pins: vec![ConstraintPin::Address, ConstraintPin::DataInput, ConstraintPin::WriteEnable, ConstraintPin::ChipSelectBar],
+            resolution: None,
+            jobs: None,
+        }
+    }
+}
+
+impl_charz_setup!(ConstraintCharz<'_>);
+
+impl<'a> ConstraintCharz<'a> {
+    pub fn config() -> Self {
+        Self::default()
+    }
+
+    pub fn period(self, period: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.period = Some(period.into());
+        build
+    }
+
+    pub fn pvt(self, pvt: impl Into<Pvt>) -> Self {
+        let mut build = self;
+        build.pvt = Some(pvt.into());
+        build
+    }
+
+    pub fn input_net_transitions(self, input_net_transitions: &'a [Time]) -> Self {
+        let mut build = self;
+        build.input_net_transitions = Some(input_net_transitions);
+        build
+    }
+
+    pub fn pins(self, pins: impl Into<Vec<ConstraintPin>>) -> Self {
+        let mut build = self;
+        build.pins = pins.into();
+        build
+    }
+
+    pub fn resolution(self, resolution: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.resolution = Some(resolution.into());
+        build
+    }
+
+    /// Max concurrent bisections, 0 means number of cpu
+    pub fn jobs(self, jobs: usize) -> Self {
+        let mut build = self;
+        build.jobs = Some(jobs);
+        build
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_captured_value() {
+        let case = |kind, edge| ConstraintCase { pin: ConstraintPin::DataInput, kind, edge, clock_slew: Time::from(0.0), pin_slew: Time::from(0.0) };
+        // a rising pin is 1 after the change
+        assert!(ConstraintCharz::captured_value(case(ConstraintKind::Setup, Edge::Rise)));
+        assert!(!ConstraintCharz::captured_value(case(ConstraintKind::Hold, Edge::Rise)));
+        assert!(!ConstraintCharz::captured_value(case(ConstraintKind::Setup, Edge::Fall)));
+        assert!(ConstraintCharz::captured_value(case(ConstraintKind::Hold, Edge::Fall)));
+    }
+}
@@ -3,4 +3,16 @@
 pub enum CharzError {
     #[error("lack function test config {0}")]
     LackFunctionTestConfigField(&'static str),
+
+    #[error("no {0} sizing meets the constraints")]
+    NoFeasibleSizing(&'static str),
+
+    #[error("{0} fails with nominal input timing, try a longer period")]
+    NominalInputTimingFail(String),
+
+    #[error("function test fails at max period {0}, try a longer period")]
+    MaxPeriodFail(String),
+
+    #[error("no transistor gate on storage net {0} of bitcell")]
+    StorageNetNotFound(String),
 }
\ No newline at end of file
@@ -114,9 +114,12 @@ impl FunctionCharz {
     }
 }
 
+#[derive(Clone)]
 pub struct FunctionTransactionGenerator {
     pub transactions: SramTransactionGenerator,
     pub target_meas_result: HashMap<String, bool>, 
+    /// Clock cycle each read bit of `target_meas_result` is measured in
+    pub read_clocks: HashMap<String, usize>,
 }
 
 pub trait FunctionCharzPolicy {
@@ -127,7 +130,8 @@ impl FunctionTransactionGenerator {
     pub fn new(sram: Shr<Sram>, period: Time) -> Self {
         Self { 
             transactions: SramTransactionGenerator::new(sram, period),
-            target_meas_result: HashMap::new()
+            target_meas_result: HashMap::new(),
+            read_clocks: HashMap::new(),
         }
     }
 
@@ -148,7 +152,10 @@ impl FunctionTransactionGenerator {
         // Wow, the last transaction is read, if there is `size` transactions
         // This read transaction's index is `transaction-1`, it will be enbale by `transaction-1` clock
         // So, we can read output in No. `transaction`'s clock rise
-        let meas_time = self.transactions.clock_rise_time(self.transactions.transaction_size()) - t!(1 n);
+        // a bit before it, at most 1ns so a short period still reads after its own clock rise
+        let margin = (self.transactions.period / 10.0).min(t!(1 n));
+        let meas_clock = self.transactions.transaction_size();
+        let meas_time = self.transactions.clock_rise_time(meas_clock) - margin;
 
         // for each bit of ouput port, add a meas
         for (bit_index, &bit) in bits.iter().enumerate() {
@@ -158,10 +165,37 @@ impl FunctionTransactionGenerator {
             let meas = VoltageAtMeas::new(meas_name.clone(), port_name.to_string(), meas_time);
 
             self.transactions.add_measurement(meas);
+            self.read_clocks.insert(meas_name.clone(), meas_clock);
             self.target_meas_result.insert(meas_name, bit);
         } 
     }
 
+    /// Every read bit of `target_meas_result` is on the expected side of half voltage
+    pub fn check_reads(target_meas_result: &HashMap<String, bool>, result: &HashMap<String, Number>, voltage: Voltage) -> bool {
+        target_meas_result.keys().all(|name| Self::check_read(target_meas_result, result, name, voltage))
+    }
+
+    /// Vdd of the clock cycle each read bit is measured in, see
+    /// `SramTransactionGenerator::set_transaction_voltage`
+    pub fn read_voltages(&self, nominal_voltage: Voltage) -> HashMap<String, Voltage> {
+        self.read_clocks.iter()
+            .map(|(name, &clock)| (name.clone(), self.transactions.cycle_voltage(clock, nominal_voltage)))
+            .collect()
+    }
+
+    /// Every read bit of `target_meas_result` is on the expected side of half its voltage
+    /// in `voltages`, see `read_voltages`
+    pub fn check_reads_at(target_meas_result: &HashMap<String, bool>, result: &HashMap<String, Number>, voltages: &HashMap<String, Voltage>) -> bool {
+        target_meas_result.keys().all(|name| {
+            voltages.get(name).is_some_and(|&voltage| Self::check_read(target_meas_result, result, name, voltage))
+        })
+    }
+
+    fn check_read(target_meas_result: &HashMap<String, bool>, result: &HashMap<String, Number>, name: &str, voltage: Voltage) -> bool {
+        let threshold = voltage.value().to_f64() / 2.0;
+        result.get(name).is_some_and(|value| (value.to_f64() > threshold) == target_meas_result[name])
+    }
+
 }
 
 impl Default for FunctionCharz {
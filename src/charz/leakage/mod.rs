@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use reda_unit::{t, Number, Power, Time, Voltage};
+use tracing::{debug, info};
+use crate::{circuit::Sram, pdk::{Enviroment, Pvt}, simulate::{CircuitSimulator, CurrentStatistic, JobContext, SimulateError, SupplyCurrentMeasBuilder}, YouRAMResult};
+use super::{CharzBench, CharzError, CharzSetup};
+
+/// Input levels the sram is held at, `csb` is always high
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LeakageState {
+    /// Level the clock is stopped at
+    pub clock: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct LeakageCharzResult {
+    pub pvt: Pvt,
+    pub states: Vec<(LeakageState, Power)>,
+}
+
+impl LeakageCharzResult {
+    /// Average of all states
+    pub fn cell_leakage_power(&self) -> Power {
+        if self.states.is_empty() {
+            return Power::from(0.0);
+        }
+        let sum: f64 = self.states.iter().map(|(_, power)| power.value().to_f64()).sum();
+        Power::from(sum / self.states.len() as f64)
+    }
+}
+
+/// Leakage charz for Sram
+///
+/// The sram is deselected with the clock stopped, after `settle_time` the supply current
+/// is averaged over `measure_time`. The power sums all supplies, the ones with their own
+/// voltage(e.g. `vdd_array`) included
+///
+/// # Deafult:
+/// - states: clock stopped low and high
+/// - settle_time: 10ns
+/// - measure_time: 10ns
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - circuit_path: "./temp/<sram_name>.sp"
+/// - jobs: number of cpu
+///
+/// # Example
+///
+/// ```ignore
+/// let results = LeakageCharz::config()
+///     .sram(sram)
+///     .pdk(pdk)
+///     .pvt(Pvt::new(Process::SlowSlow, 0.95, 125.0))
+///     .pvt(Pvt::new(Process::FastFast, 1.25, -40.0))
+///     .analyze()?;
+/// println!("{}", results[0].cell_leakage_power());
+/// ```
+pub struct LeakageCharz {
+    pub setup: CharzSetup,
+    /// Corners to characterize, one result each
+    pub pvts: Vec<Pvt>,
+    pub states: Vec<LeakageState>,
+    pub settle_time: Time,
+    pub measure_time: Time,
+
+    /// Max concurrent simulations
+    pub jobs: Option<usize>,
+}
+
+impl LeakageCharz {
+    const CURRENT_MEAS_NAME: &'static str = "leakage_current";
+
+    pub fn analyze(self) -> YouRAMResult<Vec<LeakageCharzResult>> {
+        info!("execute leakage charz");
+
+        debug!("extract arguments");
+        if self.pvts.is_empty() {
+            Err(CharzError::LackFunctionTestConfigField("pvts"))?;
+        }
+        let bench = self.setup.build()?;
+
+        let mut inputs = vec![];
+        for pvt in self.pvts.iter() {
+            for &state in self.states.iter() {
+                inputs.push((bench.env(pvt.clone(), t!(0), 0.0.into()), state));
+            }
+        }
+
+        let powers = bench.runner(self.jobs).run("leakage", inputs, |input, job| {
+            Self::analyze_in_env(&bench, input, (self.settle_time, self.measure_time), job)
+        })?;
+
+        let mut results = vec![];
+        let mut powers = powers.into_iter();
+        for pvt in self.pvts.iter() {
+            let states = self.states.iter().copied().zip(powers.by_ref()).collect();
+            results.push(LeakageCharzResult { pvt: pvt.clone(), states });
+        }
+
+        Ok(results)
+    }
+
+    fn analyze_in_env(
+        bench: &CharzBench,
+        (env, state): (Enviroment, LeakageState),
+        (settle_time, measure_time): (Time, Time),
+        job: &JobContext,
+    ) -> YouRAMResult<Power> {
+        let sram = &bench.sram;
+        let mut simulator = CircuitSimulator::create_with_dialect(
+            sram.clone(), env, bench.pdk.clone(), job.simulate_path(), &bench.circuit_path, bench.command.dialect())?;
+
+        // hold all inputs
+        let clock_voltage = simulator.logic_voltage(state.clock);
+        simulator.write_dc_stimulate(Sram::clock_pn(), clock_voltage)?;
+        simulator.write_logic1_stimulate(Sram::chip_sel_bar_pn())?;
+        simulator.write_logic0_stimulate(Sram::write_enable_pn())?;
+        for i in 0..sram.read().address_width() {
+            simulator.write_logic0_stimulate(Sram::address_pn(i))?;
+        }
+        for i in 0..sram.read().word_width() {
+            simulator.write_logic0_stimulate(Sram::data_input_pn(i))?;
+        }
+
+        let end_time = settle_time + measure_time;
+        let supplies = CircuitSimulator::supply_probes(&simulator.circuit, &simulator.env);
+        for (probe_name, _, _) in supplies.iter() {
+            let meas = SupplyCurrentMeasBuilder::default()
+                .name(Self::meas_name(probe_name))
+                .probe_name(probe_name.clone())
+                .statistic(CurrentStatistic::Average)
+                .from(settle_time)
+                .to(end_time)
+                .build().unwrap();
+            simulator.write_measurement(Box::new(meas))?;
+        }
+        simulator.write_trans(end_time / 1000.0, 0.0, end_time)?;
+
+        debug!("spice simulate");
+        let result = simulator.simulate(&bench.command, job.work_folder())?;
+        Self::extract_result(&result, &supplies)
+    }
+
+    fn meas_name(probe_name: &str) -> String {
+        format!("{}_{}", Self::CURRENT_MEAS_NAME, probe_name.to_ascii_lowercase())
+    }
+
+    /// Sum of average current times voltage of `supplies`, with the sign of current
+    fn extract_result(result: &HashMap<String, Number>, supplies: &[(String, Voltage, f64)]) -> YouRAMResult<Power> {
+        let mut power = 0.0;
+        for (probe_name, voltage, sign) in supplies.iter() {
+            let name = Self::meas_name(probe_name);
+            let current = result.get(&name)
+                .ok_or_else(|| SimulateError::MeasFailed(name.clone()))?;
+            power += sign * current.to_f64() * voltage.value().to_f64();
+        }
+        Ok(Power::from(power))
+    }
+}
+
+impl Default for LeakageCharz {
+    fn default() -> Self {
+        Self {
+            setup: CharzSetup::default(),
+            pvts: vec![],
+            states: vec![LeakageState { clock: false }, LeakageState { clock: true }],
+            settle_time: t!(10 n),
+            measure_time: t!(10 n),
+            jobs: None,
+        }
+    }
+}
+
+impl_charz_setup!(LeakageCharz);
+
+impl LeakageCharz {
+    pub fn config() -> Self {
+        Self::default()
+    }
+
+    /// Add a corner
+    pub fn pvt(self, pvt: impl Into<Pvt>) -> Self {
+        let mut build = self;
+        build.pvts.push(pvt.into());
+        build
+    }
+
+    pub fn pvts(self, pvts: impl IntoIterator<Item = Pvt>) -> Self {
+        let mut build = self;
+        build.pvts.extend(pvts);
+        build
+    }
+
+    pub fn states(self, states: impl Into<Vec<LeakageState>>) -> Self {
+        let mut build = self;
+        build.states = states.into();
+        build
+    }
+
+    pub fn settle_time(self, settle_time: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.settle_time = settle_time.into();
+        build
+    }
+
+    pub fn measure_time(self, measure_time: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.measure_time = measure_time.into();
+        build
+    }
+
+    /// Max concurrent simulations, 0 means number of cpu
+    pub fn jobs(self, jobs: usize) -> Self {
+        let mut build = self;
+        build.jobs = Some(jobs);
+        build
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_unit::v;
+    use crate::pdk::Process;
+    use super::*;
+
+    #[test]
+    fn test_extract_result() {
+        let supplies = [
+            (CircuitSimulator::VDD_PROBE_NAME.to_string(), v!(1.1), 1.0),
+            ("vdd_array".to_string(), v!(0.9), -1.0),
+        ];
+        let result: HashMap<String, Number> = [
+            ("leakage_current_vdd_probe", 2e-6),
+            ("leakage_current_vdd_array", -10e-6),
+        ].into_iter().map(|(name, value)| (name.to_string(), Number::from_f64(value))).collect();
+
+        let power = LeakageCharz::extract_result(&result, &supplies).unwrap();
+        assert!((power.value().to_f64() - (2.2e-6 + 9e-6)).abs() < 1e-15);
+        assert!(LeakageCharz::extract_result(&HashMap::new(), &supplies).is_err());
+
+        let result = LeakageCharzResult {
+            pvt: Pvt::new(Process::TypeType, 1.1, 25.0),
+            states: vec![(LeakageState { clock: false }, Power::from(1e-6)), (LeakageState { clock: true }, Power::from(3e-6))],
+        };
+        assert!((result.cell_leakage_power().value().to_f64() - 2e-6).abs() < 1e-15);
+    }
+}
@@ -0,0 +1,513 @@
+use std::{f64::consts::SQRT_2, fmt::Write, path::{Path, PathBuf}, sync::{Arc, RwLock}};
+use reda_sp::{Component, Subckt};
+use reda_unit::{t, Voltage};
+use tracing::{debug, info, warn};
+use crate::{
+    circuit::{Design, Leafcell, Port, PortDirection, Primitive, Shr, ShrCircuit, ShrString},
+    export,
+    pdk::{Enviroment, Pdk, Pvt},
+    simulate::{CircuitSimulator, JobContext, JobRunner, SimulateError, SpiceCommand},
+    ErrorContext, YouRAMResult
+};
+use super::{CharzError, CharzSetup};
+
+/// How the write margin is swept, the cell stores 1 in `Q` and is written to 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteMarginSweep {
+    /// Word line on, `bl` falls from vdd, the margin is `bl` when the cell flips
+    #[default]
+    Bitline,
+    /// `bl` at 0, word line rises from 0, the margin is vdd minus `wl` when the cell flips
+    Wordline,
+}
+
+/// Dc simulation of bitcell in `BitcellMarginCharz`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MarginAnalysis {
+    /// Open loop, word line off
+    Hold,
+    /// Open loop, word line on and both bitlines at vdd
+    Read,
+    Write(WriteMarginSweep),
+}
+
+/// Storage node voltages of a dc sweep
+#[derive(Debug, Clone, Default)]
+pub struct BitcellCurves {
+    /// Swept voltage
+    pub input: Vec<f64>,
+    pub q: Vec<f64>,
+    pub q_bar: Vec<f64>,
+}
+
+impl BitcellCurves {
+    /// Side of the largest square fitting in the smaller lobe of butterfly curves,
+    /// 0 if the curves have only one lobe
+    ///
+    /// The curves are open loop: `q_bar` is driven by `Q` at `input`, `q` is driven by
+    /// `Q_bar` at `input`. As in Seevinck's method, both curves are rotated by 45 degrees,
+    /// the diagonal of a square is the distance between them along the rotated axis
+    pub fn static_noise_margin(&self) -> Voltage {
+        // (Q, Q_bar) plane to (u, v), v is along the line of Q = Q_bar
+        let rotate = |q: f64, q_bar: f64| ((q - q_bar) / SQRT_2, (q + q_bar) / SQRT_2);
+        let mut driven_by_q: Vec<(f64, f64)> = self.input.iter().zip(self.q_bar.iter())
+            .map(|(&q, &q_bar)| rotate(q, q_bar))
+            .collect();
+        let mut driven_by_q_bar: Vec<(f64, f64)> = self.input.iter().zip(self.q.iter())
+            .map(|(&q_bar, &q)| rotate(q, q_bar))
+            .collect();
+        driven_by_q.sort_by(|a, b| a.0.total_cmp(&b.0));
+        driven_by_q_bar.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let (Some(a0), Some(b0), Some(a1), Some(b1)) = (driven_by_q.first(), driven_by_q_bar.first(), driven_by_q.last(), driven_by_q_bar.last()) else {
+            return Voltage::from(0.0);
+        };
+        let (from, to) = (a0.0.max(b0.0), a1.0.min(b1.0));
+
+        let (mut upper, mut lower) = (0.0f64, 0.0f64);
+        for u in driven_by_q.iter().chain(driven_by_q_bar.iter()).map(|p| p.0).filter(|&u| u >= from && u <= to) {
+            if let (Some(a), Some(b)) = (Self::interpolate(&driven_by_q, u), Self::interpolate(&driven_by_q_bar, u)) {
+                upper = upper.max(a - b);
+                lower = lower.max(b - a);
+            }
+        }
+
+        Voltage::from(upper.min(lower) / SQRT_2)
+    }
+
+    /// Input where `Q` falls below `Q_bar`, None if the cell never flips
+    pub fn flip_input(&self) -> Option<f64> {
+        let diffs: Vec<f64> = self.q.iter().zip(self.q_bar.iter()).map(|(q, q_bar)| q - q_bar).collect();
+        if diffs.first().is_none_or(|&d| d <= 0.0) {
+            return None;
+        }
+        let index = diffs.iter().position(|&d| d < 0.0)?;
+        let (d0, d1) = (diffs[index - 1], diffs[index]);
+        let (x0, x1) = (self.input[index - 1], self.input[index]);
+        Some(x0 + (x1 - x0) * d0 / (d0 - d1))
+    }
+
+    /// Write `input,q,q_bar` rows
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> YouRAMResult<()> {
+        let path = path.as_ref();
+        let mut content = String::from("input,q,q_bar\n");
+        for ((input, q), q_bar) in self.input.iter().zip(self.q.iter()).zip(self.q_bar.iter()) {
+            writeln!(content, "{:e},{:e},{:e}", input, q, q_bar).unwrap();
+        }
+        std::fs::write(path, content).with_context(|| format!("write '{}'", path.display()))?;
+        Ok(())
+    }
+
+    /// Linear interpolation of `points` sorted by x
+    fn interpolate(points: &[(f64, f64)], x: f64) -> Option<f64> {
+        let index = points.partition_point(|p| p.0 < x);
+        if index == 0 {
+            return points.first().filter(|p| p.0 == x).map(|p| p.1);
+        }
+        let (x0, y0) = points[index - 1];
+        let (x1, y1) = *points.get(index)?;
+        if x1 == x0 {
+            return Some(y0);
+        }
+        Some(y0 + (y1 - y0) * (x - x0) / (x1 - x0))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BitcellMarginResult {
+    pub pvt: Pvt,
+    pub hold_snm: Voltage,
+    pub read_snm: Voltage,
+    /// None if the cell can not be written in the sweep
+    pub write_margin: Option<Voltage>,
+
+    pub hold_curves: BitcellCurves,
+    pub read_curves: BitcellCurves,
+    pub write_curves: BitcellCurves,
+}
+
+impl BitcellMarginResult {
+    /// Write `<pvt>_hold.csv`, `<pvt>_read.csv` and `<pvt>_write.csv` in `folder`
+    pub fn write_csv(&self, folder: impl AsRef<Path>) -> YouRAMResult<()> {
+        let folder = folder.as_ref();
+        std::fs::create_dir_all(folder).context("create csv folder")?;
+        self.hold_curves.write_csv(folder.join(format!("{}_hold.csv", self.pvt)))?;
+        self.read_curves.write_csv(folder.join(format!("{}_read.csv", self.pvt)))?;
+        self.write_curves.write_csv(folder.join(format!("{}_write.csv", self.pvt)))?;
+        Ok(())
+    }
+}
+
+/// Bitcell with the loop of its cross-coupled inverters broken, gates on both storage
+/// nets are moved to one input port, and storage nets become ports
+struct OpenBitcell {
+    ports: Vec<Shr<Port>>,
+    netlist: Subckt,
+}
+
+impl OpenBitcell {
+    const NAME: &'static str = "bitcell_open";
+    const INPUT_PORT_NAME: &'static str = "sweep_in";
+
+    fn new(bitcell: &Leafcell, (q, q_bar): (&str, &str)) -> YouRAMResult<Self> {
+        let mut netlist = bitcell.netlist().clone();
+        netlist.name = Self::NAME.to_string();
+        for storage_net in [q, q_bar] {
+            let mut found = false;
+            for component in netlist.components.iter_mut() {
+                if let Component::M(mos) = component
+                    && mos.gate.eq_ignore_ascii_case(storage_net)
+                {
+                    mos.gate = Self::INPUT_PORT_NAME.to_string();
+                    found = true;
+                }
+            }
+            if !found {
+                Err(CharzError::StorageNetNotFound(storage_net.to_string()))?;
+            }
+        }
+        netlist.ports.extend([Self::INPUT_PORT_NAME, q, q_bar].map(String::from));
+
+        let mut ports: Vec<Shr<Port>> = bitcell.ports().iter()
+            .map(|port| Port::new(port.read().name.clone(), port.read().direction))
+            .collect();
+        ports.push(Port::new(Self::INPUT_PORT_NAME, PortDirection::Input));
+        ports.push(Port::new(q.to_string(), PortDirection::Output));
+        ports.push(Port::new(q_bar.to_string(), PortDirection::Output));
+
+        Ok(Self { ports, netlist })
+    }
+}
+
+impl Design for OpenBitcell {
+    fn name(&self) -> ShrString {
+        Self::NAME.into()
+    }
+
+    fn ports(&self) -> &[Shr<Port>] {
+        &self.ports
+    }
+}
+
+impl Primitive for OpenBitcell {
+    fn netlist(&self) -> &Subckt {
+        &self.netlist
+    }
+}
+
+/// Static noise margin and write margin charz of the pdk bitcell
+///
+/// Hold and read SNM come from butterfly curves of a dc sweep of the bitcell with its loop
+/// broken, write margin from a dc sweep of the closed bitcell, see `WriteMarginSweep`.
+/// Each corner is characterized at its own voltage and temperature, waveforms are read
+/// from the rawfile of simulator
+///
+/// # Deafult:
+/// - storage_nets: `Q` and `Q_bar`, the nets of generated bitcell
+/// - write_sweep: bitline
+/// - steps: 200 points of each sweep
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - jobs: number of cpu
+///
+/// # Example
+///
+/// ```ignore
+/// let results = BitcellMarginCharz::config()
+///     .pdk(pdk)
+///     .pvt(Pvt::new(Process::SlowSlow, 0.95, 125.0))
+///     .pvt(Pvt::new(Process::FastFast, 1.25, -40.0))
+///     .csv_folder("./output/margin")
+///     .analyze()?;
+/// println!("{}", results[0].read_snm);
+/// ```
+pub struct BitcellMarginCharz {
+    /// Only `pdk`, `command` and `temp_folder` are used, the bitcell comes from pdk
+    pub setup: CharzSetup,
+    /// Corners to characterize, one result each
+    pub pvts: Vec<Pvt>,
+    /// Supply of bitcell(e.g. `vdd_array`), default is pvt voltage
+    pub cell_voltage: Option<Voltage>,
+    /// Internal nets storing `Q` and `Q_bar`
+    pub storage_nets: (String, String),
+    pub write_sweep: WriteMarginSweep,
+    pub steps: usize,
+
+    /// Write curves of each corner in this folder, see `BitcellMarginResult::write_csv`
+    pub csv_folder: Option<PathBuf>,
+
+    /// Max concurrent simulations
+    pub jobs: Option<usize>,
+}
+
+impl BitcellMarginCharz {
+    pub fn analyze(self) -> YouRAMResult<Vec<BitcellMarginResult>> {
+        info!("execute bitcell margin charz");
+
+        debug!("extract arguments");
+        let pdk = self.setup.pdk.ok_or(CharzError::LackFunctionTestConfigField("pdk"))?;
+        let command = self.setup.command.ok_or(CharzError::LackFunctionTestConfigField("command"))?;
+        if self.pvts.is_empty() {
+            Err(CharzError::LackFunctionTestConfigField("pvts"))?;
+        }
+
+        let temp_folder = self.setup.temp_folder.unwrap_or_else(|| "./temp".into());
+        std::fs::create_dir_all(&temp_folder).context("create temp folder")?;
+
+        // closed and open loop bitcell
+        let bitcell = pdk.get_bitcell();
+        let open_bitcell = OpenBitcell::new(&bitcell.read(), (&self.storage_nets.0, &self.storage_nets.1))?;
+        let open_bitcell: Arc<RwLock<dyn Primitive>> = Shr::new(open_bitcell).inner();
+        let open_bitcell = ShrCircuit::Primitive(Shr::from_inner(open_bitcell));
+        let bitcell: ShrCircuit = bitcell.into();
+        let circuit_paths = (temp_folder.join("bitcell.sp"), temp_folder.join("bitcell_open.sp"));
+        export::write_spice(bitcell.clone(), &circuit_paths.0).context("write bitcell")?;
+        export::write_spice(open_bitcell.clone(), &circuit_paths.1).context("write open bitcell")?;
+
+        let mut runner = JobRunner::new(&temp_folder);
+        if let Some(jobs) = self.jobs {
+            runner = runner.jobs(jobs);
+        }
+
+        let analyses = [MarginAnalysis::Hold, MarginAnalysis::Read, MarginAnalysis::Write(self.write_sweep)];
+        let mut inputs = vec![];
+        for pvt in self.pvts.iter() {
+            // bitcell vdd is the only supply
+            let pvt = Pvt { voltage: self.cell_voltage.unwrap_or(pvt.voltage), ..pvt.clone() };
+            let env = Enviroment::new(pvt, t!(0), 0.0.into());
+            for analysis in analyses {
+                inputs.push((env.clone(), analysis));
+            }
+        }
+
+        let storage_nets = (self.storage_nets.0.as_str(), self.storage_nets.1.as_str());
+        let curves = runner.run("margin", inputs, |(env, analysis), job| {
+            let (circuit, circuit_path) = match analysis {
+                MarginAnalysis::Write(_) => (&bitcell, &circuit_paths.0),
+                _ => (&open_bitcell, &circuit_paths.1),
+            };
+            Self::analyze_in_env(circuit, (env, analysis), storage_nets, (pdk.clone(), &command, circuit_path), self.steps, job)
+        })?;
+
+        let mut results = vec![];
+        let mut curves = curves.into_iter();
+        for pvt in self.pvts.iter() {
+            let (Some(hold_curves), Some(read_curves), Some(write_curves)) = (curves.next(), curves.next(), curves.next()) else {
+                break;
+            };
+            let vdd = self.cell_voltage.unwrap_or(pvt.voltage).value().to_f64();
+            let write_margin = write_curves.flip_input().map(|input| match self.write_sweep {
+                WriteMarginSweep::Bitline => Voltage::from(input),
+                WriteMarginSweep::Wordline => Voltage::from(vdd - input),
+            });
+            let result = BitcellMarginResult {
+                pvt: pvt.clone(),
+                hold_snm: hold_curves.static_noise_margin(),
+                read_snm: read_curves.static_noise_margin(),
+                write_margin,
+                hold_curves,
+                read_curves,
+                write_curves,
+            };
+
+            match result.write_margin {
+                Some(write_margin) => info!("{}: hold snm {}, read snm {}, write margin {}", pvt, result.hold_snm, result.read_snm, write_margin),
+                None => warn!("{}: hold snm {}, read snm {}, bitcell is not written", pvt, result.hold_snm, result.read_snm),
+            }
+            if let Some(csv_folder) = &self.csv_folder {
+                result.write_csv(csv_folder)?;
+            }
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    fn analyze_in_env(
+        circuit: &ShrCircuit,
+        (env, analysis): (Enviroment, MarginAnalysis),
+        (q, q_bar): (&str, &str),
+        (pdk, command, circuit_path): (Arc<Pdk>, &impl SpiceCommand, &Path),
+        steps: usize,
+        job: &JobContext,
+    ) -> YouRAMResult<BitcellCurves> {
+        let (bl, br, wl) = {
+            let ports = circuit.ports();
+            (ports[0].read().name.to_string(), ports[1].read().name.to_string(), ports[2].read().name.to_string())
+        };
+        let circuit_name = circuit.name().to_string();
+        let vdd = env.voltage();
+        let step = vdd / steps.max(1) as f64;
+
+        let mut simulator = CircuitSimulator::create_with_dialect(
+            circuit.clone(), env, pdk, job.simulate_path(), circuit_path, command.dialect())?;
+
+        // nets of Q and Q_bar, internal ones for the closed bitcell
+        let (q, q_bar) = match analysis {
+            MarginAnalysis::Hold | MarginAnalysis::Read => {
+                let wl_voltage = if analysis == MarginAnalysis::Read { vdd } else { Voltage::from(0.0) };
+                simulator.write_dc_stimulate(&bl, vdd)?;
+                simulator.write_dc_stimulate(&br, vdd)?;
+                simulator.write_dc_stimulate(&wl, wl_voltage)?;
+                simulator.write_dc_stimulate(OpenBitcell::INPUT_PORT_NAME, 0.0)?;
+                simulator.write_dc_sweep(OpenBitcell::INPUT_PORT_NAME, 0.0, vdd, step)?;
+                (q.to_string(), q_bar.to_string())
+            }
+            MarginAnalysis::Write(sweep) => {
+                let (q, q_bar) = (format!("X{}.{}", circuit_name, q), format!("X{}.{}", circuit_name, q_bar));
+                simulator.write_dc_stimulate(&br, vdd)?;
+                match sweep {
+                    WriteMarginSweep::Bitline => {
+                        simulator.write_dc_stimulate(&wl, vdd)?;
+                        simulator.write_dc_stimulate(&bl, vdd)?;
+                        simulator.write_dc_sweep(&bl, vdd, 0.0, step * -1.0)?;
+                    }
+                    WriteMarginSweep::Wordline => {
+                        simulator.write_dc_stimulate(&bl, 0.0)?;
+                        simulator.write_dc_stimulate(&wl, 0.0)?;
+                        simulator.write_dc_sweep(&wl, 0.0, vdd, step)?;
+                    }
+                }
+                // start from storing 1
+                simulator.write_nodeset(&q, vdd)?;
+                simulator.write_nodeset(&q_bar, 0.0)?;
+                (q, q_bar)
+            }
+        };
+
+        debug!("spice simulate");
+        let (_, rawfile) = simulator.simulate_waveforms(command, job.work_folder())?;
+        let plot = rawfile.dc().ok_or_else(|| SimulateError::MeasFailed("dc sweep".to_string()))?;
+        let waveform = |name: &str| plot.waveform(name)
+            .map(|waveform| waveform.values)
+            .ok_or_else(|| SimulateError::MeasFailed(name.to_string()));
+
+        Ok(BitcellCurves {
+            input: plot.sweep().unwrap_or_default().to_vec(),
+            q: waveform(&q)?,
+            q_bar: waveform(&q_bar)?,
+        })
+    }
+}
+
+impl Default for BitcellMarginCharz {
+    fn default() -> Self {
+        Self {
+            setup: CharzSetup::default(),
+            pvts: vec![],
+            cell_voltage: None,
+            storage_nets: ("Q".to_string(), "Q_bar".to_string()),
+            write_sweep: WriteMarginSweep::default(),
+            steps: 200,
+            csv_folder: None,
+            jobs: None,
+        }
+    }
+}
+
+impl_charz_simulate!(BitcellMarginCharz);
+
+impl BitcellMarginCharz {
+    pub fn config() -> Self {
+        Self::default()
+    }
+
+    /// Add a corner
+    pub fn pvt(self, pvt: impl Into<Pvt>) -> Self {
+        let mut build = self;
+        build.pvts.push(pvt.into());
+        build
+    }
+
+    pub fn pvts(self, pvts: impl IntoIterator<Item = Pvt>) -> Self {
+        let mut build = self;
+        build.pvts.extend(pvts);
+        build
+    }
+
+    pub fn cell_voltage(self, cell_voltage: impl Into<Voltage>) -> Self {
+        let mut build = self;
+        build.cell_voltage = Some(cell_voltage.into());
+        build
+    }
+
+    pub fn storage_nets(self, q: impl Into<String>, q_bar: impl Into<String>) -> Self {
+        let mut build = self;
+        build.storage_nets = (q.into(), q_bar.into());
+        build
+    }
+
+    pub fn write_sweep(self, write_sweep: WriteMarginSweep) -> Self {
+        let mut build = self;
+        build.write_sweep = write_sweep;
+        build
+    }
+
+    pub fn steps(self, steps: usize) -> Self {
+        let mut build = self;
+        build.steps = steps;
+        build
+    }
+
+    pub fn csv_folder(self, csv_folder: impl Into<PathBuf>) -> Self {
+        let mut build = self;
+        build.csv_folder = Some(csv_folder.into());
+        build
+    }
+
+    /// Max concurrent simulations, 0 means number of cpu
+    pub fn jobs(self, jobs: usize) -> Self {
+        let mut build = self;
+        build.jobs = Some(jobs);
+        build
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Open loop curves of two inverters switching at `threshold_q` and `threshold_q_bar`
+    fn butterfly(threshold_q: f64, threshold_q_bar: f64) -> BitcellCurves {
+        let inverter = |x: f64, threshold: f64| 0.5 * (1.0 - (200.0 * (x - threshold)).tanh());
+        let input: Vec<f64> = (0..=1000).map(|i| i as f64 / 1000.0).collect();
+        BitcellCurves {
+            q_bar: input.iter().map(|&x| inverter(x, threshold_q)).collect(),
+            q: input.iter().map(|&x| inverter(x, threshold_q_bar)).collect(),
+            input,
+        }
+    }
+
+    #[test]
+    fn test_static_noise_margin() {
+        let snm = butterfly(0.5, 0.5).static_noise_margin().value().to_f64();
+        assert!((snm - 0.5).abs() < 0.02, "{}", snm);
+
+        // the lobe near the weak inverter is smaller
+        let snm = butterfly(0.9, 0.1).static_noise_margin().value().to_f64();
+        assert!((snm - 0.1).abs() < 0.02, "{}", snm);
+
+        // curves on top of each other have no lobe
+        let input: Vec<f64> = (0..=100).map(|i| i as f64 / 100.0).collect();
+        let line: Vec<f64> = input.iter().map(|x| 1.0 - x).collect();
+        let curves = BitcellCurves { input, q: line.clone(), q_bar: line };
+        assert!(curves.static_noise_margin().value().to_f64().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_flip_input() {
+        let curves = BitcellCurves {
+            input: vec![1.0, 0.8, 0.6, 0.4],
+            q: vec![1.0, 0.9, 0.7, 0.0],
+            q_bar: vec![0.0, 0.1, 0.3, 1.0],
+        };
+        let flip = curves.flip_input().unwrap();
+        assert!((flip - (0.6 - 0.2 * 0.4 / 1.4)).abs() < 1e-12);
+
+        // never flips, or not storing 1 at the start
+        let curves = BitcellCurves { q: vec![1.0, 0.9, 0.7, 0.6], q_bar: vec![0.0, 0.1, 0.3, 0.4], ..curves };
+        assert!(curves.flip_input().is_none());
+        let curves = BitcellCurves { q: vec![0.0, 0.0, 0.0, 0.0], ..curves };
+        assert!(curves.flip_input().is_none());
+    }
+}
@@ -1,9 +1,31 @@
+#[macro_use]
+mod setup;
 mod error;
+mod bisect;
 mod transaction;
 mod function;
 mod timing;
+mod constraint;
+mod power;
+mod leakage;
+mod period;
+mod capacitance;
+mod margin;
+mod vmin;
+mod sizing;
+mod montecarlo;
 
 pub use error::*;
+pub use setup::*;
 pub use transaction::*;
 pub use function::*;
-pub use timing::*;
\ No newline at end of file
+pub use timing::*;
+pub use constraint::*;
+pub use power::*;
+pub use leakage::*;
+pub use period::*;
+pub use capacitance::*;
+pub use margin::*;
+pub use vmin::*;
+pub use sizing::*;
+pub use montecarlo::*;
\ No newline at end of file
@@ -0,0 +1,192 @@
+use std::{collections::BTreeMap, path::PathBuf};
+use tracing::{info, warn};
+use crate::{pdk::Enviroment, simulate::{JobContext, JobRunner}, YouRAMResult};
+
+/// Run a characterization on many mismatch samples of the leafcells, see `MismatchConfig`
+///
+/// Run `i` simulates with mismatch seed `seed + i`, so a run can be reproduced alone.
+/// A run returning error is counted as a failure, like a read flipping the bitcell
+///
+/// # Default:
+/// - seed: 0
+/// - jobs: number of cpu
+///
+/// # Example
+///
+/// ```ignore
+/// let summary = MonteCarlo::new("./temp/mc", 100)
+///     .seed(42)
+///     .run(&env, |env, job| {
+///         let delay = read_delay(env, job.simulate_path(), job.work_folder())?;
+///         Ok(BTreeMap::from([("read_delay".to_string(), delay.to_f64())]))
+///     })?;
+/// println!("{}", summary.metric("read_delay").unwrap().sigma);
+/// ```
+pub struct MonteCarlo {
+    pub work_folder: PathBuf,
+    pub runs: usize,
+    pub seed: u64,
+    pub jobs: usize,
+}
+
+/// Aggregated results of a Monte Carlo
+#[derive(Debug, Clone)]
+pub struct MonteCarloSummary {
+    pub runs: usize,
+    pub failures: Vec<MonteCarloFailure>,
+    /// Statistic of each metric over the passed runs
+    pub metrics: BTreeMap<String, MetricStatistic>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MonteCarloFailure {
+    pub run: usize,
+    pub seed: u64,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricStatistic {
+    pub count: usize,
+    pub mean: f64,
+    /// Sample standard deviation
+    pub sigma: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MonteCarlo {
+    pub fn new(work_folder: impl Into<PathBuf>, runs: usize) -> Self {
+        Self { work_folder: work_folder.into(), runs, seed: 0, jobs: 0 }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set max concurrent runs, 0 means number of cpu
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Run `charz` once per sample with `env` carrying its mismatch seed,
+    /// `charz` returns named metrics of the run
+    pub fn run<F>(&self, env: &Enviroment, charz: F) -> YouRAMResult<MonteCarloSummary>
+    where
+        F: Fn(Enviroment, &JobContext) -> YouRAMResult<BTreeMap<String, f64>> + Sync,
+    {
+        let seeds: Vec<u64> = (0..self.runs as u64).map(|i| self.seed.wrapping_add(i)).collect();
+        let results = JobRunner::new(&self.work_folder)
+            .jobs(self.jobs)
+            .run("mc", seeds.clone(), |seed, job| {
+                // a failed sample is a result, not a reason to stop others
+                Ok(charz(env.clone().with_mismatch_seed(seed), job).map_err(|e| e.to_string()))
+            })?;
+
+        let mut failures = vec![];
+        let mut samples: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+        for (run, (seed, result)) in seeds.into_iter().zip(results).enumerate() {
+            match result {
+                Ok(metrics) => for (name, value) in metrics {
+                    samples.entry(name).or_default().push(value);
+                }
+                Err(error) => {
+                    warn!("monte carlo run {} with seed {} failed: {}", run, seed, error);
+                    failures.push(MonteCarloFailure { run, seed, error });
+                }
+            }
+        }
+
+        let summary = MonteCarloSummary {
+            runs: self.runs,
+            failures,
+            metrics: samples.into_iter()
+                .filter_map(|(name, values)| MetricStatistic::from_samples(&values).map(|s| (name, s)))
+                .collect(),
+        };
+        info!("monte carlo {} runs, {} failed", summary.runs, summary.failure_count());
+        Ok(summary)
+    }
+}
+
+impl MonteCarloSummary {
+    pub fn failure_count(&self) -> usize {
+        self.failures.len()
+    }
+
+    pub fn failure_rate(&self) -> f64 {
+        if self.runs == 0 { 0.0 } else { self.failures.len() as f64 / self.runs as f64 }
+    }
+
+    pub fn metric(&self, name: &str) -> Option<&MetricStatistic> {
+        self.metrics.get(name)
+    }
+}
+
+impl MetricStatistic {
+    /// None if there is no sample
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let count = samples.len();
+        let mean = samples.iter().sum::<f64>() / count as f64;
+        let sigma = if count > 1 {
+            (samples.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = samples.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Some(Self { count, mean, sigma, min, max })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_unit::t;
+    use crate::{pdk::{Process, Pvt}, simulate::SimulateError};
+    use super::*;
+
+    #[test]
+    fn test_monte_carlo() {
+        let temp = tempfile::tempdir().unwrap();
+        let env = Enviroment::new(Pvt::new(Process::TypeType, 1.1, 25.0), t!(10 p), 0.0.into());
+        let summary = MonteCarlo::new(temp.path(), 10)
+            .seed(100)
+            .jobs(3)
+            .run(&env, |env, job| {
+                let seed = env.mismatch_seed().unwrap();
+                assert!(job.work_folder().is_dir());
+                if seed % 4 == 0 {
+                    Err(SimulateError::MeasFailed(format!("read_{}", seed)))?;
+                }
+                Ok(BTreeMap::from([("delay".to_string(), seed as f64)]))
+            })
+            .unwrap();
+
+        assert_eq!(summary.runs, 10);
+        // seeds 100..110
+        assert_eq!(summary.failures.iter().map(|f| f.seed).collect::<Vec<_>>(), vec![100, 104, 108]);
+        assert_eq!(summary.failures[1].run, 4);
+        assert!((summary.failure_rate() - 0.3).abs() < 1e-12);
+
+        let delay = summary.metric("delay").unwrap();
+        assert_eq!(delay.count, 7);
+        assert_eq!((delay.min, delay.max), (101.0, 109.0));
+        let expected = [101.0, 102.0, 103.0, 105.0, 106.0, 107.0, 109.0];
+        assert_eq!(Some(delay.clone()), MetricStatistic::from_samples(&expected));
+        assert!((delay.mean - 733.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_statistic() {
+        let statistic = MetricStatistic::from_samples(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert_eq!(statistic.mean, 2.5);
+        assert!((statistic.sigma - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!(MetricStatistic::from_samples(&[2.0]).unwrap().sigma, 0.0);
+        assert!(MetricStatistic::from_samples(&[]).is_none());
+    }
+}
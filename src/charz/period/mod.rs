@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use reda_unit::{Number, Time, Voltage};
+use tracing::{debug, info};
+use crate::{pdk::Pvt, simulate::JobContext, ErrorContext, YouRAMResult};
+use super::{bisect::bisect, CharzError, CharzSetup, FunctionCharzPolicy, FunctionTransactionGenerator, MarchCPolicy};
+
+const DEFAULT_RESOLUTION: f64 = 10e-12;
+
+/// Minimum clock period charz for Sram
+///
+/// Bisect the shortest period in `[min_period, max_period]` at which every read of the
+/// policy's transactions is on the expected side of half voltage. Passing is assumed
+/// monotonic in period. The clock is a 50% duty square wave, so the min pulse width is
+/// half of the min period
+///
+/// # Deafult:
+/// - policy: march c
+/// - input_net_transition: the largest timing input net transition of pdk
+/// - min_period: max_period / 20
+/// - resolution: 10ps
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - circuit_path: "./temp/<sram_name>.sp"
+///
+/// # Example
+///
+/// ```ignore
+/// let min_period = PeriodCharz::config()
+///     .sram(sram)
+///     .pdk(pdk)
+///     .pvt(pvt)
+///     .max_period(t!(10 n))
+///     .analyze()?;
+/// ```
+pub struct PeriodCharz {
+    pub setup: CharzSetup,
+    pub pvt: Option<Pvt>,
+    pub input_net_transition: Option<Time>,
+    /// Upper bound of search, must pass
+    pub max_period: Option<Time>,
+    pub min_period: Option<Time>,
+    pub resolution: Option<Time>,
+
+    pub policy: Option<Box<dyn FunctionCharzPolicy>>,
+}
+
+impl PeriodCharz {
+    pub fn analyze(self) -> YouRAMResult<Time> {
+        info!("execute min period charz");
+
+        debug!("extract arguments");
+        let pvt = self.pvt.ok_or(CharzError::LackFunctionTestConfigField("pvt"))?;
+        let max_period = self.max_period.ok_or(CharzError::LackFunctionTestConfigField("max_period"))?;
+        let policy = self.policy.ok_or(CharzError::LackFunctionTestConfigField("policy"))?;
+        let min_period = self.min_period.unwrap_or(max_period / 20.0);
+        let resolution = self.resolution.map(|r| r.value().to_f64()).unwrap_or(DEFAULT_RESOLUTION);
+        let bench = self.setup.build()?;
+        let input_net_transition = bench.largest_input_net_transition(self.input_net_transition)?;
+        let env = bench.env(pvt, input_net_transition, bench.output_load());
+
+        // the bisection is sequential, all steps share one job folder
+        let job = JobContext { index: 0, name: "period".to_string(), work_folder: bench.temp_folder.join("period") };
+        std::fs::create_dir_all(job.work_folder()).context("create period folder")?;
+        let min_period = bisect(min_period.value().to_f64(), max_period.value().to_f64(), resolution, |period| {
+            let mut generator = FunctionTransactionGenerator::new(bench.sram.clone(), Time::from(period));
+            policy.generate_transactions(&mut generator)?;
+
+            let voltage = env.voltage();
+            let result = generator.transactions.simulate(
+                env.clone(), bench.pdk.clone(), &bench.command, job.simulate_path(), Some(&bench.circuit_path), job.work_folder()
+            )?;
+            let pass = Self::extract_pass(&generator.target_meas_result, &result, voltage);
+            debug!("period {:e} {}", period, if pass { "passed" } else { "failed" });
+            Ok(pass)
+        })?;
+        let min_period = Self::extract_result(min_period, max_period)?;
+
+        info!("min period is {}", min_period);
+        Ok(min_period)
+    }
+
+    /// A period passes if every read is on the expected side of half `voltage`, a missing read fails
+    fn extract_pass(target_meas_result: &HashMap<String, bool>, result: &HashMap<String, Number>, voltage: Voltage) -> bool {
+        FunctionTransactionGenerator::check_reads(target_meas_result, result, voltage)
+    }
+
+    /// Bisected min period, error if `max_period` fails
+    fn extract_result(min_period: Option<f64>, max_period: Time) -> YouRAMResult<Time> {
+        let min_period = min_period.ok_or_else(|| CharzError::MaxPeriodFail(max_period.to_string()))?;
+        Ok(Time::from(min_period))
+    }
+}
+
+impl Default for PeriodCharz {
+    fn default() -> Self {
+        Self {
+            setup: CharzSetup::default(),
+            pvt: None,
+            input_net_transition: None,
+            max_period: None,
+            min_period: None,
+            resolution: None,
+            policy: Some(Box::new(MarchCPolicy)),
+        }
+    }
+}
+
+impl_charz_setup!(PeriodCharz);
+
+impl PeriodCharz {
+    pub fn config() -> Self {
+        Self::default()
+    }
+
+    pub fn pvt(self, pvt: impl Into<Pvt>) -> Self {
+        let mut build = self;
+        build.pvt = Some(pvt.into());
+        build
+    }
+
+    pub fn input_net_transition(self, input_net_transition: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.input_net_transition = Some(input_net_transition.into());
+        build
+    }
+
+    pub fn max_period(self, max_period: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.max_period = Some(max_period.into());
+        build
+    }
+
+    pub fn min_period(self, min_period: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.min_period = Some(min_period.into());
+        build
+    }
+
+    pub fn resolution(self, resolution: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.resolution = Some(resolution.into());
+        build
+    }
+
+    pub fn policy_box(mut self, policy: Box<dyn FunctionCharzPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_unit::{t, v};
+    use super::*;
+
+    #[test]
+    fn test_extract_result() {
+        let target: HashMap<String, bool> = [("dout0_0", true), ("dout0_1", false)]
+            .into_iter().map(|(name, bit)| (name.to_string(), bit)).collect();
+        let result = |values: [f64; 2]| -> HashMap<String, Number> {
+            ["dout0_0", "dout0_1"].into_iter().zip(values).map(|(name, value)| (name.to_string(), Number::from_f64(value))).collect()
+        };
+
+        assert!(PeriodCharz::extract_pass(&target, &result([1.0, 0.1]), v!(1.1)));
+        assert!(!PeriodCharz::extract_pass(&target, &result([0.5, 0.1]), v!(1.1)));
+        assert!(!PeriodCharz::extract_pass(&target, &result([1.0, 0.6]), v!(1.1)));
+        assert!(!PeriodCharz::extract_pass(&target, &HashMap::new(), v!(1.1)));
+
+        // reads resolve from 2ns on
+        let min_period = bisect(0.5e-9, 10e-9, DEFAULT_RESOLUTION, |period| {
+            let values = if period >= 2e-9 { [1.0, 0.1] } else { [0.1, 1.0] };
+            Ok(PeriodCharz::extract_pass(&target, &result(values), v!(1.1)))
+        }).unwrap();
+        let min_period = PeriodCharz::extract_result(min_period, t!(10 n)).unwrap().value().to_f64();
+        assert!(min_period >= 2e-9 && min_period - 2e-9 <= DEFAULT_RESOLUTION);
+
+        assert!(PeriodCharz::extract_result(None, t!(10 n)).is_err());
+    }
+}
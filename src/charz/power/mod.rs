@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use reda_unit::{Capacitance, Energy, Number, Time, Voltage};
+use tracing::{debug, info};
+use crate::{pdk::{Enviroment, Pvt}, simulate::{CircuitSimulator, EnergyMeasBuilder, JobContext, SimulateError}, YouRAMResult};
+use super::{CharzBench, CharzError, CharzSetup, SramTransactionGenerator};
+
+/// Energy drawn from all supplies by one clock cycle of each operation
+#[derive(Debug, Clone)]
+pub struct PowerCharzResult {
+    /// Read driving all `dout` from 0 to 1, the energy to charge the output load excluded
+    pub read_rise: Energy,
+    /// Read driving all `dout` from 1 to 0
+    pub read_fall: Energy,
+    pub write: Energy,
+    /// Clock with chip deselected
+    pub deselect: Energy,
+    /// One `addr` bit rising with chip deselected, a deselected cycle excluded
+    pub address_rise: Energy,
+    /// One `addr` bit falling with chip deselected, a deselected cycle excluded
+    pub address_fall: Energy,
+    /// One `din` bit rising with chip deselected, a deselected cycle excluded
+    pub data_input_rise: Energy,
+    /// One `din` bit falling with chip deselected, a deselected cycle excluded
+    pub data_input_fall: Energy,
+}
+
+/// Operation measured in a transaction of `PowerCharz`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PowerOperation {
+    ReadRise,
+    ReadFall,
+    Write,
+    Deselect,
+    AddressRise,
+    AddressFall,
+    DataInputRise,
+    DataInputFall,
+}
+
+/// Dynamic energy charz for Sram
+///
+/// The energy of an operation is the charge drawn through every supply times its voltage
+/// over one period, see `EnergyMeas` and `CircuitSimulator::supply_probes`. Reads and writes
+/// are measured from their clock rise, so a slow `dout` is still counted. Deselected cycles
+/// are measured from their begin, where `addr` and `din` change.
+///
+/// `dout` rising also charges the output load from vdd, `C * V^2` of each bit is subtracted
+/// as it is not internal to the sram. Input pin energy is the toggle of all bits of a bus in
+/// a deselected cycle beyond a quiet one, shared by each bit
+///
+/// # Deafult:
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - circuit_path: "./temp/<sram_name>.sp"
+/// - jobs: number of cpu
+///
+/// # Example
+///
+/// ```ignore
+/// let result = PowerCharz::config()
+///     .sram(sram)
+///     .pdk(pdk)
+///     .pvt(pvt)
+///     .input_net_transitions(&transitions)
+///     .output_net_capacitances(&capacitances)
+///     .period(t!(10 n))
+///     .analyze()?;
+/// ```
+#[derive(Default)]
+pub struct PowerCharz<'a> {
+    pub setup: CharzSetup,
+    pub period: Option<Time>,
+    pub pvt: Option<Pvt>,
+    pub input_net_transitions: Option<&'a [Time]>,
+    pub output_net_capacitances: Option<&'a [Capacitance]>,
+
+    /// Max concurrent simulations
+    pub jobs: Option<usize>,
+}
+
+impl<'a> PowerCharz<'a> {
+    pub fn analyze(self) -> YouRAMResult<Vec<Vec<PowerCharzResult>>> {
+        info!("execute power charz");
+
+        debug!("extract arguments");
+        let period = self.period.ok_or(CharzError::LackFunctionTestConfigField("period"))?;
+        let pvt = self.pvt.ok_or(CharzError::LackFunctionTestConfigField("pvt"))?;
+        let input_net_transitions = self.input_net_transitions.ok_or(CharzError::LackFunctionTestConfigField("input_net_transitions"))?;
+        let output_net_capacitances = self.output_net_capacitances.ok_or(CharzError::LackFunctionTestConfigField("output_net_capacitances"))?;
+        let bench = self.setup.build()?;
+
+        let mut envs = vec![];
+        for &input_net_transition in input_net_transitions.iter() {
+            for &output_net_capacitance in output_net_capacitances.iter() {
+                envs.push(bench.env(pvt.clone(), input_net_transition, output_net_capacitance));
+            }
+        }
+
+        let results = bench.runner(self.jobs).run("power", envs, |env, job| {
+            Self::analyze_in_env(&bench, period, env, job)
+        })?;
+
+        // back to [input_net_transition][output_net_capacitance]
+        let mut all_result = vec![];
+        let mut results = results.into_iter();
+        for _ in input_net_transitions.iter() {
+            all_result.push(results.by_ref().take(output_net_capacitances.len()).collect());
+        }
+
+        Ok(all_result)
+    }
+
+    fn analyze_in_env(bench: &CharzBench, period: Time, env: Enviroment, job: &JobContext) -> YouRAMResult<PowerCharzResult> {
+        let mut transactions = SramTransactionGenerator::new(bench.sram.clone(), period);
+        let operations = Self::generate_transactions(&mut transactions);
+        let supplies = CircuitSimulator::supply_probes(&bench.sram.clone().into(), &env);
+
+        for &(index, operation) in operations.iter() {
+            let from = match operation {
+                PowerOperation::ReadRise | PowerOperation::ReadFall | PowerOperation::Write => transactions.clock_rise_time(index),
+                _ => transactions.clock_begin(index),
+            };
+            for (probe_name, voltage, _) in supplies.iter() {
+                let meas = EnergyMeasBuilder::default()
+                    .name(Self::meas_name(operation, index, probe_name))
+                    .probe_name(probe_name.clone())
+                    .voltage(*voltage)
+                    .from(from)
+                    .to(from + period)
+                    .build().unwrap();
+                transactions.add_measurement(meas);
+            }
+        }
+
+        // each rising dout bit charges its load from vdd
+        let load_energy = env.output_load().value().to_f64() * env.voltage().value().to_f64().powi(2);
+        let widths = (bench.sram.read().address_width(), bench.sram.read().word_width());
+
+        debug!("spice simulate");
+        let result = transactions.simulate(env, bench.pdk.clone(), &bench.command, job.simulate_path(), Some(&bench.circuit_path), job.work_folder())?;
+
+        debug!("extract power result");
+        Self::extract_result(&result, &operations, &supplies, widths, load_energy)
+    }
+
+    /// Two cycles of each operation, return the transaction index of them
+    fn generate_transactions(transactions: &mut SramTransactionGenerator) -> Vec<(usize, PowerOperation)> {
+        let ones = transactions.max_word();
+        let (a, b) = (1, 0);
+        let mut operations = vec![];
+        let mut add = |transactions: &mut SramTransactionGenerator, operation: PowerOperation| {
+            operations.push((transactions.transaction_size() - 1, operation));
+        };
+
+        transactions.add_write_transaction(a, ones);
+        transactions.add_write_transaction(b, 0);
+        // leave all dout at 0
+        transactions.add_read_transaction(b);
+
+        transactions.add_read_transaction(a);
+        add(transactions, PowerOperation::ReadRise);
+        transactions.add_read_transaction(b);
+        add(transactions, PowerOperation::ReadFall);
+        transactions.add_read_transaction(a);
+        add(transactions, PowerOperation::ReadRise);
+        transactions.add_read_transaction(b);
+        add(transactions, PowerOperation::ReadFall);
+
+        // flip all bits of both words
+        transactions.add_write_transaction(a, 0);
+        add(transactions, PowerOperation::Write);
+        transactions.add_write_transaction(b, ones);
+        add(transactions, PowerOperation::Write);
+
+        // csb rises, then quiet deselected cycles
+        transactions.add_idle_transaction(b, ones);
+        transactions.add_idle_transaction(b, ones);
+        add(transactions, PowerOperation::Deselect);
+        transactions.add_idle_transaction(b, ones);
+        add(transactions, PowerOperation::Deselect);
+
+        // toggle all bits of addr, then of din
+        transactions.add_idle_transaction(transactions.max_address(), ones);
+        add(transactions, PowerOperation::AddressRise);
+        transactions.add_idle_transaction(b, ones);
+        add(transactions, PowerOperation::AddressFall);
+        transactions.add_idle_transaction(b, 0);
+        add(transactions, PowerOperation::DataInputFall);
+        transactions.add_idle_transaction(b, ones);
+        add(transactions, PowerOperation::DataInputRise);
+
+        // the last measured cycle ends before it
+        transactions.add_read_transaction(a);
+
+        operations
+    }
+
+    fn meas_name(operation: PowerOperation, index: usize, probe_name: &str) -> String {
+        let operation = match operation {
+            PowerOperation::ReadRise => "read_rise",
+            PowerOperation::ReadFall => "read_fall",
+            PowerOperation::Write => "write",
+            PowerOperation::Deselect => "deselect",
+            PowerOperation::AddressRise => "address_rise",
+            PowerOperation::AddressFall => "address_fall",
+            PowerOperation::DataInputRise => "data_input_rise",
+            PowerOperation::DataInputFall => "data_input_fall",
+        };
+        format!("energy_{}_t{}_{}", operation, index, probe_name.to_ascii_lowercase())
+    }
+
+    /// Average energy of each operation, summed over `supplies` with the sign of current.
+    /// `widths` are the `addr` and `dout` widths, `load_energy` is drawn by each rising `dout`
+    fn extract_result(
+        result: &HashMap<String, Number>,
+        operations: &[(usize, PowerOperation)],
+        supplies: &[(String, Voltage, f64)],
+        (address_width, word_width): (usize, usize),
+        load_energy: f64,
+    ) -> YouRAMResult<PowerCharzResult> {
+        let mut energies = vec![];
+        for &(index, operation) in operations.iter() {
+            let mut energy = 0.0;
+            for (probe_name, _, sign) in supplies.iter() {
+                let name = Self::meas_name(operation, index, probe_name);
+                let value = result.get(&name)
+                    .ok_or_else(|| SimulateError::MeasFailed(name.clone()))?;
+                energy += sign * value.to_f64();
+            }
+            energies.push((operation, energy));
+        }
+
+        let average = |operation: PowerOperation| -> f64 {
+            let values: Vec<f64> = energies.iter()
+                .filter(|(o, _)| *o == operation)
+                .map(|(_, energy)| *energy)
+                .collect();
+            if values.is_empty() {
+                return 0.0;
+            }
+            values.iter().sum::<f64>() / values.len() as f64
+        };
+
+        let deselect = average(PowerOperation::Deselect);
+        let per_bit = |operation: PowerOperation, width: usize| -> Energy {
+            Energy::from((average(operation) - deselect) / width.max(1) as f64)
+        };
+
+        Ok(PowerCharzResult {
+            read_rise: Energy::from(average(PowerOperation::ReadRise) - load_energy * word_width as f64),
+            read_fall: Energy::from(average(PowerOperation::ReadFall)),
+            write: Energy::from(average(PowerOperation::Write)),
+            deselect: Energy::from(deselect),
+            address_rise: per_bit(PowerOperation::AddressRise, address_width),
+            address_fall: per_bit(PowerOperation::AddressFall, address_width),
+            data_input_rise: per_bit(PowerOperation::DataInputRise, word_width),
+            data_input_fall: per_bit(PowerOperation::DataInputFall, word_width),
+        })
+    }
+}
+
+impl_charz_setup!(PowerCharz<'_>);
+
+impl<'a> PowerCharz<'a> {
+    pub fn config() -> Self {
+        Self::default()
+    }
+
+    pub fn period(self, period: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.period = Some(period.into());
+        build
+    }
+
+    pub fn pvt(self, pvt: impl Into<Pvt>) -> Self {
+        let mut build = self;
+        build.pvt = Some(pvt.into());
+        build
+    }
+
+    pub fn input_net_transitions(self, input_net_transitions: &'a [Time]) -> Self {
+        let mut build = self;
+        build.input_net_transitions = Some(input_net_transitions);
+        build
+    }
+
+    pub fn output_net_capacitances(self, output_net_capacitances: &'a [Capacitance]) -> Self {
+        let mut build = self;
+        build.output_net_capacitances = Some(output_net_capacitances);
+        build
+    }
+
+    /// Max concurrent simulations, 0 means number of cpu
+    pub fn jobs(self, jobs: usize) -> Self {
+        let mut build = self;
+        build.jobs = Some(jobs);
+        build
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_unit::v;
+    use super::*;
+
+    #[test]
+    fn test_extract_result() {
+        let operations = [
+            (3, PowerOperation::ReadRise),
+            (5, PowerOperation::ReadRise),
+            (4, PowerOperation::ReadFall),
+            (7, PowerOperation::Write),
+            (9, PowerOperation::Deselect),
+            (11, PowerOperation::AddressRise),
+            (12, PowerOperation::AddressFall),
+            (13, PowerOperation::DataInputFall),
+            (14, PowerOperation::DataInputRise),
+        ];
+        let supplies = [
+            (CircuitSimulator::VDD_PROBE_NAME.to_string(), v!(1.1), 1.0),
+            ("vdd_array".to_string(), v!(0.9), -1.0),
+        ];
+        let result: HashMap<String, Number> = [
+            ("energy_read_rise_t3_vdd_probe", 2e-12),
+            ("energy_read_rise_t5_vdd_probe", 4e-12),
+            ("energy_read_fall_t4_vdd_probe", 1e-12),
+            ("energy_write_t7_vdd_probe", 5e-12),
+            ("energy_deselect_t9_vdd_probe", 0.5e-12),
+            ("energy_address_rise_t11_vdd_probe", 0.9e-12),
+            ("energy_address_fall_t12_vdd_probe", 0.7e-12),
+            ("energy_data_input_fall_t13_vdd_probe", 0.6e-12),
+            ("energy_data_input_rise_t14_vdd_probe", 0.5e-12),
+            // vdd_array source delivers its charge out of circuit
+            ("energy_read_rise_t3_vdd_array", -1e-12),
+            ("energy_read_rise_t5_vdd_array", -1e-12),
+            ("energy_read_fall_t4_vdd_array", -1e-12),
+            ("energy_write_t7_vdd_array", -2e-12),
+            ("energy_deselect_t9_vdd_array", 0.0),
+            ("energy_address_rise_t11_vdd_array", 0.0),
+            ("energy_address_fall_t12_vdd_array", 0.0),
+            ("energy_data_input_fall_t13_vdd_array", 0.0),
+            ("energy_data_input_rise_t14_vdd_array", -0.2e-12),
+            // the charge meas of energy
+            ("energy_write_t7_vdd_probe_charge", 4.5e-12),
+        ].into_iter().map(|(name, value)| (name.to_string(), Number::from_f64(value))).collect();
+
+        // 2 address bits, 4 dout bits loaded by 0.1pJ each
+        let power = PowerCharz::extract_result(&result, &operations, &supplies, (2, 4), 0.1e-12).unwrap();
+        let assert_energy = |energy: Energy, expected: f64| assert!((energy.value().to_f64() - expected).abs() < 1e-24, "{} != {}", energy, expected);
+        assert_energy(power.read_rise, 4e-12 - 0.4e-12);
+        assert_energy(power.read_fall, 2e-12);
+        assert_energy(power.write, 7e-12);
+        assert_energy(power.deselect, 0.5e-12);
+        assert_energy(power.address_rise, 0.2e-12);
+        assert_energy(power.address_fall, 0.1e-12);
+        assert_energy(power.data_input_fall, 0.025e-12);
+        assert_energy(power.data_input_rise, 0.05e-12);
+
+        // a supply without its meas fails
+        let supplies = [("vdd_periphery".to_string(), v!(1.1), -1.0)];
+        assert!(PowerCharz::extract_result(&result, &operations, &supplies, (2, 4), 0.0).is_err());
+        assert_eq!(PowerCharz::meas_name(PowerOperation::Write, 7, "VDD_PROBE"), "energy_write_t7_vdd_probe");
+    }
+}
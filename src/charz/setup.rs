@@ -0,0 +1,168 @@
+use std::{cmp::Ordering, collections::BTreeMap, path::PathBuf, sync::Arc};
+use reda_unit::{Capacitance, Time, Voltage};
+use crate::{circuit::{Shr, Sram}, export, pdk::{Enviroment, Pdk, Pvt}, simulate::{JobRunner, NgSpice, SpiceCommand}, ErrorContext, YouRAMResult};
+use super::CharzError;
+
+/// Configs every sram charz shares, its builder methods are added by `impl_charz_setup!`
+///
+/// # Deafult:
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - circuit_path: "./temp/<sram_name>.sp"
+pub struct CharzSetup {
+    pub sram: Option<Shr<Sram>>,
+    pub pdk: Option<Arc<Pdk>>,
+
+    /// Supply ports(e.g. `vdd_array`) not driven by pvt voltage
+    pub supply_voltages: BTreeMap<String, Voltage>,
+
+    pub command: Option<Box<dyn SpiceCommand>>,
+
+    pub temp_folder: Option<PathBuf>,
+    /// if no circuit_path, create <temp>/<sram_name>.sp. if have, include it directiontly
+    pub circuit_path: Option<PathBuf>,
+}
+
+/// `CharzSetup` with all fields given and the sram written
+pub struct CharzBench {
+    pub sram: Shr<Sram>,
+    pub pdk: Arc<Pdk>,
+    pub supply_voltages: BTreeMap<String, Voltage>,
+    pub command: Box<dyn SpiceCommand>,
+    pub temp_folder: PathBuf,
+    pub circuit_path: PathBuf,
+}
+
+impl CharzSetup {
+    pub fn build(self) -> YouRAMResult<CharzBench> {
+        let sram = self.sram.ok_or(CharzError::LackFunctionTestConfigField("sram"))?;
+        let pdk = self.pdk.ok_or(CharzError::LackFunctionTestConfigField("pdk"))?;
+        let command = self.command.ok_or(CharzError::LackFunctionTestConfigField("command"))?;
+
+        let temp_folder = self.temp_folder.unwrap_or_else(|| "./temp".into());
+        let circuit_path = match self.circuit_path {
+            Some(circuit_path) => circuit_path,
+            None => {
+                let circuit_path = temp_folder.join(sram.read().name.to_string());
+                export::write_simulation_spice(sram.clone(), &circuit_path, &pdk).context("write sram")?;
+                circuit_path
+            }
+        };
+
+        Ok(CharzBench { sram, pdk, supply_voltages: self.supply_voltages, command, temp_folder, circuit_path })
+    }
+}
+
+impl Default for CharzSetup {
+    fn default() -> Self {
+        Self {
+            sram: None,
+            pdk: None,
+            supply_voltages: BTreeMap::new(),
+            command: Some(Box::new(NgSpice)),
+            temp_folder: Some("./temp".into()),
+            circuit_path: None,
+        }
+    }
+}
+
+impl CharzBench {
+    /// Enviroment with the supply voltages
+    pub fn env(&self, pvt: Pvt, input_net_transition: Time, output_net_capacitance: Capacitance) -> Enviroment {
+        Enviroment::new(pvt, input_net_transition, output_net_capacitance)
+            .with_supply_voltages(self.supply_voltages.clone())
+    }
+
+    /// Load of outputs when the charz has no output index, 0 if pdk has no default fanout load
+    pub fn output_load(&self) -> Capacitance {
+        self.pdk.default_fanout_load().unwrap_or(0.0.into())
+    }
+
+    /// `input_net_transition`, or the largest timing input net transition of pdk
+    pub fn largest_input_net_transition(&self, input_net_transition: Option<Time>) -> YouRAMResult<Time> {
+        self.pick_input_net_transition(input_net_transition, Ordering::Greater)
+    }
+
+    /// `input_net_transition`, or the smallest timing input net transition of pdk
+    pub fn smallest_input_net_transition(&self, input_net_transition: Option<Time>) -> YouRAMResult<Time> {
+        self.pick_input_net_transition(input_net_transition, Ordering::Less)
+    }
+
+    /// Run jobs in `<temp>/<job_name>_<i>`, `jobs` 0 or None means number of cpu
+    pub fn runner(&self, jobs: Option<usize>) -> JobRunner {
+        let runner = JobRunner::new(&self.temp_folder);
+        match jobs {
+            Some(jobs) => runner.jobs(jobs),
+            None => runner,
+        }
+    }
+
+    /// The pdk transition that is `ordering` to all others
+    fn pick_input_net_transition(&self, input_net_transition: Option<Time>, ordering: Ordering) -> YouRAMResult<Time> {
+        if let Some(input_net_transition) = input_net_transition {
+            return Ok(input_net_transition);
+        }
+        let picked = self.pdk.timing_input_net_transitions().iter().copied()
+            .reduce(|picked, t| if t.value().to_f64().total_cmp(&picked.value().to_f64()) == ordering { t } else { picked })
+            .ok_or(CharzError::LackFunctionTestConfigField("input_net_transition"))?;
+        Ok(picked)
+    }
+}
+
+/// Builder methods of the `CharzSetup` in field `setup`
+macro_rules! impl_charz_setup {
+    ($charz:ty) => {
+        impl_charz_simulate!($charz);
+
+        impl $charz {
+            pub fn sram(mut self, sram: impl Into<$crate::circuit::Shr<$crate::circuit::Sram>>) -> Self {
+                self.setup.sram = Some(sram.into());
+                self
+            }
+
+            pub fn supply_voltage(mut self, port_name: impl Into<String>, voltage: impl Into<reda_unit::Voltage>) -> Self {
+                self.setup.supply_voltages.insert(port_name.into(), voltage.into());
+                self
+            }
+
+            pub fn supply_voltages(mut self, supply_voltages: std::collections::BTreeMap<String, reda_unit::Voltage>) -> Self {
+                self.setup.supply_voltages.extend(supply_voltages);
+                self
+            }
+
+            pub fn circuit_path(mut self, circuit_path: impl Into<std::path::PathBuf>) -> Self {
+                self.setup.circuit_path = Some(circuit_path.into());
+                self
+            }
+        }
+    };
+}
+
+/// Builder methods of `pdk`, `command` and `temp_folder` in field `setup`,
+/// for charz simulating circuits other than the sram
+macro_rules! impl_charz_simulate {
+    ($charz:ty) => {
+        impl $charz {
+            pub fn pdk(mut self, pdk: std::sync::Arc<$crate::pdk::Pdk>) -> Self {
+                self.setup.pdk = Some(pdk);
+                self
+            }
+
+            pub fn command<T: $crate::simulate::SpiceCommand + 'static>(mut self, command: impl Into<Box<T>>) -> Self {
+                let command: Box<T> = command.into();
+                self.setup.command = Some(command);
+                self
+            }
+
+            pub fn command_box(mut self, command: Box<dyn $crate::simulate::SpiceCommand>) -> Self {
+                self.setup.command = Some(command);
+                self
+            }
+
+            pub fn temp_folder(mut self, temp_folder: impl Into<std::path::PathBuf>) -> Self {
+                self.setup.temp_folder = Some(temp_folder.into());
+                self
+            }
+        }
+    };
+}
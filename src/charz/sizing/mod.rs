@@ -0,0 +1,561 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::Arc};
+use reda_sp::{Spice, Subckt, ToSpice};
+use reda_unit::{v, Capacitance, Length, Number, Time, Voltage};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use crate::{
+    circuit::{Design, Leafcell, Shr},
+    export,
+    pdk::{BitcellSizing, Enviroment, LeafcellGenerator, LeafcellSizing, Pdk, PdkCells, Process, SenseAmpSizing},
+    simulate::{CircuitSimulator, CurrentStatistic, DelayMeasBuilder, Edge, FindWhenMeas, FindWhenMeasBuilder, NgSpice, SpiceCommand, SupplyCurrentMeasBuilder, VoltageAtMeas},
+    ErrorContext, YouRAMResult
+};
+use super::CharzError;
+
+/// Candidate widths of bitcell, an empty vector keeps the base width
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BitcellSweep {
+    #[serde(default)]
+    pub pull_down: Vec<Length>,
+    #[serde(default)]
+    pub pull_up: Vec<Length>,
+    #[serde(default)]
+    pub access: Vec<Length>,
+}
+
+/// Candidate widths of sense amp, an empty vector keeps the base width
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SenseAmpSweep {
+    #[serde(default)]
+    pub latch_pmos: Vec<Length>,
+    #[serde(default)]
+    pub latch_nmos: Vec<Length>,
+    #[serde(default)]
+    pub enable: Vec<Length>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SizingConstraints {
+    #[serde(default)]
+    pub min_read_margin: Option<Voltage>,
+    #[serde(default)]
+    pub min_write_margin: Option<Voltage>,
+    #[serde(default)]
+    pub max_sense_delay: Option<Time>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BitcellMetrics {
+    /// Distance from the disturbed '0' storage node to vdd/2 during a read with
+    /// both bitlines clamped at vdd, a transient estimation of read SNM
+    pub read_margin: Voltage,
+    /// Highest bitline voltage that still flips the cell
+    pub write_margin: Voltage,
+    /// Charge to drive `wl` from 0 to vdd, divided by vdd
+    pub wordline_capacitance: Capacitance,
+}
+
+#[derive(Debug, Clone)]
+pub struct SenseAmpMetrics {
+    /// Delay from `en` rising to `dout` resolved
+    pub sense_delay: Time,
+}
+
+#[derive(Debug, Clone)]
+pub struct SizingResult {
+    pub sizing: LeafcellSizing,
+    pub bitcell: BitcellMetrics,
+    pub sense_amp: SenseAmpMetrics,
+}
+
+impl SizingResult {
+    /// Write all leafcells in cdl, can be used as `leafcell_spice` of pdk
+    pub fn write_leafcell(&self, path: impl AsRef<Path>) -> YouRAMResult<()> {
+        let path = path.as_ref();
+        info!("write optimized leafcells to {:?}", path);
+        let content = LeafcellGenerator::new(&self.sizing).spice().subckts.iter()
+            .map(|subckt| subckt.to_spice())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        std::fs::write(path, content + "\n").context("write leafcell")?;
+        Ok(())
+    }
+
+    /// Write the sizing in json, can be used as `leafcell_sizing` of pdk
+    pub fn write_sizing(&self, path: impl AsRef<Path>) -> YouRAMResult<()> {
+        let content = serde_json::to_string_pretty(&self.sizing).context("serialize sizing")?;
+        std::fs::write(path.as_ref(), content).context("write sizing")?;
+        Ok(())
+    }
+}
+
+/// Sweep leafcell transistor sizes and pick the smallest one meeting the constraints
+///
+/// Bitcell and sense amp are optimized independently, each candidate is evaluated by a spice simulation
+///
+/// # Deafult:
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - phase: 1ns
+/// - sense_differential: 0.1v
+///
+/// # Example
+///
+/// ```ignore
+/// let result = SizingOptimizer::config()
+///     .pdk(pdk)
+///     .env(env)
+///     .sizing(base_sizing)
+///     .bitcell_sweep(sweep)
+///     .constraints(constraints)
+///     .optimize()?;
+/// result.write_leafcell("leafcell.cdl")?;
+/// ```
+pub struct SizingOptimizer {
+    pub pdk: Option<Arc<Pdk>>,
+    pub env: Option<Enviroment>,
+    pub sizing: Option<LeafcellSizing>,
+
+    pub bitcell_sweep: BitcellSweep,
+    pub sense_amp_sweep: SenseAmpSweep,
+    pub constraints: SizingConstraints,
+
+    /// Duration of each operation in test bench
+    pub phase: Time,
+    /// Bitline differential when sense amp is enabled
+    pub sense_differential: Voltage,
+
+    pub command: Option<Box<dyn SpiceCommand>>,
+    pub temp_folder: Option<PathBuf>,
+}
+
+impl SizingOptimizer {
+    pub fn optimize(self) -> YouRAMResult<SizingResult> {
+        info!("execute leafcell sizing optimize");
+
+        debug!("extract arguments");
+        let pdk = self.pdk.ok_or(CharzError::LackFunctionTestConfigField("pdk"))?;
+        let env = self.env.ok_or(CharzError::LackFunctionTestConfigField("env"))?;
+        let base = self.sizing.ok_or(CharzError::LackFunctionTestConfigField("sizing"))?
+            .with_default_models(pdk.mos_model_names(Process::TypeType)?);
+        let command = self.command.ok_or(CharzError::LackFunctionTestConfigField("command"))?;
+        let temp_folder = self.temp_folder.unwrap_or_else(|| "./temp".into());
+
+        let bench = SizingBench {
+            pdk, env, command, temp_folder,
+            phase: self.phase,
+            sense_differential: self.sense_differential,
+        };
+
+        // bitcell
+        let mut best_bitcell: Option<(BitcellSizing, BitcellMetrics)> = None;
+        for (index, candidate) in Self::bitcell_candidates(&base.bitcell, &self.bitcell_sweep).into_iter().enumerate() {
+            let mut sizing = base.clone();
+            sizing.bitcell = candidate.clone();
+
+            let metrics = match bench.evaluate_bitcell(&sizing, index) {
+                Ok(metrics) => metrics,
+                Err(e) => {
+                    warn!("bitcell candidate {:?} failed: {}", candidate, e);
+                    continue;
+                }
+            };
+            debug!("bitcell candidate {:?}: {:?}", candidate, metrics);
+
+            if !self.constraints.bitcell_feasible(&metrics) {
+                continue;
+            }
+
+            let better = match &best_bitcell {
+                None => true,
+                Some((best, best_metrics)) => {
+                    let (area, best_area) = (Self::bitcell_area(&candidate), Self::bitcell_area(best));
+                    area < best_area || (area == best_area && metrics.read_margin.to_f64() > best_metrics.read_margin.to_f64())
+                }
+            };
+            if better {
+                best_bitcell = Some((candidate, metrics));
+            }
+        }
+        let (bitcell, bitcell_metrics) = best_bitcell.ok_or(CharzError::NoFeasibleSizing("bitcell"))?;
+
+        // sense amp
+        let mut best_sense_amp: Option<(SenseAmpSizing, SenseAmpMetrics)> = None;
+        for (index, candidate) in Self::sense_amp_candidates(&base.sense_amp, &self.sense_amp_sweep).into_iter().enumerate() {
+            let mut sizing = base.clone();
+            sizing.sense_amp = candidate.clone();
+
+            let metrics = match bench.evaluate_sense_amp(&sizing, index) {
+                Ok(metrics) => metrics,
+                Err(e) => {
+                    warn!("sense amp candidate {:?} failed: {}", candidate, e);
+                    continue;
+                }
+            };
+            debug!("sense amp candidate {:?}: {:?}", candidate, metrics);
+
+            if !self.constraints.sense_amp_feasible(&metrics) {
+                continue;
+            }
+
+            let better = match &best_sense_amp {
+                None => true,
+                Some((best, best_metrics)) => {
+                    let (area, best_area) = (Self::sense_amp_area(&candidate), Self::sense_amp_area(best));
+                    area < best_area || (area == best_area && metrics.sense_delay.to_f64() < best_metrics.sense_delay.to_f64())
+                }
+            };
+            if better {
+                best_sense_amp = Some((candidate, metrics));
+            }
+        }
+        let (sense_amp, sense_amp_metrics) = best_sense_amp.ok_or(CharzError::NoFeasibleSizing("sense amp"))?;
+
+        info!("optimized bitcell: {:?}, {:?}", bitcell, bitcell_metrics);
+        info!("optimized sense amp: {:?}, {:?}", sense_amp, sense_amp_metrics);
+
+        let mut sizing = base;
+        sizing.bitcell = bitcell;
+        sizing.sense_amp = sense_amp;
+        sizing.bitcell_wordline_capacitance = Some(bitcell_metrics.wordline_capacitance);
+
+        Ok(SizingResult { sizing, bitcell: bitcell_metrics, sense_amp: sense_amp_metrics })
+    }
+
+    fn bitcell_candidates(base: &BitcellSizing, sweep: &BitcellSweep) -> Vec<BitcellSizing> {
+        let mut candidates = vec![];
+        for &pull_down in Self::sweep_values(&sweep.pull_down, base.pull_down).iter() {
+            for &pull_up in Self::sweep_values(&sweep.pull_up, base.pull_up).iter() {
+                for &access in Self::sweep_values(&sweep.access, base.access).iter() {
+                    candidates.push(BitcellSizing { pull_down, pull_up, access });
+                }
+            }
+        }
+        candidates
+    }
+
+    fn sense_amp_candidates(base: &SenseAmpSizing, sweep: &SenseAmpSweep) -> Vec<SenseAmpSizing> {
+        let mut candidates = vec![];
+        for &latch_pmos in Self::sweep_values(&sweep.latch_pmos, base.latch_pmos).iter() {
+            for &latch_nmos in Self::sweep_values(&sweep.latch_nmos, base.latch_nmos).iter() {
+                for &enable in Self::sweep_values(&sweep.enable, base.enable).iter() {
+                    candidates.push(SenseAmpSizing { latch_pmos, latch_nmos, enable, ..base.clone() });
+                }
+            }
+        }
+        candidates
+    }
+
+    fn sweep_values(values: &[Length], base: Length) -> Vec<Length> {
+        if values.is_empty() { vec![base] } else { values.to_vec() }
+    }
+
+    /// Total gate width, as area estimation
+    fn bitcell_area(sizing: &BitcellSizing) -> f64 {
+        2.0 * (sizing.pull_down.to_f64() + sizing.pull_up.to_f64() + sizing.access.to_f64())
+    }
+
+    fn sense_amp_area(sizing: &SenseAmpSizing) -> f64 {
+        2.0 * (sizing.latch_pmos.to_f64() + sizing.latch_nmos.to_f64() + sizing.access.to_f64())
+            + sizing.enable.to_f64()
+            + sizing.buffer_pmos.to_f64() + sizing.buffer_nmos.to_f64()
+            + sizing.output_pmos.to_f64() + sizing.output_nmos.to_f64()
+    }
+}
+
+impl SizingConstraints {
+    pub fn bitcell_feasible(&self, metrics: &BitcellMetrics) -> bool {
+        self.min_read_margin.is_none_or(|min| metrics.read_margin.to_f64() >= min.to_f64()) &&
+        self.min_write_margin.is_none_or(|min| metrics.write_margin.to_f64() >= min.to_f64())
+    }
+
+    pub fn sense_amp_feasible(&self, metrics: &SenseAmpMetrics) -> bool {
+        self.max_sense_delay.is_none_or(|max| metrics.sense_delay.to_f64() <= max.to_f64())
+    }
+}
+
+/// Test benches of leafcells
+struct SizingBench {
+    pdk: Arc<Pdk>,
+    env: Enviroment,
+    command: Box<dyn SpiceCommand>,
+    temp_folder: PathBuf,
+    phase: Time,
+    sense_differential: Voltage,
+}
+
+impl SizingBench {
+    const STEPS_PER_PHASE: f64 = 200.0;
+
+    /*
+        phase:   | write 0 | hold  | read  | write 1 by ramping br |
+        wl:      ‾‾‾‾‾‾‾‾‾‾|_______|‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾
+        bl:      __________|‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾
+        br:      ‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\___________
+
+        read margin: vdd/2 - v(Q) at the end of read
+        write margin: v(br) when v(Q_bar) falls to vdd/2
+        wordline capacitance: charge through wl source in read / vdd
+    */
+    fn evaluate_bitcell(&self, sizing: &LeafcellSizing, index: usize) -> YouRAMResult<BitcellMetrics> {
+        let subckt = LeafcellGenerator::new(sizing).bitcell();
+        let bitcell = Self::leafcell(subckt, PdkCells::extract_bitcell)?;
+        let name = format!("bitcell_{}", index);
+        let mut simulator = self.create_simulator(bitcell.clone(), &name)?;
+
+        let vdd = self.env.voltage();
+        let phase = self.phase;
+        let slew = self.env.input_slew();
+        let (bl, br, wl, q, q_bar) = {
+            let cell = bitcell.read();
+            let ports: Vec<String> = cell.ports().iter().map(|p| p.read().name.to_string()).collect();
+            let cell_name = cell.name().to_string();
+            (ports[0].clone(), ports[1].clone(), ports[2].clone(), Self::internal_net(&cell_name, "Q"), Self::internal_net(&cell_name, "Q_bar"))
+        };
+
+        simulator.write_pwl_stimulate(&wl, [
+            (phase * 0.0, vdd), (phase, vdd), (phase + slew, v!(0)),
+            (phase * 2.0, v!(0)), (phase * 2.0 + slew, vdd)
+        ].into_iter())?;
+        simulator.write_pwl_stimulate(&bl, [
+            (phase * 0.0, v!(0)), (phase, v!(0)), (phase + slew, vdd)
+        ].into_iter())?;
+        simulator.write_pwl_stimulate(&br, [
+            (phase * 0.0, vdd), (phase * 3.0, vdd), (phase * 7.0, v!(0))
+        ].into_iter())?;
+
+        let read_meas = VoltageAtMeas::new(format!("read_q_{}", index), q, phase * 3.0 - slew);
+        let write_meas: FindWhenMeas = FindWhenMeasBuilder::default()
+            .name(format!("write_br_{}", index))
+            .find_net_name(br)
+            .when_net_name(q_bar)
+            .when_edge(Edge::Fall)
+            .when_voltage(vdd * 0.5)
+            .time_delay(phase * 3.0)
+            .build().unwrap();
+        // the source delivers charge to wl on rise, so the current into it is negative
+        let wordline_meas = SupplyCurrentMeasBuilder::default()
+            .name(format!("wordline_charge_{}", index))
+            .probe_name(wl)
+            .statistic(CurrentStatistic::Integral)
+            .from(phase * 2.0)
+            .to(phase * 3.0)
+            .build().unwrap();
+        let read_name = read_meas.name.clone();
+        let write_name = write_meas.name.clone();
+        let wordline_name = wordline_meas.name.clone();
+
+        simulator.write_measurement(Box::new(read_meas))?;
+        simulator.write_measurement(Box::new(write_meas))?;
+        simulator.write_measurement(Box::new(wordline_meas))?;
+        simulator.write_trans(phase / Self::STEPS_PER_PHASE, 0.0, phase * 7.0)?;
+
+        let result = simulator.simulate(&self.command, &self.temp_folder)?;
+        let read_q = Self::get_result(&result, &read_name)?;
+        let write_br = Self::get_result(&result, &write_name)?;
+        let wordline_charge = Self::get_result(&result, &wordline_name)?;
+
+        Ok(BitcellMetrics {
+            read_margin: Voltage::from(vdd.to_f64() * 0.5 - read_q),
+            write_margin: Voltage::from(write_br),
+            wordline_capacitance: Capacitance::from(-wordline_charge / vdd.to_f64()),
+        })
+    }
+
+    /*
+        en:      _________|‾‾‾‾‾‾‾‾‾
+        bl:      vdd - sense_differential
+        br:      vdd
+        dout:    ‾‾‾‾‾‾‾‾‾‾‾‾\______
+
+        sense delay: en rise to dout fall
+    */
+    fn evaluate_sense_amp(&self, sizing: &LeafcellSizing, index: usize) -> YouRAMResult<SenseAmpMetrics> {
+        let subckt = LeafcellGenerator::new(sizing).sense_amp();
+        let sense_amp = Self::leafcell(subckt, PdkCells::extract_sense_amp)?;
+        let name = format!("sense_amp_{}", index);
+        let mut simulator = self.create_simulator(sense_amp.clone(), &name)?;
+
+        let vdd = self.env.voltage();
+        let phase = self.phase;
+        let (bl, br, dout, en) = {
+            let cell = sense_amp.read();
+            let ports: Vec<String> = cell.ports().iter().map(|p| p.read().name.to_string()).collect();
+            (ports[0].clone(), ports[1].clone(), ports[2].clone(), ports[3].clone())
+        };
+
+        simulator.write_dc_stimulate(&bl, vdd - self.sense_differential)?;
+        simulator.write_dc_stimulate(&br, vdd)?;
+        simulator.write_pwl_stimulate(&en, [
+            (phase * 0.0, v!(0)), (phase, v!(0)), (phase + self.env.input_slew(), vdd)
+        ].into_iter())?;
+
+        let meas = DelayMeasBuilder::default()
+            .name(format!("sense_delay_{}", index))
+            .trig_net_name(en)
+            .trig_edge(Edge::Rise)
+            .trig_voltage(vdd * 0.5)
+            .trig_time_delay(phase * 0.0)
+            .targ_net_name(dout)
+            .targ_edge(Edge::Fall)
+            .targ_voltage(vdd * 0.5)
+            .targ_time_delay(phase * 0.0)
+            .build().unwrap();
+        let meas_name = meas.name.clone();
+
+        simulator.write_measurement(Box::new(meas))?;
+        simulator.write_trans(phase / Self::STEPS_PER_PHASE, 0.0, phase * 3.0)?;
+
+        let result = simulator.simulate(&self.command, &self.temp_folder)?;
+        let sense_delay = Self::get_result(&result, &meas_name)?;
+
+        Ok(SenseAmpMetrics { sense_delay: Time::from(sense_delay) })
+    }
+
+    fn create_simulator(&self, leafcell: Shr<Leafcell>, name: &str) -> YouRAMResult<CircuitSimulator> {
+        let circuit_path = self.temp_folder.join(format!("{}.sp", name));
+        let simulate_path = self.temp_folder.join(format!("simulate_{}.sp", name));
+        export::write_spice(leafcell.clone(), &circuit_path).with_context(|| format!("write {}", name))?;
+
+        CircuitSimulator::create_with_dialect(
+            leafcell, self.env.clone(), self.pdk.clone(), simulate_path, circuit_path, self.command.dialect())
+    }
+
+    fn leafcell<L, E>(subckt: Subckt, extract: E) -> YouRAMResult<Shr<Leafcell>>
+    where
+        L: Into<Leafcell>,
+        E: Fn(&Spice) -> Result<L, crate::pdk::PdkError>,
+    {
+        let mut spice = Spice::new();
+        spice.subckts.push(subckt);
+        let leafcell: Leafcell = extract(&spice)?.into();
+        Ok(Shr::new(leafcell))
+    }
+
+    /// Net inside the circuit instance written by `CircuitSimulator`
+    fn internal_net(circuit_name: &str, net_name: &str) -> String {
+        format!("X{}.{}", circuit_name, net_name)
+    }
+
+    fn get_result(result: &HashMap<String, Number>, name: &str) -> YouRAMResult<f64> {
+        result.get(name)
+            .map(|value| value.to_f64())
+            .ok_or(crate::YouRAMError::Message(format!("meas '{}' not found", name)))
+    }
+}
+
+impl Default for SizingOptimizer {
+    fn default() -> Self {
+        Self {
+            pdk: None,
+            env: None,
+            sizing: None,
+            bitcell_sweep: BitcellSweep::default(),
+            sense_amp_sweep: SenseAmpSweep::default(),
+            constraints: SizingConstraints::default(),
+            phase: Time::from(1e-9),
+            sense_differential: v!(0.1),
+            command: Some(Box::new(NgSpice)),
+            temp_folder: Some("./temp".into()),
+        }
+    }
+}
+
+impl SizingOptimizer {
+    pub fn config() -> Self {
+        Self::default()
+    }
+
+    pub fn pdk(self, pdk: Arc<Pdk>) -> Self {
+        let mut build = self;
+        build.pdk = Some(pdk);
+        build
+    }
+
+    pub fn env(self, env: impl Into<Enviroment>) -> Self {
+        let mut build = self;
+        build.env = Some(env.into());
+        build
+    }
+
+    pub fn sizing(self, sizing: LeafcellSizing) -> Self {
+        let mut build = self;
+        build.sizing = Some(sizing);
+        build
+    }
+
+    pub fn bitcell_sweep(self, sweep: BitcellSweep) -> Self {
+        let mut build = self;
+        build.bitcell_sweep = sweep;
+        build
+    }
+
+    pub fn sense_amp_sweep(self, sweep: SenseAmpSweep) -> Self {
+        let mut build = self;
+        build.sense_amp_sweep = sweep;
+        build
+    }
+
+    pub fn constraints(self, constraints: SizingConstraints) -> Self {
+        let mut build = self;
+        build.constraints = constraints;
+        build
+    }
+
+    pub fn phase(self, phase: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.phase = phase.into();
+        build
+    }
+
+    pub fn sense_differential(self, sense_differential: impl Into<Voltage>) -> Self {
+        let mut build = self;
+        build.sense_differential = sense_differential.into();
+        build
+    }
+
+    pub fn command<T: SpiceCommand + 'static>(mut self, command: impl Into<Box<T>>) -> Self {
+        let command: Box<T> = command.into();
+        self.command = Some(command);
+        self
+    }
+
+    pub fn command_box(mut self, command: Box<dyn SpiceCommand>) -> Self {
+        self.command = Some(command);
+        self
+    }
+
+    pub fn temp_folder(self, temp_folder: impl Into<PathBuf>) -> Self {
+        let mut build = self;
+        build.temp_folder = Some(temp_folder.into());
+        build
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_unit::Length;
+    use super::*;
+
+    fn length(nm: f64) -> Length {
+        Length::from(nm * 1e-9)
+    }
+
+    #[test]
+    fn test_bitcell_candidates() {
+        let base = BitcellSizing { pull_down: length(205.), pull_up: length(90.), access: length(135.) };
+        let sweep = BitcellSweep {
+            pull_down: vec![length(150.), length(200.), length(250.)],
+            access: vec![length(100.), length(135.)],
+            ..Default::default()
+        };
+
+        let candidates = SizingOptimizer::bitcell_candidates(&base, &sweep);
+        assert_eq!(candidates.len(), 6);
+        assert!(candidates.iter().all(|c| c.pull_up == base.pull_up));
+
+        let constraints = SizingConstraints { min_write_margin: Some(v!(0.2)), ..Default::default() };
+        assert!(constraints.bitcell_feasible(&BitcellMetrics { read_margin: v!(0.1), write_margin: v!(0.3), wordline_capacitance: Capacitance::from(0.2e-15) }));
+        assert!(!constraints.bitcell_feasible(&BitcellMetrics { read_margin: v!(0.1), write_margin: v!(0.1), wordline_capacitance: Capacitance::from(0.2e-15) }));
+    }
+}
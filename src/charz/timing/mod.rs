@@ -1,8 +1,18 @@
 use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}, sync::Arc};
 use reda_unit::{Capacitance, Number, Time};
 use tracing::{debug, info};
-use crate::{circuit::{Shr, Sram}, export, pdk::{Enviroment, Pdk, Pvt}, simulate::{DelayMeasBuilder, Edge, NgSpice, SpiceCommand}, ErrorContext, YouRAMResult};
-use super::{CharzError, SramTransactionGenerator};
+use crate::{circuit::Sram, pdk::{Enviroment, Pdk, Pvt}, simulate::{DelayMeasBuilder, Edge, SpiceCommand}, YouRAMResult};
+use super::{CharzError, CharzSetup, SramTransactionGenerator};
+
+/// How `TimingCharz` picks addresses and reduces their measurements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimingAddressMode {
+    /// Max of the farthest cell in each core, see `AddressDistribution::worst_case_addresses`
+    #[default]
+    WorstCase,
+    /// Average of random addresses
+    RandomAverage,
+}
 
 #[derive(Debug)]
 pub struct TimingCharzResult {
@@ -17,8 +27,10 @@ pub struct TimingCharzResult {
 /// # Deafult:
 /// - command: ngspice
 /// - temp_folder: "./temp"
-/// - simulate_path: "./temp/simulator.sp"
+/// - simulate_path: "./temp/simulate.sp", each job writes `<temp>/simulate_<i>/simulate_<i>.sp`
 /// - circuit_path: "./temp/<sram_name>.sp"
+/// - jobs: number of cpu
+/// - address_mode: worst case
 /// 
 /// # Example
 /// 
@@ -31,21 +43,21 @@ pub struct TimingCharzResult {
 ///     .period(t!(10 n))
 ///     .analyze()?;
 /// ```
+#[derive(Default)]
 pub struct TimingCharz<'a> {
-    pub sram: Option<Shr<Sram>>,
+    pub setup: CharzSetup,
     pub period: Option<Time>,
     pub pvt: Option<Pvt>,
     pub input_net_transitions: Option<&'a [Time]>,
     pub output_net_capacitances: Option<&'a [Capacitance]>,
-    pub pdk: Option<Arc<Pdk>>,
-    
-    pub command: Option<Box<dyn SpiceCommand>>,
 
-    pub temp_folder: Option<PathBuf>,
+    /// Only the file stem is used to name the jobs
     pub simulate_path: Option<PathBuf>,
 
-    /// if no circuit_path, create <temp>/<sram_name>.sp. if have, include it directiontly
-    pub circuit_path: Option<PathBuf>,
+    /// Max concurrent simulations
+    pub jobs: Option<usize>,
+
+    pub address_mode: TimingAddressMode,
 }
 
 impl<'a> TimingCharz<'a> {
@@ -54,44 +66,56 @@ impl<'a> TimingCharz<'a> {
         
         // extract all args
         debug!("extract arguments");
-        let sram = self.sram.ok_or(CharzError::LackFunctionTestConfigField("sram"))?;
         let period = self.period.ok_or(CharzError::LackFunctionTestConfigField("period"))?;
         let pvt = self.pvt.ok_or(CharzError::LackFunctionTestConfigField("pvt"))?;
-        let pdk = self.pdk.ok_or(CharzError::LackFunctionTestConfigField("pdk"))?;
         let input_net_transitions = self.input_net_transitions.ok_or(CharzError::LackFunctionTestConfigField("input_net_transitions"))?;
-        let output_net_capacitances = self.output_net_capacitances.ok_or(CharzError::LackFunctionTestConfigField("input_net_transitions"))?;
-
-        let command = self.command.ok_or(CharzError::LackFunctionTestConfigField("command"))?;
-
-        let temp_folder =  self.temp_folder.unwrap_or_else(|| "./temp".into());
-        let simulate_path = self.simulate_path.unwrap_or_else(|| temp_folder.join("simulate.sp"));
-        let circuit_path = match self.circuit_path {
-            Some(circuit_path) => circuit_path,
-            None => {
-                let circuit_path = temp_folder.join(sram.read().name.to_string());
-                export::write_spice(sram.clone(), &circuit_path).with_context(|| format!("write sram"))?;
-                circuit_path
-            }
-        };
+        let output_net_capacitances = self.output_net_capacitances.ok_or(CharzError::LackFunctionTestConfigField("output_net_capacitances"))?;
+        let bench = self.setup.build()?;
+
+        let simulate_path = self.simulate_path.unwrap_or_else(|| bench.temp_folder.join("simulate.sp"));
+        let job_name = simulate_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "simulate".into());
+        let runner = bench.runner(self.jobs);
 
         // for all input_net_transition and output_net_capacitance
-        let mut all_result = vec![];
+        let mut envs = vec![];
         for &input_net_transition in input_net_transitions.iter() {
-            let mut result_in_same_slew = vec![];
             for &output_net_capacitance in output_net_capacitances.iter() {
-                let env = Enviroment::new(pvt.clone(), input_net_transition, output_net_capacitance);
-                let result = Self::analyze_in_env(
-                    sram.clone(), 
-                    period, 
-                    env, 
-                    pdk.clone(), 
-                    &command, 
-                    &simulate_path, 
-                    &circuit_path, 
-                    &temp_folder
-                )?;
-                result_in_same_slew.push(result);
+                envs.push(bench.env(pvt.clone(), input_net_transition, output_net_capacitance));
             }
+        }
+
+        let address_mode = self.address_mode;
+        let results = runner.run(&job_name, envs, |env, job| {
+            let mut transactions = SramTransactionGenerator::new(bench.sram.clone(), period);
+
+            // generate some unique address
+            debug!("generate transactions");
+            let addresses = Self::generate_address(&mut transactions, address_mode);
+
+            let result = Self::analyze_in_env(
+                transactions, 
+                &addresses, 
+                env, 
+                bench.pdk.clone(), 
+                &bench.command, 
+                job.simulate_path(), 
+                &bench.circuit_path, 
+                job.work_folder(),
+            )?;
+
+            // extract result
+            debug!("extract timing result");
+            Self::extract_result(&result, address_mode)
+        })?;
+
+        // back to [input_net_transition][output_net_capacitance]
+        let mut all_result = vec![];
+        let mut results = results.into_iter();
+        for _ in input_net_transitions.iter() {
+            let result_in_same_slew: Vec<_> = results.by_ref().take(output_net_capacitances.len()).collect();
             all_result.push(result_in_same_slew);
         }
 
@@ -99,21 +123,15 @@ impl<'a> TimingCharz<'a> {
     }
 
     fn analyze_in_env(
-        sram: Shr<Sram>, 
-        period: Time, 
+        mut transactions: SramTransactionGenerator, 
+        addresses: &[usize], 
         env: Enviroment, 
         pdk: Arc<Pdk>,
         command: &impl SpiceCommand,
         simulate_path: impl Into<PathBuf>,
         circuit_path: impl Into<PathBuf>,
         temp_folder: impl AsRef<Path>,
-    ) -> YouRAMResult<TimingCharzResult> {
-        let mut transactions = SramTransactionGenerator::new(sram, period);
-            
-        // generate some unique address
-        debug!("generate transactions");
-        let addresses: HashSet<usize> = Self::generate_random_address(&mut transactions);
-
+    ) -> YouRAMResult<HashMap<String, Number>> {
         // for each address, write 0 + read 0, and add delay and slew meas
         for &address in addresses.iter() {
             transactions.add_write_transaction(address, 0);
@@ -178,11 +196,14 @@ impl<'a> TimingCharz<'a> {
 
         // simulate
         debug!("spice simulate");
-        let result = transactions.simulate(env, pdk, command, simulate_path, Some(circuit_path), temp_folder)?;
+        transactions.simulate(env, pdk, command, simulate_path, Some(circuit_path), temp_folder)
+    }
 
-        // extract result
-        debug!("extract timing result");
-        Self::extract_result(&result)
+    fn generate_address(transactions: &mut SramTransactionGenerator, address_mode: TimingAddressMode) -> Vec<usize> {
+        match address_mode {
+            TimingAddressMode::WorstCase => transactions.sram.read().distribution().worst_case_addresses(),
+            TimingAddressMode::RandomAverage => Self::generate_random_address(transactions).into_iter().collect(),
+        }
     }
 
     fn generate_random_address(transactions: &mut SramTransactionGenerator) -> HashSet<usize> {
@@ -191,6 +212,10 @@ impl<'a> TimingCharz<'a> {
         (0..address_count).map(|_| transactions.random_address()).collect()
     }
 
+    fn max(values: &[Number]) -> Number {
+        values.iter().copied().max().unwrap_or_else(Number::zero)
+    }
+
     fn average(values: &[Number]) -> Number {
         let mut sum = Number::zero();
         let size = values.len();
@@ -200,7 +225,7 @@ impl<'a> TimingCharz<'a> {
         sum / size as f64
     }
 
-    fn extract_result(result: &HashMap<String, Number>) -> YouRAMResult<TimingCharzResult> {
+    fn extract_result(result: &HashMap<String, Number>, address_mode: TimingAddressMode) -> YouRAMResult<TimingCharzResult> {
         let mut delay_hls = vec![];
         let mut slew_hls = vec![];
 
@@ -212,9 +237,10 @@ impl<'a> TimingCharz<'a> {
             }
         }
 
-        // average 
-        let delay_hl = Self::average(&delay_hls);
-        let slew_hl = Self::average(&slew_hls);
+        let (delay_hl, slew_hl) = match address_mode {
+            TimingAddressMode::WorstCase => (Self::max(&delay_hls), Self::max(&slew_hls)),
+            TimingAddressMode::RandomAverage => (Self::average(&delay_hls), Self::average(&slew_hls)),
+        };
 
         Ok(TimingCharzResult { 
             delay_hl: Time::from(delay_hl), 
@@ -225,34 +251,13 @@ impl<'a> TimingCharz<'a> {
     }
 }
 
-impl<'a> Default for TimingCharz<'a> {
-    fn default() -> Self {
-        Self {
-            sram: None,
-            period: None,
-            pdk: None,
-            pvt: None,
-            input_net_transitions: None,
-            output_net_capacitances: None,
-            command: Some(Box::new(NgSpice)),
-            temp_folder: Some("./temp".into()),
-            simulate_path: None, 
-            circuit_path: None,
-        }
-    }
-}
+impl_charz_setup!(TimingCharz<'_>);
 
 impl<'a> TimingCharz<'a> {
     pub fn config() -> Self {
         Self::default()
     }
 
-    pub fn sram(self, sram: impl Into<Shr<Sram>>) -> Self {
-        let mut build = self;
-        build.sram = Some(sram.into());
-        build
-    }
-
     pub fn period(self, period: impl Into<Time>) -> Self {
         let mut build = self;
         build.period = Some(period.into());
@@ -277,38 +282,22 @@ impl<'a> TimingCharz<'a> {
         build
     }
 
-    pub fn pdk(self, pdk: Arc<Pdk>) -> Self {
-        let mut build = self;
-        build.pdk = Some(pdk);
-        build
-    }
-
-    pub fn command<T: SpiceCommand + 'static>(mut self, command: impl Into<Box<T>>) -> Self {
-        let command: Box<T> = command.into();
-        self.command = Some(command);
-        self
-    }
-
-    pub fn command_box(mut self, command: Box<dyn SpiceCommand>) -> Self {
-        self.command = Some(command.into());
-        self
-    }
-
-    pub fn temp_folder(self, temp_folder: impl Into<PathBuf>) -> Self {
+    pub fn simulate_path(self, simulate_path: impl Into<PathBuf>) -> Self {
         let mut build = self;
-        build.temp_folder = Some(temp_folder.into());
+        build.simulate_path = Some(simulate_path.into());
         build
     }
 
-    pub fn simulate_path(self, simulate_path: impl Into<PathBuf>) -> Self {
+    /// Max concurrent simulations, 0 means number of cpu
+    pub fn jobs(self, jobs: usize) -> Self {
         let mut build = self;
-        build.simulate_path = Some(simulate_path.into());
+        build.jobs = Some(jobs);
         build
     }
 
-    pub fn circuit_path(self, circuit_path: impl Into<PathBuf>) -> Self {
+    pub fn address_mode(self, address_mode: TimingAddressMode) -> Self {
         let mut build = self;
-        build.circuit_path = Some(circuit_path.into());
+        build.address_mode = address_mode;
         build
     }
 }
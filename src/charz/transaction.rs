@@ -5,9 +5,12 @@ use tracing::warn;
 use crate::{circuit::{Shr, ShrString, Sram}, export, pdk::{Enviroment, Pdk}, simulate::{CircuitSimulator, Meas, SpiceCommand}, ErrorContext, YouRAMResult};
 pub type Bits = Vec<bool>;
 
+#[derive(Clone)]
 pub enum SramTransaction {
     Write { address: Bits, word: Bits },
     Read { address: Bits },
+    /// A write with chip deselected, should change nothing
+    Idle { address: Bits, word: Bits },
 }
 
 /// Generate SRAM transaction and meas in logic.
@@ -27,6 +30,7 @@ pub enum SramTransaction {
 ///    t = 0   t = period
 ///
 ///
+#[derive(Clone)]
 pub struct SramTransactionGenerator {
     pub sram: Shr<Sram>,
     pub period: Time,
@@ -34,12 +38,25 @@ pub struct SramTransactionGenerator {
     transactions: Vec<SramTransaction>,
     read_transaction_size: usize,
     write_transaction_size: usize,
-    measurements: Vec<Box<dyn Meas>>, 
+    /// Shared by clones, so a generator can be cloned for several simulations
+    measurements: Vec<Arc<dyn Meas>>,
     memory: HashMap<usize, Vec<bool>>,
     addr_mask: usize,
     word_mask: usize,
     max_address: usize,
     max_word: usize,
+    /// Shift of an input's change into a transaction, by (port name, transaction index)
+    skews: HashMap<(String, usize), Time>,
+    clock_slew: Option<Time>,
+    /// Vdd and input high level of a transaction, by transaction index
+    transaction_voltages: TransactionVoltages,
+}
+
+/// Voltages set by `SramTransactionGenerator::set_transaction_voltage`, other transactions
+/// run at the nominal voltage
+#[derive(Debug, Clone, Default)]
+struct TransactionVoltages {
+    voltages: HashMap<usize, Voltage>,
 }
 
 impl SramTransactionGenerator {
@@ -61,6 +78,9 @@ impl SramTransactionGenerator {
             word_mask,
             max_address,
             max_word,
+            skews: HashMap::new(),
+            clock_slew: None,
+            transaction_voltages: TransactionVoltages::default(),
         }
     }
 
@@ -79,36 +99,53 @@ impl SramTransactionGenerator {
             Some(circuit_path) => circuit_path.into(),
             None => {
                 let circuit_path = temp_folder.join(self.sram.read().name.to_string());
-                export::write_spice(self.sram.clone(), &circuit_path).with_context(|| format!("write sram"))?;
+                export::write_simulation_spice(self.sram.clone(), &circuit_path, &pdk).with_context(|| format!("write sram"))?;
                 circuit_path
             }
         };
 
-        let mut simulator = CircuitSimulator::create(self.sram.clone(), env, pdk, simulate_path, circuit_path)?;
+        let nominal_voltage = env.voltage();
+        let clock_slew = self.clock_slew.unwrap_or(env.input_slew());
+        let env = match self.transaction_voltages.is_empty() {
+            true => env,
+            false => {
+                let vdd_waveform = self.transaction_voltages.vdd_waveform(self.transactions.len(), self.period, nominal_voltage, env.input_slew());
+                env.with_vdd_waveform(vdd_waveform)
+            }
+        };
+
+        let mut simulator = CircuitSimulator::create_with_dialect(
+            self.sram.clone(), env, pdk, simulate_path, circuit_path, command.dialect())?;
     
         // transform logic transactions to real voltages values
+        let mut csb_voltags = vec![];
         let mut we_voltags = vec![];
         let mut address_voltags = vec![vec![]; self.sram.read().address_width()];
         let mut word_voltags = vec![vec![]; self.sram.read().word_width()];
 
-        for transaction in self.transactions.iter() {
+        for (index, transaction) in self.transactions.iter().enumerate() {
+            let high_voltage = self.cycle_voltage(index, nominal_voltage);
+            let logic_voltage = |bit: bool| if bit { high_voltage } else { v!(0) };
             match transaction {
-                SramTransaction::Write { address, word } => {
-                    we_voltags.push(simulator.logic1_voltage());
+                SramTransaction::Write { address, word } | SramTransaction::Idle { address, word } => {
+                    let selected = matches!(transaction, SramTransaction::Write { .. });
+                    csb_voltags.push(logic_voltage(!selected));
+                    we_voltags.push(high_voltage);
                     
                     for (voltags, &value) in address_voltags.iter_mut().zip(address) {
-                        voltags.push(simulator.logic_voltage(value));
+                        voltags.push(logic_voltage(value));
                     }
 
                     for (voltags, &value) in word_voltags.iter_mut().zip(word) {
-                        voltags.push(simulator.logic_voltage(value));
+                        voltags.push(logic_voltage(value));
                     }
 
                 }
                 SramTransaction::Read { address } => {
+                    csb_voltags.push(v!(0));
                     we_voltags.push(v!(0));
                     for (voltags, &value) in address_voltags.iter_mut().zip(address) {
-                        voltags.push(simulator.logic_voltage(value));
+                        voltags.push(logic_voltage(value));
                     }
 
                     for voltags in word_voltags.iter_mut() {
@@ -119,13 +156,41 @@ impl SramTransactionGenerator {
         }
 
         // write inputs
-        simulator.write_clock(self.period)?;
+        if !self.transaction_voltages.is_empty() {
+            // same edges as `write_clock_with_slew`, high level of each cycle follows its vdd
+            let mut points = vec![];
+            for index in 0..(self.transactions.len() + 2) {
+                let high_voltage = self.cycle_voltage(index, nominal_voltage);
+                let last_high_voltage = self.cycle_voltage(index.saturating_sub(1), nominal_voltage);
+                points.extend([
+                    (self.clock_begin(index), last_high_voltage),
+                    (self.clock_begin(index) + clock_slew, v!(0)),
+                    (self.clock_rise_time(index), v!(0)),
+                    (self.clock_rise_time(index) + clock_slew, high_voltage),
+                ]);
+            }
+            simulator.write_pwl_stimulate(CircuitSimulator::CLOSK_PORT_NAME, points.into_iter())?;
+        } else {
+            match self.clock_slew {
+                Some(slew) => simulator.write_clock_with_slew(self.period, slew)?,
+                None => simulator.write_clock(self.period)?,
+            }
+        }
 
         let mut write_stimulation = |port_name: ShrString, voltages: &[Voltage]| -> YouRAMResult<()> {
-            simulator.write_period_stimulate(port_name, voltages, self.period, 0.0)
+            if !self.skews.keys().any(|(name, _)| name == port_name.as_str()) {
+                return simulator.write_period_stimulate(port_name, voltages, self.period, 0.0);
+            }
+            let times: Vec<Time> = (0..voltages.len())
+                .map(|index| {
+                    let skew = self.skews.get(&(port_name.to_string(), index)).copied().unwrap_or(t!(0));
+                    (self.clock_begin(index) + skew).max(t!(0))
+                })
+                .collect();
+            simulator.write_square_wave_stimulate(port_name, times.into_iter().zip(voltages.iter().copied()))
         };
             
-        write_stimulation(Sram::chip_sel_bar_pn(), &[v!(0.)])?;
+        write_stimulation(Sram::chip_sel_bar_pn(), &csb_voltags)?;
         write_stimulation(Sram::write_enable_pn(), &we_voltags)?;
         
         for (i, address) in address_voltags.iter().enumerate() {
@@ -138,7 +203,7 @@ impl SramTransactionGenerator {
 
         // write meas
         for meas in self.measurements {
-            simulator.write_measurement(meas)?;
+            simulator.write_measurement(Box::new(meas))?;
         }
 
         // write trans
@@ -192,8 +257,43 @@ impl SramTransactionGenerator {
         true
     } 
 
+    /// Add a write transaction with chip deselected, sram memory state is unchanged
+    pub fn add_idle_transaction(&mut self, address: usize, word: usize) {
+        let address: usize = self.mask_address(address);
+        let word = self.mask_word(word);
+
+        self.transactions.push(SramTransaction::idle(
+            self.address_to_bits(address), 
+            self.word_to_bits(word)
+        ));
+    }
+
+    /// Move the change of input `port_name` into transaction `transaction_index` by `skew`,
+    /// a positive skew changes the input later. Inputs change at `clock_begin` by default
+    pub fn skew_input(&mut self, port_name: impl Into<String>, transaction_index: usize, skew: Time) {
+        self.skews.insert((port_name.into(), transaction_index), skew);
+    }
+
+    /// Run transaction `transaction_index` at `voltage` instead of the pvt voltage. Vdd, clock
+    /// and the high level of inputs change at the begin of the transaction's clock cycle
+    pub fn set_transaction_voltage(&mut self, transaction_index: usize, voltage: Voltage) {
+        self.transaction_voltages.insert(transaction_index, voltage);
+    }
+
+    /// Voltage of clock cycle `clock_index`, cycles after the last transaction keep its voltage
+    /// so the last read is measured at it
+    pub fn cycle_voltage(&self, clock_index: usize, nominal_voltage: Voltage) -> Voltage {
+        self.transaction_voltages.cycle_voltage(clock_index, self.transactions.len(), nominal_voltage)
+    }
+
+    /// Use a clock transition different from the inputs' one of enviroment
+    pub fn set_clock_slew(&mut self, slew: Time) {
+        self.clock_slew = Some(slew);
+    }
+
     pub fn add_measurement<M: Meas + 'static>(&mut self, meas: impl Into<Box<M>>) {
-        self.measurements.push(meas.into());
+        let meas: Arc<M> = Arc::from(meas.into());
+        self.measurements.push(meas);
     }
 
     pub fn clock_rise_time(&self, clock_index: usize) -> Time {
@@ -224,6 +324,10 @@ impl SramTransactionGenerator {
         self.memory.get(&address)
     }
 
+    pub fn transactions(&self) -> &[SramTransaction] {
+        &self.transactions
+    }
+
     pub fn transaction_size(&self) -> usize {
         self.transactions.len()
     }
@@ -295,6 +399,37 @@ impl SramTransactionGenerator {
     
 }
 
+impl TransactionVoltages {
+    fn insert(&mut self, transaction_index: usize, voltage: Voltage) {
+        self.voltages.insert(transaction_index, voltage);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.voltages.is_empty()
+    }
+
+    /// See `SramTransactionGenerator::cycle_voltage`
+    fn cycle_voltage(&self, clock_index: usize, transaction_size: usize, nominal_voltage: Voltage) -> Voltage {
+        let index = clock_index.min(transaction_size.saturating_sub(1));
+        self.voltages.get(&index).copied().unwrap_or(nominal_voltage)
+    }
+
+    /// Vdd ramps at the begin of a clock cycle when the voltage changes, with inputs
+    fn vdd_waveform(&self, transaction_size: usize, period: Time, nominal_voltage: Voltage, slew: Time) -> Vec<(Time, Voltage)> {
+        let voltage = |index: usize| self.cycle_voltage(index, transaction_size, nominal_voltage);
+        let mut points = vec![(t!(0), voltage(0))];
+        for index in 1..transaction_size {
+            let (from, to) = (voltage(index - 1), voltage(index));
+            if from.value().to_f64() != to.value().to_f64() {
+                let begin = index as f64 * period;
+                points.push((begin, from));
+                points.push((begin + slew, to));
+            }
+        }
+        points
+    }
+}
+
 impl SramTransaction {
     pub fn write(address: impl Into<Bits>, word: impl Into<Bits>) -> Self {
         Self::Write { address: address.into(), word: word.into() }
@@ -303,4 +438,58 @@ impl SramTransaction {
     pub fn read(address: impl Into<Bits>) -> Self {
         Self::Read { address: address.into() }
     }
+
+    pub fn idle(address: impl Into<Bits>, word: impl Into<Bits>) -> Self {
+        Self::Idle { address: address.into(), word: word.into() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_voltage() {
+        let mut voltages = TransactionVoltages::default();
+        voltages.insert(1, v!(0.8));
+        voltages.insert(3, v!(0.7));
+
+        let voltage = |index| voltages.cycle_voltage(index, 4, v!(1.1)).value().to_f64();
+        assert_eq!(voltage(0), 1.1);
+        assert_eq!(voltage(1), 0.8);
+        assert_eq!(voltage(2), 1.1);
+        // cycles after the last transaction keep its voltage
+        assert_eq!(voltage(3), 0.7);
+        assert_eq!(voltage(5), 0.7);
+    }
+
+    #[test]
+    fn test_vdd_waveform() {
+        let mut voltages = TransactionVoltages::default();
+        // transactions 2 and 3 lowered together, 5 lowered alone
+        voltages.insert(2, v!(0.8));
+        voltages.insert(3, v!(0.8));
+        voltages.insert(5, v!(0.8));
+
+        let points: Vec<(f64, f64)> = voltages.vdd_waveform(7, t!(10 n), v!(1.1), t!(100 p))
+            .into_iter()
+            .map(|(time, voltage)| (time.value().to_f64(), voltage.value().to_f64()))
+            .collect();
+        let expected = [
+            (0.0, 1.1),
+            (20e-9, 1.1), (20.1e-9, 0.8),
+            (40e-9, 0.8), (40.1e-9, 1.1),
+            (50e-9, 1.1), (50.1e-9, 0.8),
+            (60e-9, 0.8), (60.1e-9, 1.1),
+        ];
+        assert_eq!(points.len(), expected.len());
+        for ((time, voltage), (expected_time, expected_voltage)) in points.into_iter().zip(expected) {
+            assert!((time - expected_time).abs() < 1e-15, "{} != {}", time, expected_time);
+            assert_eq!(voltage, expected_voltage);
+        }
+
+        // nothing lowered, vdd stays flat
+        let points = TransactionVoltages::default().vdd_waveform(7, t!(10 n), v!(1.1), t!(100 p));
+        assert_eq!(points.len(), 1);
+    }
 }
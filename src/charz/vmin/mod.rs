@@ -0,0 +1,244 @@
+use reda_unit::{Time, Voltage};
+use tracing::{debug, info, warn};
+use crate::{pdk::Pvt, simulate::JobContext, ErrorContext, YouRAMResult};
+use super::{bisect::bisect, CharzError, CharzSetup, FunctionCharzPolicy, FunctionTransactionGenerator, MarchCPolicy, SramTransaction};
+
+const DEFAULT_RESOLUTION: f64 = 10e-3;
+
+/// Operation run at the lowered voltage in `VminCharz`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VminOperation {
+    /// Reads run at the lowered voltage, writes at the pvt voltage
+    Read,
+    /// Writes run at the lowered voltage, reads at the pvt voltage
+    Write,
+}
+
+#[derive(Debug, Clone)]
+pub struct VminCharzResult {
+    pub pvt: Pvt,
+    /// None if reads fail at the pvt voltage
+    pub read_vmin: Option<Voltage>,
+    /// None if writes fail at the pvt voltage
+    pub write_vmin: Option<Voltage>,
+}
+
+/// Minimum supply voltage charz for Sram
+///
+/// For each corner, bisect the lowest vdd in `[min_voltage, pvt voltage]` at which every read
+/// of the policy's transactions is on the expected side of half the vdd of the cycle it is
+/// measured in, see `FunctionTransactionGenerator::read_voltages`. Read and write are
+/// searched separately: only the transactions of one operation run at the lowered vdd, see
+/// `SramTransactionGenerator::set_transaction_voltage`. Passing is assumed monotonic in voltage.
+/// Only `vdd` is lowered, an independent `vdd_array` of `supply_voltages` keeps its voltage
+///
+/// # Deafult:
+/// - policy: march c
+/// - input_net_transition: the largest timing input net transition of pdk
+/// - min_voltage: half of pvt voltage
+/// - resolution: 10mv
+/// - command: ngspice
+/// - temp_folder: "./temp"
+/// - circuit_path: "./temp/<sram_name>.sp"
+///
+/// # Example
+///
+/// ```ignore
+/// let results = VminCharz::config()
+///     .sram(sram)
+///     .pdk(pdk)
+///     .pvt(Pvt::new(Process::SlowSlow, 1.1, 125.0))
+///     .period(t!(10 n))
+///     .analyze()?;
+/// println!("{:?}", results[0].read_vmin);
+/// ```
+pub struct VminCharz {
+    pub setup: CharzSetup,
+    /// Corners to characterize, one result each. The search starts from the pvt voltage
+    pub pvts: Vec<Pvt>,
+    pub period: Option<Time>,
+    pub input_net_transition: Option<Time>,
+    /// Lower bound of search
+    pub min_voltage: Option<Voltage>,
+    pub resolution: Option<Voltage>,
+
+    pub policy: Option<Box<dyn FunctionCharzPolicy>>,
+}
+
+impl VminCharz {
+    pub fn analyze(self) -> YouRAMResult<Vec<VminCharzResult>> {
+        info!("execute vmin charz");
+
+        debug!("extract arguments");
+        let period = self.period.ok_or(CharzError::LackFunctionTestConfigField("period"))?;
+        let policy = self.policy.ok_or(CharzError::LackFunctionTestConfigField("policy"))?;
+        if self.pvts.is_empty() {
+            Err(CharzError::LackFunctionTestConfigField("pvts"))?;
+        }
+        let resolution = self.resolution.map(|r| r.value().to_f64()).unwrap_or(DEFAULT_RESOLUTION);
+        let bench = self.setup.build()?;
+        let input_net_transition = bench.largest_input_net_transition(self.input_net_transition)?;
+
+        let mut results = vec![];
+        for (index, pvt) in self.pvts.iter().enumerate() {
+            let env = bench.env(pvt.clone(), input_net_transition, bench.output_load());
+            let max_voltage = pvt.voltage.value().to_f64();
+            let min_voltage = self.min_voltage.map(|v| v.value().to_f64()).unwrap_or(max_voltage / 2.0);
+
+            let mut vmins = vec![];
+            for operation in [VminOperation::Read, VminOperation::Write] {
+                // the bisection is sequential, all steps share one job folder
+                let job = JobContext { index, name: format!("vmin_{:?}", operation).to_ascii_lowercase(), work_folder: bench.temp_folder.join("vmin") };
+                std::fs::create_dir_all(job.work_folder()).context("create vmin folder")?;
+
+                // every step simulates the same transactions, only the voltage is lowered
+                let mut transactions = FunctionTransactionGenerator::new(bench.sram.clone(), period);
+                policy.generate_transactions(&mut transactions)?;
+
+                let vmin = bisect(min_voltage, max_voltage, resolution, |voltage| {
+                    let mut generator = transactions.clone();
+                    Self::lower_voltage(&mut generator, operation, Voltage::from(voltage));
+                    let read_voltages = generator.read_voltages(env.voltage());
+
+                    let result = generator.transactions.simulate(
+                        env.clone(), bench.pdk.clone(), &bench.command, job.simulate_path(), Some(&bench.circuit_path), job.work_folder()
+                    )?;
+                    let pass = FunctionTransactionGenerator::check_reads_at(&generator.target_meas_result, &result, &read_voltages);
+                    debug!("{} {:?} at {:e}v {}", pvt, operation, voltage, if pass { "passed" } else { "failed" });
+                    Ok(pass)
+                })?.map(Voltage::from);
+
+                match vmin {
+                    Some(vmin) => info!("{}: {:?} vmin is {}", pvt, operation, vmin),
+                    None => warn!("{}: {:?} fails at {}", pvt, operation, pvt.voltage),
+                }
+                vmins.push(vmin);
+            }
+
+            results.push(VminCharzResult { pvt: pvt.clone(), read_vmin: vmins[0], write_vmin: vmins[1] });
+        }
+
+        Ok(results)
+    }
+
+    /// Run every transaction of `operation` at `voltage`
+    fn lower_voltage(generator: &mut FunctionTransactionGenerator, operation: VminOperation, voltage: Voltage) {
+        for index in Self::operation_transactions(generator.transactions.transactions(), operation) {
+            generator.transactions.set_transaction_voltage(index, voltage);
+        }
+    }
+
+    /// Index of every transaction of `operation`
+    fn operation_transactions(transactions: &[SramTransaction], operation: VminOperation) -> Vec<usize> {
+        transactions.iter()
+            .enumerate()
+            .filter(|(_, transaction)| match operation {
+                VminOperation::Read => matches!(transaction, SramTransaction::Read { .. }),
+                VminOperation::Write => matches!(transaction, SramTransaction::Write { .. }),
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+impl Default for VminCharz {
+    fn default() -> Self {
+        Self {
+            setup: CharzSetup::default(),
+            pvts: vec![],
+            period: None,
+            input_net_transition: None,
+            min_voltage: None,
+            resolution: None,
+            policy: Some(Box::new(MarchCPolicy)),
+        }
+    }
+}
+
+impl_charz_setup!(VminCharz);
+
+impl VminCharz {
+    pub fn config() -> Self {
+        Self::default()
+    }
+
+    /// Add a corner
+    pub fn pvt(self, pvt: impl Into<Pvt>) -> Self {
+        let mut build = self;
+        build.pvts.push(pvt.into());
+        build
+    }
+
+    pub fn pvts(self, pvts: impl IntoIterator<Item = Pvt>) -> Self {
+        let mut build = self;
+        build.pvts.extend(pvts);
+        build
+    }
+
+    pub fn period(self, period: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.period = Some(period.into());
+        build
+    }
+
+    pub fn input_net_transition(self, input_net_transition: impl Into<Time>) -> Self {
+        let mut build = self;
+        build.input_net_transition = Some(input_net_transition.into());
+        build
+    }
+
+    pub fn min_voltage(self, min_voltage: impl Into<Voltage>) -> Self {
+        let mut build = self;
+        build.min_voltage = Some(min_voltage.into());
+        build
+    }
+
+    pub fn resolution(self, resolution: impl Into<Voltage>) -> Self {
+        let mut build = self;
+        build.resolution = Some(resolution.into());
+        build
+    }
+
+    pub fn policy_box(mut self, policy: Box<dyn FunctionCharzPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use reda_unit::{v, Number};
+    use super::*;
+
+    #[test]
+    fn test_operation_transactions() {
+        let transactions = [
+            SramTransaction::write([false], [true]),
+            SramTransaction::read([false]),
+            SramTransaction::idle([true], [false]),
+            SramTransaction::write([true], [false]),
+            SramTransaction::read([true]),
+        ];
+        assert_eq!(VminCharz::operation_transactions(&transactions, VminOperation::Read), vec![1, 4]);
+        assert_eq!(VminCharz::operation_transactions(&transactions, VminOperation::Write), vec![0, 3]);
+    }
+
+    #[test]
+    fn test_check_reads_at() {
+        let target: HashMap<String, bool> = [("dout0_0", true), ("dout0_1", true)]
+            .into_iter().map(|(name, bit)| (name.to_string(), bit)).collect();
+        // read 0 measured in a lowered cycle, read 1 at pvt voltage
+        let voltages: HashMap<String, Voltage> = [("dout0_0", v!(0.6)), ("dout0_1", v!(1.1))]
+            .into_iter().map(|(name, voltage)| (name.to_string(), voltage)).collect();
+        let result = |values: [f64; 2]| -> HashMap<String, Number> {
+            ["dout0_0", "dout0_1"].into_iter().zip(values).map(|(name, value)| (name.to_string(), Number::from_f64(value))).collect()
+        };
+
+        assert!(FunctionTransactionGenerator::check_reads_at(&target, &result([0.5, 1.0]), &voltages));
+        // a weak 1 at pvt voltage fails although it is above the lowered half voltage
+        assert!(!FunctionTransactionGenerator::check_reads_at(&target, &result([0.5, 0.5]), &voltages));
+        assert!(!FunctionTransactionGenerator::check_reads_at(&target, &result([0.2, 1.0]), &voltages));
+        assert!(!FunctionTransactionGenerator::check_reads_at(&target, &result([0.5, 1.0]), &HashMap::new()));
+    }
+}
@@ -35,6 +35,9 @@ pub enum CircuitError {
     #[error("no exit dff with({0})")]
     DffNotFound(DriveStrength),
 
+    #[error("no exit leafcell '{0}' in pdk")]
+    LeafcellNotFound(&'static str),
+
     #[error("logicgate input order {0} port out of range ")]
     LogicGateInputPortOutOfRange(usize),
 
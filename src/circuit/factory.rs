@@ -4,7 +4,7 @@ use std::sync::{Arc, RwLock};
 use tracing::info;
 use crate::pdk::Pdk;
 use crate::{ErrorContext, YouRAMResult};
-use super::{CircuitError, Dff, DriveStrength, Leafcell, LogicGate, LogicGateKind, Module, Shr, ShrString};
+use super::{CircuitError, Dff, DriveStrength, Leafcell, LogicGate, LogicGateKind, Module, Shr, ShrString, LEVEL_SHIFTER_NAME};
 
 pub trait ModuleArg: Sized + Debug + Send + Sync {
     fn module_name(&self) -> ShrString;
@@ -101,6 +101,11 @@ impl CircuitFactory {
     pub fn precharge(&self) -> Shr<Leafcell> {
         self.pdk.get_precharge()
     }
+
+    pub fn level_shifter(&self) -> Result<Shr<Leafcell>, CircuitError> {
+        self.pdk.get_level_shifter()
+            .ok_or(CircuitError::LeafcellNotFound(LEVEL_SHIFTER_NAME))
+    }
 }
 
 trait DowncastArc {
@@ -180,6 +180,28 @@ impl<A> Module<A> {
     impl_link_instance!(link_column_trigate_instance, column_trigate, [bl_in, br_in, bl_out, br_out, sel, vdd, gnd]);
     impl_link_instance!(link_precharge_instance, precharge, [bl, br, en, vdd]);
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn link_level_shifter_instance(
+        &mut self,
+        factory: &mut CircuitFactory,
+        name: impl Into<ShrString>,
+        input: impl Into<ShrString>,
+        output: impl Into<ShrString>,
+        vdd: impl Into<ShrString>,
+        vdd_high: impl Into<ShrString>,
+        gnd: impl Into<ShrString>,
+    ) -> YouRAMResult<Shr<Instance>> {
+        let name: ShrString = name.into();
+        (|| -> YouRAMResult<Shr<Instance>> {
+            let cell = factory.level_shifter()?;
+            self.sub_circuits.insert(cell.clone().into());
+            let instance = self.add_instance(name.clone(), cell)?;
+            self.connect_instance(instance.clone(), [input.into(), output.into(), vdd.into(), vdd_high.into(), gnd.into()].into_iter())?;
+            Ok(instance)
+        })()
+        .with_context(|| format!("link leafcell {} to circuit {}", name, self.name))
+    }
+
     pub fn link_dff_instance(
         &mut self, 
         name: impl Into<ShrString>,
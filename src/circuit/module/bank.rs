@@ -17,6 +17,7 @@ use super::{BitcellArrayRecursiveArg, PrechargeArrayArg};
     replical_bitline:     ("rbl", InOut),
 
     vdd:                  ("vdd", Vdd),
+    vdd_array:            ("vdd_array", Vdd, "dual_supply"),
     gnd:                  ("gnd", Gnd),
 )]
 pub struct Bank {
@@ -26,6 +27,10 @@ pub struct Bank {
 
     #[new(value = "column_sel_size * word_width")]
     pub column_size: usize,
+
+    /// Bitcells are powered by `vdd_array` instead of `vdd`
+    #[new(default)]
+    pub dual_supply: bool,
 }
 
 
@@ -48,6 +53,17 @@ impl Bank {
         let br_nets: Vec<_> = (0..self.args.column_size).map(|i| format_shr!("br{}", i)).collect();
 
         let rbr_net: ShrString = "rbr".into();
+        let array_vdd_net = self.array_vdd_pn();
+
+        // replical wordline should swing to array supply too
+        let rwl_net = if self.args.dual_supply {
+            let rwl_net: ShrString = "rwl".into();
+            self.link_level_shifter_instance(factory, "level_shifter_rwl", 
+                Self::wordline_enbale_pn(), rwl_net.clone(), Self::vdd_pn(), array_vdd_net.clone(), Self::gnd_pn())?;
+            rwl_net
+        } else {
+            Self::wordline_enbale_pn()
+        };
    
         // bitcell array
         {
@@ -55,7 +71,7 @@ impl Bank {
             nets.extend(bl_nets.iter().cloned());
             nets.extend(br_nets.iter().cloned());
             nets.extend((0..self.args.row_size).map(|i| Self::wordline_pn(i)));
-            nets.push(Self::vdd_pn());
+            nets.push(array_vdd_net.clone());
             nets.push(Self::gnd_pn());
 
             self.link_module_instance("bitcell_array", bitcell_array, nets.into_iter())?;
@@ -66,8 +82,8 @@ impl Bank {
             let mut nets = vec![];
             nets.push(Self::replical_bitline_pn());
             nets.push(rbr_net.clone());
-            nets.push(Self::wordline_enbale_pn());
-            nets.push(Self::vdd_pn());
+            nets.push(rwl_net);
+            nets.push(array_vdd_net.clone());
             nets.push(Self::gnd_pn());
 
             self.link_module_instance("replical_bitcell_array", replical_bitcell_array, nets.into_iter())?;   
@@ -126,4 +142,13 @@ impl Bank {
     pub fn has_column_address(&self) -> bool {
         self.args.column_sel_size > 1
     }
+
+    /// Net powering the bitcells
+    pub fn array_vdd_pn(&self) -> ShrString {
+        if self.args.dual_supply {
+            Self::vdd_array_pn()
+        } else {
+            Self::vdd_pn()
+        }
+    }
 }
\ No newline at end of file
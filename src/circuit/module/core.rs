@@ -1,5 +1,5 @@
 use youram_macro::module;
-use crate::{check_arg, circuit::{AndArrayArg, Bank, BankArg, CircuitFactory, ControlLogic, ControlLogicArg}, format_shr, YouRAMResult};
+use crate::{check_arg, circuit::{AndArrayArg, Bank, BankArg, CircuitFactory, ControlLogic, ControlLogicArg, WordlineDriverArray, WordlineDriverArrayArg}, YouRAMResult};
 
 #[module(
     clock:         ("clk", Input),
@@ -12,6 +12,7 @@ use crate::{check_arg, circuit::{AndArrayArg, Bank, BankArg, CircuitFactory, Con
     data_output:   ("dout{word_width}", Input),
 
     vdd:           ("vdd", Vdd),
+    vdd_array:     ("vdd_array", Vdd, "dual_supply"),
     gnd:           ("gnd", Gnd),
 )]
 pub struct Core {
@@ -21,6 +22,10 @@ pub struct Core {
 
     #[new(value = "column_sel_size * word_width")]
     pub column_size: usize,
+
+    /// Bitcells are powered by `vdd_array`, wordlines are level shifted to it
+    #[new(default)]
+    pub dual_supply: bool,
 }
 
 impl Core {
@@ -33,13 +38,14 @@ impl Core {
         check_arg!(self.args.column_sel_size <= Self::MAX_COLUMN_SIZE, "column sel size '{}' > {}", self.args.column_sel_size, Self::MAX_COLUMN_SIZE);
         check_arg!(self.bitcell_size() <= Self::MAX_BITCELL_SIZE, "Too much bitcell size");
 
-        let bank 
-            = self.add_module(BankArg::new(self.args.row_size, self.args.column_sel_size, self.args.word_width), factory)?;
+        let bank = self.add_module(BankArg {
+            dual_supply: self.args.dual_supply,
+            ..BankArg::new(self.args.row_size, self.args.column_sel_size, self.args.word_width)
+        }, factory)?;
         let control_logic 
             = self.add_module(ControlLogicArg::new(), factory)?;
         let and_array 
             = self.add_module(AndArrayArg::new(self.args.row_size), factory)?;
-        // TODO: wordline driver
 
         let rbl_net = ControlLogic::replical_bitline_pn();
         let wl_en_net = ControlLogic::wordline_enable_pn();
@@ -82,17 +88,41 @@ impl Core {
             nets.extend((0..self.args.word_width).map(|i| Self::data_output_pn(i)));
             nets.push(rbl_net.clone());
             nets.push(Self::vdd_pn());
+            if self.args.dual_supply {
+                nets.push(Self::vdd_array_pn());
+            }
             nets.push(Self::gnd_pn());
 
             self.link_module_instance("bank", bank, nets.into_iter())?;
         }
 
+        // wordline driver, only needed to shift wordline to array supply
+        let and_output_nets = if self.args.dual_supply {
+            let wordline_driver_array = self.add_module(WordlineDriverArrayArg {
+                level_shift: true,
+                ..WordlineDriverArrayArg::new(self.args.column_size, self.args.row_size)
+            }, factory)?;
+
+            let wl_in_nets: Vec<_> = (0..self.args.row_size).map(WordlineDriverArray::wordline_input_pn).collect();
+            let mut nets = vec![];
+            nets.extend(wl_in_nets.iter().cloned());
+            nets.extend(wl_nets.iter().cloned());
+            nets.push(Self::vdd_pn());
+            nets.push(Self::vdd_array_pn());
+            nets.push(Self::gnd_pn());
+
+            self.link_module_instance("wordline_driver_array", wordline_driver_array, nets.into_iter())?;
+            wl_in_nets
+        } else {
+            wl_nets
+        };
+
         // and array 
         {
             let mut nets = vec![];
             nets.extend((0..self.args.row_size).map(|r| Self::row_select_pn(r)));
             nets.push(wl_en_net.clone());
-            nets.extend(wl_nets.into_iter());
+            nets.extend(and_output_nets.into_iter());
             nets.push(Self::vdd_pn());
             nets.push(Self::gnd_pn());
 
@@ -13,6 +13,7 @@ use super::{Core, CoreArg, CoreSelector, CoreSelectorArg, DecoderArg, InputDffs,
     data_output:   ("dout{word_width}", Input),
 
     vdd:           ("vdd", Vdd),
+    vdd_array:     ("vdd_array", Vdd, "dual_supply"),
     gnd:           ("gnd", Gnd),
 )]
 pub struct Sram {
@@ -20,7 +21,11 @@ pub struct Sram {
     pub word_width: usize,
 
     #[new(value = "AddressDistribution::new(address_width, word_width)")]
-    pub distribution: AddressDistribution
+    pub distribution: AddressDistribution,
+
+    /// Expose a separate `vdd_array` supply for bitcell array
+    #[new(default)]
+    pub dual_supply: bool,
 }
 
 impl Sram {
@@ -34,8 +39,10 @@ impl Sram {
         // add module
         let input_dffs 
             = self.add_module(InputDffsArg::new(self.args.address_width, self.args.word_width), factory)?;
-        let core 
-            = self.add_module(CoreArg::new(self.core_row_size(), self.core_column_sel_size(), self.args.word_width), factory)?;
+        let core = self.add_module(CoreArg {
+            dual_supply: self.args.dual_supply,
+            ..CoreArg::new(self.core_row_size(), self.core_column_sel_size(), self.args.word_width)
+        }, factory)?;
         let row_decoder
             = self.add_module(DecoderArg::new(self.row_address_width()), factory)?;
         let column_decoder = if self.column_address_width() > 0 {
@@ -140,6 +147,9 @@ impl Sram {
                 nets.extend(core_dout_nets[core_index].iter().cloned());
 
                 nets.push(Self::vdd_pn());
+                if self.args.dual_supply {
+                    nets.push(Self::vdd_array_pn());
+                }
                 nets.push(Self::gnd_pn());
 
                 self.link_module_instance(format_shr!("core{}", core_index), core.clone(), nets.into_iter())?;
@@ -158,6 +168,9 @@ impl Sram {
             nets.extend((0..self.args.word_width).map(|bit| Self::data_output_pn(bit)));
 
             nets.push(Self::vdd_pn());
+            if self.args.dual_supply {
+                nets.push(Self::vdd_array_pn());
+            }
             nets.push(Self::gnd_pn());
 
             self.link_module_instance("core", core.clone(), nets.into_iter())?;            
@@ -202,6 +215,10 @@ impl Sram {
         self.args.address_width
     }
 
+    pub fn distribution(&self) -> &AddressDistribution {
+        &self.args.distribution
+    }
+
     pub fn word_width(&self) -> usize {
         self.args.word_width
     }
@@ -209,6 +226,10 @@ impl Sram {
     pub fn word_size(&self) -> usize {
         2usize.pow(self.row_address_width() as u32)
     }
+
+    pub fn dual_supply(&self) -> bool {
+        self.args.dual_supply
+    }
 }
 
 #[derive(Debug)]
@@ -236,6 +257,18 @@ impl AddressDistribution {
         panic!("Can't find valid address distribution for the option of (address width: {}, word width: {})", address_width, word_width);
     }
 
+    /// Address of the slowest cell in each core, the address is `core | row | column` from msb.
+    /// Wordlines and bitlines are driven from index 0, so the last row and the last column
+    /// select are the farthest from the row decoder and the sense amps
+    pub fn worst_case_addresses(&self) -> Vec<usize> {
+        let row = (1usize << self.row_address_width) - 1;
+        let column = (1usize << self.column_address_width) - 1;
+        let core_shift = self.row_address_width + self.column_address_width;
+        (0..1usize << self.core_address_width)
+            .map(|core| (core << core_shift) | (row << self.column_address_width) | column)
+            .collect()
+    }
+
     fn try_one_core(address_width: usize, word_width: usize) -> Option<usize> {
         let mut array_config = vec![];
         let max_col_address = Sram::MAX_COLUMN_ADDRESS_WIDTH.min(address_width - 1);
@@ -261,3 +294,18 @@ impl AddressDistribution {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AddressDistribution;
+
+    #[test]
+    fn test_worst_case_addresses() {
+        let single = AddressDistribution { core_address_width: 0, row_address_width: 4, column_address_width: 2 };
+        assert_eq!(single.worst_case_addresses(), vec![0b111111]);
+
+        let multiple = AddressDistribution { core_address_width: 2, row_address_width: 3, column_address_width: 1 };
+        let expected = (0..4).map(|core| (core << 4) | (0b111 << 1) | 0b1).collect::<Vec<_>>();
+        assert_eq!(multiple.worst_case_addresses(), expected);
+    }
+}
@@ -1,5 +1,5 @@
 use youram_macro::module;
-use crate::{check_arg, circuit::{CircuitFactory, DriveStrength}, YouRAMResult};
+use crate::{check_arg, circuit::{CircuitFactory, DriveStrength, ShrString}, pdk::Pdk, YouRAMResult};
 
 use super::BufferArg;
 
@@ -7,30 +7,75 @@ use super::BufferArg;
     wordline_input:  ("wl_in", Input),
     wordline:        ("wl", Output),
     vdd:             ("vdd", Vdd),
+    vdd_array:       ("vdd_array", Vdd, "level_shift"),
     gnd:             ("gnd", Gnd),
 )]
 pub struct WordlineDriver {
+    /// Bitcells on the wordline
     pub fanout: usize,
+
+    /// Shift `wl_in` from `vdd` to `vdd_array` before driving wordline
+    #[new(default)]
+    pub level_shift: bool,
 }
 
 impl WordlineDriver {
     pub fn build(&mut self, factory: &mut CircuitFactory) -> YouRAMResult<()> {
         check_arg!(self.args.fanout > 0, "Fanout size '{}' less than 1", self.args.fanout);
         
-        let strength = match self.args.fanout {
-            fanout if fanout > 1 => DriveStrength::X1,
-            fanout if fanout > 16 => DriveStrength::X2,
-            _ => DriveStrength::X4,
+        let strength = Self::drive_strength(Self::unit_loads(self.args.fanout, &factory.pdk));
+
+        let (buffer_input_net, buffer_vdd_net) = if self.args.level_shift {
+            let shifted_net: ShrString = "wl_shifted".into();
+            self.link_level_shifter_instance(factory, "level_shifter", 
+                Self::wordline_input_pn(), shifted_net.clone(), Self::vdd_pn(), Self::vdd_array_pn(), Self::gnd_pn())?;
+            (shifted_net, Self::vdd_array_pn())
+        } else {
+            (Self::wordline_input_pn(), Self::vdd_pn())
         };
 
         let buffer = self.add_module(BufferArg::new(strength), factory)?;
         self.link_module_instance("buffer", buffer, [
-            Self::wordline_input_pn(),
+            buffer_input_net,
             Self::wordline_pn(),
-            Self::vdd_pn(),
+            buffer_vdd_net,
             Self::gnd_pn(),
         ].into_iter())?;
 
         Ok(())
     }
+
+    fn drive_strength(unit_loads: f64) -> DriveStrength {
+        match unit_loads {
+            loads if loads > 16.0 => DriveStrength::X4,
+            loads if loads > 1.0 => DriveStrength::X2,
+            _ => DriveStrength::X1,
+        }
+    }
+
+    /// Wordline load in `default_fanout_load` of pdk: the measured bitcell `wl` capacitance
+    /// and the wire across the cells. Without the capacitance each bitcell is one load
+    fn unit_loads(fanout: usize, pdk: &Pdk) -> f64 {
+        let (Some(bitcell), Some(unit)) = (pdk.bitcell_wordline_capacitance(), pdk.default_fanout_load()) else {
+            return fanout as f64;
+        };
+        let wire = pdk.parasitics()
+            .map(|parasitics| parasitics.wire_rc(fanout as f64 * parasitics.bitcell_width).1)
+            .unwrap_or(0.0);
+        let load = fanout as f64 * bitcell.value().to_f64() + wire;
+        load / unit.value().to_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drive_strength() {
+        assert_eq!(WordlineDriver::drive_strength(32.0), DriveStrength::X4);
+        assert_eq!(WordlineDriver::drive_strength(16.0), DriveStrength::X2);
+        assert_eq!(WordlineDriver::drive_strength(1.5), DriveStrength::X2);
+        assert_eq!(WordlineDriver::drive_strength(0.4), DriveStrength::X1);
+    }
 }
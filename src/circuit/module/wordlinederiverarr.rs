@@ -5,13 +5,17 @@ use super::WordlineDriverArg;
 
 #[module(
     wordline_input: ("wl_in{wordline_size}", Input),
-    wordline:       ("wl{wordline_size}", Input),
+    wordline:       ("wl{wordline_size}", Output),
     vdd:            ("vdd", Vdd),
+    vdd_array:      ("vdd_array", Vdd, "level_shift"),
     gnd:            ("gnd", Gnd),
 )]
 pub struct WordlineDriverArray {
     pub fanout: usize,
     pub wordline_size: usize,
+
+    #[new(default)]
+    pub level_shift: bool,
 }
 
 impl WordlineDriverArray {
@@ -19,19 +23,29 @@ impl WordlineDriverArray {
         check_arg!(self.args.fanout > 0, "Fanout size '{}' less than 1", self.args.fanout);
         check_arg!(self.args.wordline_size > 0, "wordline size size '{}' less than 1", self.args.wordline_size);
 
-        let wordline = self.add_module(WordlineDriverArg::new(self.args.fanout), factory)?;
+        let wordline = self.add_module(WordlineDriverArg {
+            level_shift: self.args.level_shift,
+            ..WordlineDriverArg::new(self.args.fanout)
+        }, factory)?;
+
         for wordline_index in 0..self.args.wordline_size {
+            let mut nets = vec![
+                Self::wordline_input_pn(wordline_index),
+                Self::wordline_pn(wordline_index),
+                Self::vdd_pn(),
+            ];
+            if self.args.level_shift {
+                nets.push(Self::vdd_array_pn());
+            }
+            nets.push(Self::gnd_pn());
+
             self.link_module_instance(
                 format_shr!("wordline_driver{}", wordline_index), 
-                wordline.clone(), [
-                    Self::wordline_input_pn(wordline_index),
-                    Self::wordline_input_pn(wordline_index),
-                    Self::vdd_pn(),
-                    Self::gnd_pn(),
-                ].into_iter()
+                wordline.clone(), 
+                nets.into_iter()
             )?;
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -9,6 +9,7 @@ pub enum Leafcell {
     WriteDriver(WriteDriver),
     ColumnTriGate(ColumnTriGate),
     Precharge(Precharge),
+    LevelShifter(LevelShifter),
 }
 
 macro_rules! define_leafcell {
@@ -39,12 +40,14 @@ pub const SENSE_AMP_NAME: &'static str = "sense_amp";
 pub const WRITE_DRIVER_NAME: &'static str = "write_driver";
 pub const COLUMN_TRI_GATE_NAME: &'static str = "column_trigate";
 pub const PRECHARGE_NAME: &'static str = "precharge";
+pub const LEVEL_SHIFTER_NAME: &str = "level_shifter";
 
 define_leafcell!(Bitcell, bitline, bitline_bar, word_line, vdd, gnd);
 define_leafcell!(SenseAmp, bitline, bitline_bar, data_output, enable, vdd, gnd);
 define_leafcell!(WriteDriver, data_input, bitline, bitline_bar, enable, vdd, gnd);
 define_leafcell!(ColumnTriGate, bitline, bitline_bar, bitline_output, bitline_bar_output, select, vdd, gnd);
 define_leafcell!(Precharge, bitline, bitline_bar, enable, vdd);
+define_leafcell!(LevelShifter, input, output, vdd, vdd_high, gnd);
 
 impl Design for Leafcell {
     fn name(&self) -> crate::circuit::ShrString {
@@ -54,6 +57,7 @@ impl Design for Leafcell {
             Self::WriteDriver(_) => WRITE_DRIVER_NAME.into(),
             Self::ColumnTriGate(_) => COLUMN_TRI_GATE_NAME.into(),
             Self::Precharge(_) => PRECHARGE_NAME.into(),
+            Self::LevelShifter(_) => LEVEL_SHIFTER_NAME.into(),
         }
     }
 
@@ -64,6 +68,7 @@ impl Design for Leafcell {
             Self::WriteDriver(l) => &l.ports,
             Self::ColumnTriGate(l) => &l.ports,
             Self::Precharge(l) => &l.ports,
+            Self::LevelShifter(l) => &l.ports,
         }
     }
 }
@@ -76,6 +81,7 @@ impl Primitive for Leafcell {
             Self::WriteDriver(l) => &l.netlist,
             Self::ColumnTriGate(l) => &l.netlist,
             Self::Precharge(l) => &l.netlist,
+            Self::LevelShifter(l) => &l.netlist,
         }
     }
 }
\ No newline at end of file
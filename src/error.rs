@@ -27,7 +27,7 @@ pub enum YouRAMError {
     Message(String),
 
     #[error("{msg} >> {err}")]
-    Context { msg: String, err: Box<dyn std::error::Error> }
+    Context { msg: String, err: Box<dyn std::error::Error + Send + Sync> }
 }
 
 pub type YouRAMResult<T> = Result<T, YouRAMError>;
@@ -37,7 +37,7 @@ pub trait ErrorContext<T> {
     fn with_context<S: Into<String>>(self, f: impl Fn() -> S) -> YouRAMResult<T>;
 }
 
-impl<T, E: std::error::Error + 'static> ErrorContext<T> for Result<T, E> {
+impl<T, E: std::error::Error + Send + Sync + 'static> ErrorContext<T> for Result<T, E> {
     fn context<S: Into<String>>(self, msg: S) -> YouRAMResult<T> {
         self.map_err(|e| YouRAMError::Context { msg: msg.into(), err: Box::new(e) }) 
     }
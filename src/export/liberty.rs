@@ -1,11 +1,11 @@
-use std::{fs::File, path::Path, sync::Arc};
+use std::{collections::BTreeMap, fs::File, path::{Path, PathBuf}, sync::Arc};
 use reda_lib::model::LibLuTable;
-use reda_unit::{Capacitance, Temperature, Time, Voltage};
+use reda_unit::{Capacitance, Energy, Power, Temperature, Time, Voltage};
 use tracing::info;
 use std::io::{BufWriter, Write};
 use std::fmt::Write as FmtWrite;
 use crate::circuit::DriveStrength;
-use crate::{charz::TimingCharz, circuit::{Shr, Sram}, pdk::{Pdk, Process, Pvt}, simulate::SpiceCommand, YouRAMResult};
+use crate::{check_arg, charz::{CapacitanceCharz, ConstraintCharz, ConstraintPin, LeakageCharz, LeakageCharzResult, PeriodCharz, PinCapacitance, PinConstraint, PowerCharz, PowerCharzResult, TimingAddressMode, TimingCharz}, circuit::{Shr, Sram}, pdk::{Pdk, Process, Pvt}, simulate::SpiceCommand, YouRAMResult};
 
 pub fn write_liberty(
     sram: Shr<Sram>, 
@@ -15,11 +15,204 @@ pub fn write_liberty(
     command: Box<dyn SpiceCommand>, 
     temp_folder: impl AsRef<Path>,
 ) -> YouRAMResult<()> {
+    write_liberty_with_supply_voltages(sram, path, period, pdk, command, temp_folder, BTreeMap::new())
+}
+
+/// Same as `write_liberty`, but drive some supply ports(e.g. `vdd_array`) with their own voltage
+pub fn write_liberty_with_supply_voltages(
+    sram: Shr<Sram>, 
+    path: impl AsRef<Path>, 
+    period: Time, 
+    pdk: Arc<Pdk>, 
+    command: Box<dyn SpiceCommand>, 
+    temp_folder: impl AsRef<Path>,
+    supply_voltages: BTreeMap<String, Voltage>,
+) -> YouRAMResult<()> {
+    let options = LibertyOptions::default().supply_voltages(supply_voltages);
+    write_liberty_with_options(sram, path, period, pdk, command, temp_folder, options)
+}
+
+/// Optional settings of liberty characterization
+#[derive(Debug, Clone, Default)]
+pub struct LibertyOptions {
+    /// Supply ports(e.g. `vdd_array`) not driven by pvt voltage
+    pub supply_voltages: BTreeMap<String, Voltage>,
+    /// Max concurrent simulations, default is number of cpu
+    pub jobs: Option<usize>,
+    /// Characterize setup/hold of inputs by `ConstraintCharz`, instead of
+    /// copying the tables of pdk's dff
+    pub characterize_constraints: bool,
+    /// Characterize read/write energy by `PowerCharz`, written as `internal_power`
+    pub characterize_power: bool,
+    /// Characterize idle supply power by `LeakageCharz`, written as `cell_leakage_power`
+    pub characterize_leakage: bool,
+    /// Search the min clock period by `PeriodCharz`, written as `min_period` of `clk`
+    pub characterize_min_period: bool,
+    /// Characterize the tables at the min period times this margin instead of the given
+    /// period, implies `characterize_min_period`. At least 1, 1 is exactly the min period
+    pub min_period_margin: Option<f64>,
+    /// Characterize input pin capacitance by `CapacitanceCharz`, written as `capacitance`
+    pub characterize_capacitance: bool,
+    /// Addresses of delay/slew tables, the max of worst-case addresses by default
+    pub timing_address_mode: TimingAddressMode,
+    /// Corners to characterize, each into `<name>_<pvt>.lib` beside the given path.
+    /// Only pdk's pvt is characterized into the given path if empty
+    pub pvts: Vec<Pvt>,
+}
+
+impl LibertyOptions {
+    pub fn supply_voltages(mut self, supply_voltages: BTreeMap<String, Voltage>) -> Self {
+        self.supply_voltages = supply_voltages;
+        self
+    }
+
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    pub fn characterize_constraints(mut self, characterize_constraints: bool) -> Self {
+        self.characterize_constraints = characterize_constraints;
+        self
+    }
+
+    pub fn characterize_power(mut self, characterize_power: bool) -> Self {
+        self.characterize_power = characterize_power;
+        self
+    }
+
+    pub fn characterize_leakage(mut self, characterize_leakage: bool) -> Self {
+        self.characterize_leakage = characterize_leakage;
+        self
+    }
+
+    pub fn characterize_min_period(mut self, characterize_min_period: bool) -> Self {
+        self.characterize_min_period = characterize_min_period;
+        self
+    }
+
+    pub fn min_period_margin(mut self, min_period_margin: f64) -> Self {
+        self.min_period_margin = Some(min_period_margin);
+        self
+    }
+
+    pub fn characterize_capacitance(mut self, characterize_capacitance: bool) -> Self {
+        self.characterize_capacitance = characterize_capacitance;
+        self
+    }
+
+    pub fn timing_address_mode(mut self, timing_address_mode: TimingAddressMode) -> Self {
+        self.timing_address_mode = timing_address_mode;
+        self
+    }
+
+    /// Add a corner
+    pub fn pvt(mut self, pvt: impl Into<Pvt>) -> Self {
+        self.pvts.push(pvt.into());
+        self
+    }
+
+    pub fn pvts(mut self, pvts: impl IntoIterator<Item = Pvt>) -> Self {
+        self.pvts.extend(pvts);
+        self
+    }
+}
+
+pub fn write_liberty_with_options(
+    sram: Shr<Sram>, 
+    path: impl AsRef<Path>, 
+    period: Time, 
+    pdk: Arc<Pdk>, 
+    command: Box<dyn SpiceCommand>, 
+    temp_folder: impl AsRef<Path>,
+    options: LibertyOptions,
+) -> YouRAMResult<()> {
+    let path = path.as_ref();
+    let temp_folder = temp_folder.as_ref();
+    let command: Arc<dyn SpiceCommand> = command.into();
+
+    if options.pvts.is_empty() {
+        let pvt = pdk.pvt().clone();
+        return write_corner_liberty(sram, (&pvt, path), period, pdk, command, temp_folder, &options);
+    }
+
+    // fail before any simulation if pdk has no model of some corner
+    for pvt in options.pvts.iter() {
+        pdk.model_statements(pvt.process)?;
+    }
+
+    for pvt in options.pvts.iter() {
+        info!("characterize liberty at corner {}", pvt);
+        let corner_path = corner_liberty_path(path, pvt);
+        let corner_temp_folder = temp_folder.join(pvt.to_string());
+        std::fs::create_dir_all(&corner_temp_folder)?;
+        write_corner_liberty(
+            sram.clone(), (pvt, &corner_path), period, pdk.clone(), command.clone(), &corner_temp_folder, &options
+        )?;
+    }
+
+    Ok(())
+}
+
+/// `<folder>/<name>.lib` to `<folder>/<name>_<pvt>.lib`
+fn corner_liberty_path(path: &Path, pvt: &Pvt) -> PathBuf {
+    let name = path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}_{}.lib", name, pvt))
+}
+
+/// Unique for each corner, e.g. `SS_0p9V_125C`
+fn operating_conditions_name(pvt: &Pvt) -> String {
+    let voltage = format!("{}", pvt.voltage.value().to_f64()).replace('.', "p");
+    let temp = format!("{}", pvt.temperature.value().to_f64()).replace('.', "p").replace('-', "m");
+    format!("{}_{}V_{}C", pvt.process, voltage, temp)
+}
+
+/// Characterize and write one liberty at the corner `pvt`
+fn write_corner_liberty(
+    sram: Shr<Sram>, 
+    (pvt, path): (&Pvt, &Path), 
+    period: Time, 
+    pdk: Arc<Pdk>, 
+    command: Arc<dyn SpiceCommand>, 
+    temp_folder: &Path,
+    options: &LibertyOptions,
+) -> YouRAMResult<()> {
+    let LibertyOptions {
+        supply_voltages, jobs, characterize_constraints, characterize_power, characterize_leakage,
+        characterize_min_period, min_period_margin, characterize_capacitance, timing_address_mode, pvts: _,
+    } = options.clone();
+
     // collect all 
     let input_net_transitions = pdk.timing_input_net_transitions();
     let output_net_capacitances = pdk.timing_output_net_capacitances();
-    let pvt = pdk.pvt();
-    let temp_folder: &Path = temp_folder.as_ref();
+
+    if let Some(margin) = min_period_margin {
+        check_arg!(margin >= 1.0, "min period margin '{}' less than 1", margin);
+    }
+
+    let min_period = if characterize_min_period || min_period_margin.is_some() {
+        let min_period = PeriodCharz::config()
+            .sram(sram.clone())
+            .pvt(pvt.clone())
+            .pdk(pdk.clone())
+            .max_period(period)
+            .command_box(Box::new(command.clone()))
+            .temp_folder(temp_folder)
+            .supply_voltages(supply_voltages.clone())
+            .analyze()?;
+        Some(min_period)
+    } else {
+        None
+    };
+    let period = match (min_period, min_period_margin) {
+        (Some(min_period), Some(margin)) => {
+            info!("characterize at period {}", min_period * margin);
+            min_period * margin
+        }
+        _ => period,
+    };
 
     let all_result = TimingCharz::config()
         .sram(sram.clone())
@@ -28,9 +221,87 @@ pub fn write_liberty(
         .input_net_transitions(input_net_transitions)
         .output_net_capacitances(output_net_capacitances)
         .pdk(pdk.clone())
-        .command_box(command)
+        .command_box(Box::new(command.clone()))
         .temp_folder(temp_folder)
-        .analyze()?;
+        .supply_voltages(supply_voltages.clone())
+        .address_mode(timing_address_mode);
+    let all_result = match jobs {
+        Some(jobs) => all_result.jobs(jobs),
+        None => all_result,
+    }.analyze()?;
+
+    let constraints = if characterize_constraints {
+        let constraints = ConstraintCharz::config()
+            .sram(sram.clone())
+            .period(period)
+            .pvt(pvt.clone())
+            .input_net_transitions(input_net_transitions)
+            .pdk(pdk.clone())
+            .command_box(Box::new(command.clone()))
+            .temp_folder(temp_folder)
+            .supply_voltages(supply_voltages.clone());
+        let constraints = match jobs {
+            Some(jobs) => constraints.jobs(jobs),
+            None => constraints,
+        }.analyze()?;
+        Some(constraints)
+    } else {
+        None
+    };
+
+    let powers = if characterize_power {
+        let powers = PowerCharz::config()
+            .sram(sram.clone())
+            .period(period)
+            .pvt(pvt.clone())
+            .input_net_transitions(input_net_transitions)
+            .output_net_capacitances(output_net_capacitances)
+            .pdk(pdk.clone())
+            .command_box(Box::new(command.clone()))
+            .temp_folder(temp_folder)
+            .supply_voltages(supply_voltages.clone());
+        let powers = match jobs {
+            Some(jobs) => powers.jobs(jobs),
+            None => powers,
+        }.analyze()?;
+        Some(powers)
+    } else {
+        None
+    };
+
+    let capacitances = if characterize_capacitance {
+        let capacitances = CapacitanceCharz::config()
+            .sram(sram.clone())
+            .pvt(pvt.clone())
+            .pdk(pdk.clone())
+            .command_box(Box::new(command.clone()))
+            .temp_folder(temp_folder)
+            .supply_voltages(supply_voltages.clone());
+        let capacitances = match jobs {
+            Some(jobs) => capacitances.jobs(jobs),
+            None => capacitances,
+        }.analyze()?;
+        Some(capacitances)
+    } else {
+        None
+    };
+
+    let leakage = if characterize_leakage {
+        let leakages = LeakageCharz::config()
+            .sram(sram.clone())
+            .pvt(pvt.clone())
+            .pdk(pdk.clone())
+            .command_box(Box::new(command.clone()))
+            .temp_folder(temp_folder)
+            .supply_voltages(supply_voltages.clone());
+        let leakages = match jobs {
+            Some(jobs) => leakages.jobs(jobs),
+            None => leakages,
+        }.analyze()?;
+        leakages.into_iter().next()
+    } else {
+        None
+    };
 
     let mut delay_lhs = vec![];
     let mut delay_hls = vec![];
@@ -55,7 +326,6 @@ pub fn write_liberty(
     }
 
     // write to path
-    let path = path.as_ref();
     info!("write circuit {} to {:?}", sram.read().name, path);
     let mut writor = LibertyWritor::new(
         sram, 
@@ -69,6 +339,12 @@ pub fn write_liberty(
         slew_hls,
         path,
     )?;
+    writor.supply_voltages = supply_voltages;
+    writor.constraints = constraints;
+    writor.powers = powers;
+    writor.leakage = leakage;
+    writor.min_period = min_period;
+    writor.capacitances = capacitances;
     writor.write()?;
 
     Ok(())
@@ -84,6 +360,16 @@ struct LibertyWritor {
     delay_hls: Vec<Vec<Time>>,
     slew_lhs: Vec<Vec<Time>>,
     slew_hls: Vec<Vec<Time>>,
+    /// Supply ports not driven by pvt voltage, only `vdd_array` is written in `voltage_map`
+    supply_voltages: BTreeMap<String, Voltage>,
+    /// Measured setup/hold tables, pdk's dff tables are used without them
+    constraints: Option<BTreeMap<ConstraintPin, PinConstraint>>,
+    /// Energy by [input_net_transition][output_net_capacitance], no `internal_power` without it
+    powers: Option<Vec<Vec<PowerCharzResult>>>,
+    leakage: Option<LeakageCharzResult>,
+    min_period: Option<Time>,
+    /// Input pin capacitance by port name
+    capacitances: Option<BTreeMap<String, PinCapacitance>>,
     writor: BufWriter<File>,
 }
 
@@ -106,7 +392,7 @@ impl LibertyWritor {
 
 
         Ok(Self {
-            sram, pvt, pdk, input_net_transitions, output_net_capacitances, delay_hls, delay_lhs, slew_hls, slew_lhs, writor
+            sram, pvt, pdk, input_net_transitions, output_net_capacitances, delay_hls, delay_lhs, slew_hls, slew_lhs, supply_voltages: BTreeMap::new(), constraints: None, powers: None, leakage: None, min_period: None, capacitances: None, writor
         })
     }
 
@@ -144,6 +430,8 @@ impl LibertyWritor {
         self.write_line(2, "dont_touch : true;")?;
         self.write_enter()?;
 
+        self.write_leakage_power(2)?;
+
         self.write_pgpin()?;
         self.write_word_bus()?;
         self.write_address_bus()?;
@@ -162,7 +450,7 @@ impl LibertyWritor {
         self.write_line(1, "capacitive_load_unit(1, pF);")?;
         self.write_line(1, "leakage_power_unit : \"1mW\";")?;
         self.write_line(1, "pulling_resistance_unit :\"1kohm\";")?;
-        self.write_line(1, "operating_conditions(OC) {")?;
+        self.write_line(1, &format!("operating_conditions({}) {{", operating_conditions_name(&self.pvt)))?;
         self.write_line(2, &format!("process : {};", self.pvt_process()))?;
         self.write_line(2, &format!("voltage : {}", self.pvt_voltage()))?;
         self.write_line(2, &format!("temperature : {}", self.pvt_temp()))?;
@@ -181,11 +469,24 @@ impl LibertyWritor {
         self.write_line(1, &format!("slew_upper_threshold_pct_fall : {};", self.pdk.slew_upper_threshold_pct_fall() * 100.0))?;
         self.write_line(1, &format!("slew_lower_threshold_pct_rise : {};", self.pdk.slew_lower_threshold_pct_rise() * 100.0))?;
         self.write_line(1, &format!("slew_upper_threshold_pct_rise : {};", self.pdk.slew_upper_threshold_pct_rise() * 100.0))?;
+
+        self.write_line(1, &format!("nom_process                   : {};", self.pvt_process()))?;
+        self.write_line(1, &format!("nom_voltage                   : {};", self.pvt_voltage()))?;
+        self.write_line(1, &format!("nom_temperature               : {};", self.pvt_temp()))?;
         
-        // TODO nom
-        self.write_line(1, "default_cell_leakage_power    : 0.0;")?;
+        let cell_leakage_power = self.leakage.as_ref()
+            .map(|leakage| Self::power_value(leakage.cell_leakage_power()))
+            .unwrap_or(0.0);
+        self.write_line(1, &format!("default_cell_leakage_power    : {};", cell_leakage_power))?;
         self.write_line(1, "default_leakage_power_density : 0.0;")?;
-        self.write_line(1, "default_input_pin_cap         : 1.0;")?;
+        let input_pin_cap = self.capacitances.as_ref()
+            .filter(|capacitances| !capacitances.is_empty())
+            .map(|capacitances| {
+                let sum: f64 = capacitances.values().map(|c| Self::cap_value(c.average())).sum();
+                sum / capacitances.len() as f64
+            })
+            .unwrap_or(1.0);
+        self.write_line(1, &format!("default_input_pin_cap         : {};", input_pin_cap))?;
         self.write_line(1, "default_inout_pin_cap         : 1.0;")?;
         self.write_line(1, "default_output_pin_cap        : 0.0;")?;
         self.write_line(1, "default_max_transition        : 0.5;")?;
@@ -194,8 +495,11 @@ impl LibertyWritor {
         self.write_line(1, "default_connection_class      : universal;")?;
 
         self.write_line(1, &format!("voltage_map ({}, {});", Sram::vdd_pn(), self.pvt_voltage()))?;
+        if self.sram.read().dual_supply() {
+            self.write_line(1, &format!("voltage_map ({}, {});", Sram::vdd_array_pn(), self.array_voltage()))?;
+        }
         self.write_line(1, &format!("voltage_map ({}, 0);", Sram::gnd_pn()))?;
-        self.write_line(1, "default_operating_conditions : OC;")?;
+        self.write_line(1, &format!("default_operating_conditions : {};", operating_conditions_name(&self.pvt)))?;
 
         self.write_enter()?;
 
@@ -219,6 +523,24 @@ impl LibertyWritor {
 
         self.write_enter()?;
 
+        if self.powers.is_some() {
+            self.write_line(1, "power_lut_template(ENERGY_TABLE) {")?;
+            self.write_line(2, "variable_1 : input_transition_time;")?;
+            self.write_line(2, "variable_2 : total_output_net_capacitance;")?;
+            self.write_time_index()?;
+            self.write_cap_index()?;
+            self.write_line(1, "}")?;
+
+            self.write_enter()?;
+
+            self.write_line(1, "power_lut_template(INPUT_ENERGY_TABLE) {")?;
+            self.write_line(2, "variable_1 : input_transition_time;")?;
+            self.write_time_index()?;
+            self.write_line(1, "}")?;
+
+            self.write_enter()?;
+        }
+
         Ok(())
     }
 
@@ -251,6 +573,14 @@ impl LibertyWritor {
         self.write_line(2, "}")?;
         self.write_enter()?;
 
+        if self.sram.read().dual_supply() {
+            self.write_line(2, &format!("pg_pin({}) {{", Sram::vdd_array_pn()))?;
+            self.write_line(3, &format!("voltage_name : {};", Sram::vdd_array_pn()))?;
+            self.write_line(3, "pg_type : primary_power;")?;
+            self.write_line(2, "}")?;
+            self.write_enter()?;
+        }
+
         self.write_line(2, &format!("pg_pin({}) {{", Sram::gnd_pn()))?;
         self.write_line(3, &format!("voltage_name : {};", Sram::gnd_pn()))?;
         self.write_line(3, "pg_type : primary_ground;")?;
@@ -275,7 +605,10 @@ impl LibertyWritor {
         self.write_line(4, "clocked_on  : clk")?;
         self.write_line(3, "}")?; // memory_write
         self.write_line(3, &format!("pin(din[{}:0]) {{", self.sram.read().word_width() - 1))?;
-        self.write_dff_timing(4)?;
+        let pins: Vec<_> = (0..self.sram.read().word_width()).map(|i| Sram::data_input_pn(i).to_string()).collect();
+        self.write_pin_capacitance(4, &pins)?;
+        self.write_constraint_timing(4, ConstraintPin::DataInput)?;
+        self.write_input_power(4, |power| power.data_input_rise, |power| power.data_input_fall)?;
         self.write_line(3, "}")?; // pin
         self.write_line(2, "}")?; // bus
 
@@ -296,6 +629,7 @@ impl LibertyWritor {
         // Mark: dout
         self.write_line(3, &format!("pin(dout[{}:0]) {{",  self.sram.read().word_width() - 1))?;
         self.write_timing_charz(4)?;
+        self.write_read_power(4)?;
         self.write_line(3, "}")?; // pin
         self.write_line(2, "}")?; // bus
 
@@ -314,7 +648,10 @@ impl LibertyWritor {
         self.write_line(3, "address : addr")?;
         self.write_line(3, "}")?; // memory_read()
         self.write_line(3, &format!("pin(addr[{}:0]) {{", self.sram.read().address_width()))?;
-        self.write_dff_timing(4)?;
+        let pins: Vec<_> = (0..self.sram.read().address_width()).map(|i| Sram::address_pn(i).to_string()).collect();
+        self.write_pin_capacitance(4, &pins)?;
+        self.write_constraint_timing(4, ConstraintPin::Address)?;
+        self.write_input_power(4, |power| power.address_rise, |power| power.address_fall)?;
         self.write_line(3, "}")?; // pin
         self.write_line(2, "}")?;// bus
 
@@ -326,18 +663,28 @@ impl LibertyWritor {
     fn write_control_pins(&mut self) -> YouRAMResult<()> {
         self.write_line(2, &format!("pin({}) {{", Sram::chip_sel_bar_pn()))?;
         self.write_line(3, "direction  : input;")?;
-        self.write_dff_timing(3)?;
+        self.write_pin_capacitance(3, &[Sram::chip_sel_bar_pn().to_string()])?;
+        self.write_constraint_timing(3, ConstraintPin::ChipSelectBar)?;
         self.write_line(2, "}")?; // pin
         self.write_enter()?;
 
         self.write_line(2, &format!("pin({}) {{", Sram::write_enable_pn()))?;
         self.write_line(3, "direction  : input;")?;
-        self.write_dff_timing(3)?;
+        self.write_pin_capacitance(3, &[Sram::write_enable_pn().to_string()])?;
+        self.write_constraint_timing(3, ConstraintPin::WriteEnable)?;
         self.write_line(2, "}")?; // pin
         self.write_enter()?;
 
         self.write_line(2, &format!("pin({}) {{", Sram::clock_pn()))?;
         self.write_line(3, "direction  : input;")?;
+        self.write_pin_capacitance(3, &[Sram::clock_pn().to_string()])?;
+        if let Some(min_period) = self.min_period {
+            // 50% duty clock
+            self.write_line(3, &format!("min_period : {};", Self::time_value(min_period)))?;
+            self.write_line(3, &format!("min_pulse_width_high : {};", Self::time_value(min_period / 2.0)))?;
+            self.write_line(3, &format!("min_pulse_width_low : {};", Self::time_value(min_period / 2.0)))?;
+        }
+        self.write_clock_power(3)?;
         self.write_line(2, "}")?; // pin
         self.write_enter()?;
 
@@ -392,6 +739,174 @@ impl LibertyWritor {
         Ok(())
     }
 
+    /// Read energy shared by each `dout` bit, a read of all bits in one direction
+    fn write_read_power(&mut self, indent: usize) -> YouRAMResult<()> {
+        let Some(powers) = self.powers.as_ref() else {
+            return Ok(());
+        };
+        let word_width = self.sram.read().word_width() as f64;
+        let per_bit = |energy: fn(&PowerCharzResult) -> Energy| -> Vec<Vec<f64>> {
+            powers.iter()
+                .map(|row| row.iter().map(|power| Self::energy_value(energy(power)) / word_width).collect())
+                .collect()
+        };
+        let rise_power = per_bit(|power| power.read_rise);
+        let fall_power = per_bit(|power| power.read_fall);
+
+        self.write_line(indent, "internal_power() {")?;
+        self.write_line(indent + 1, &format!("related_pin : \"{}\";", Sram::clock_pn()))?;
+        self.write_line(indent + 1, &format!("when : \"!{} & !{}\";", Sram::chip_sel_bar_pn(), Sram::write_enable_pn()))?;
+
+        self.write_line(indent + 1, "rise_power(ENERGY_TABLE) {")?;
+        self.write_values(indent + 2, &rise_power)?;
+        self.write_line(indent + 1, "}")?; // rise_power
+
+        self.write_line(indent + 1, "fall_power(ENERGY_TABLE) {")?;
+        self.write_values(indent + 2, &fall_power)?;
+        self.write_line(indent + 1, "}")?; // fall_power
+
+        self.write_line(indent, "}")?; // internal_power
+
+        Ok(())
+    }
+
+    /// Energy of one input bit toggling with chip deselected, no output switches so the loads are averaged
+    fn write_input_power(&mut self, indent: usize, rise: fn(&PowerCharzResult) -> Energy, fall: fn(&PowerCharzResult) -> Energy) -> YouRAMResult<()> {
+        let Some(powers) = self.powers.as_ref() else {
+            return Ok(());
+        };
+        let by_transition = |energy: fn(&PowerCharzResult) -> Energy| -> Vec<Vec<f64>> {
+            let values = powers.iter()
+                .map(|row| row.iter().map(|power| Self::energy_value(energy(power))).sum::<f64>() / row.len() as f64)
+                .collect();
+            vec![values]
+        };
+        let rise_power = by_transition(rise);
+        let fall_power = by_transition(fall);
+
+        self.write_line(indent, "internal_power() {")?;
+        self.write_line(indent + 1, &format!("when : \"{}\";", Sram::chip_sel_bar_pn()))?;
+
+        self.write_line(indent + 1, "rise_power(INPUT_ENERGY_TABLE) {")?;
+        self.write_values(indent + 2, &rise_power)?;
+        self.write_line(indent + 1, "}")?; // rise_power
+
+        self.write_line(indent + 1, "fall_power(INPUT_ENERGY_TABLE) {")?;
+        self.write_values(indent + 2, &fall_power)?;
+        self.write_line(indent + 1, "}")?; // fall_power
+
+        self.write_line(indent, "}")?; // internal_power
+
+        Ok(())
+    }
+
+    /// Largest capacitance of `pins`, a bus shares one pin group for all bits
+    fn write_pin_capacitance(&mut self, indent: usize, pins: &[String]) -> YouRAMResult<()> {
+        let Some(capacitances) = self.capacitances.as_ref() else {
+            return Ok(());
+        };
+        let max = |capacitance: fn(&PinCapacitance) -> Capacitance| -> f64 {
+            pins.iter()
+                .filter_map(|pin| capacitances.get(pin))
+                .map(|c| Self::cap_value(capacitance(c)))
+                .fold(0.0, f64::max)
+        };
+        let (average, rise, fall) = (max(|c| c.average()), max(|c| c.rise), max(|c| c.fall));
+
+        self.write_line(indent, &format!("capacitance : {};", average))?;
+        self.write_line(indent, &format!("rise_capacitance : {};", rise))?;
+        self.write_line(indent, &format!("fall_capacitance : {};", fall))?;
+
+        Ok(())
+    }
+
+    /// Power with `csb` high and `clk` stopped, one group for each clock level
+    fn write_leakage_power(&mut self, indent: usize) -> YouRAMResult<()> {
+        let Some(leakage) = self.leakage.clone() else {
+            return Ok(());
+        };
+
+        self.write_line(indent, &format!("cell_leakage_power : {};", Self::power_value(leakage.cell_leakage_power())))?;
+        for (state, power) in leakage.states {
+            let clock = if state.clock { "" } else { "!" };
+            self.write_line(indent, "leakage_power() {")?;
+            self.write_line(indent + 1, &format!("when : \"{} & {}{}\";", Sram::chip_sel_bar_pn(), clock, Sram::clock_pn()))?;
+            self.write_line(indent + 1, &format!("value : {};", Self::power_value(power)))?;
+            self.write_line(indent, "}")?; // leakage_power
+        }
+        self.write_enter()?;
+
+        Ok(())
+    }
+
+    /// Write and deselected cycles on `clk`, `dout` does not switch so the loads are averaged
+    fn write_clock_power(&mut self, indent: usize) -> YouRAMResult<()> {
+        let Some(powers) = self.powers.as_ref() else {
+            return Ok(());
+        };
+        let by_transition = |energy: fn(&PowerCharzResult) -> Energy| -> Vec<Vec<f64>> {
+            let values = powers.iter()
+                .map(|row| row.iter().map(|power| Self::energy_value(energy(power))).sum::<f64>() / row.len() as f64)
+                .collect();
+            vec![values]
+        };
+        let modes = [
+            (format!("!{} & {}", Sram::chip_sel_bar_pn(), Sram::write_enable_pn()), by_transition(|power| power.write)),
+            (Sram::chip_sel_bar_pn().to_string(), by_transition(|power| power.deselect)),
+        ];
+
+        for (when, rise_power) in modes {
+            self.write_line(indent, "internal_power() {")?;
+            self.write_line(indent + 1, &format!("when : \"{}\";", when))?;
+
+            self.write_line(indent + 1, "rise_power(INPUT_ENERGY_TABLE) {")?;
+            self.write_values(indent + 2, &rise_power)?;
+            self.write_line(indent + 1, "}")?; // rise_power
+
+            self.write_line(indent, "}")?; // internal_power
+        }
+
+        Ok(())
+    }
+
+    fn write_constraint_timing(&mut self, indent: usize, pin: ConstraintPin) -> YouRAMResult<()> {
+        let Some(constraint) = self.constraints.as_ref().and_then(|c| c.get(&pin)).cloned() else {
+            return self.write_dff_timing(indent);
+        };
+
+        let tables = [
+            ("setup_rising", &constraint.setup_rise, &constraint.setup_fall),
+            ("hold_rising", &constraint.hold_rise, &constraint.hold_fall),
+        ];
+        for (timing_type, rise_constraint, fall_constraint) in tables {
+            self.write_line(indent, "timing() {")?;
+
+            self.write_line(indent + 1, &format!("timing_type : {};", timing_type))?;
+            self.write_line(indent + 1, &format!("related_pin  : \"{}\";", Sram::clock_pn()))?;
+
+            self.write_line(indent + 1, "rise_constraint(CONSTRAINT_TABLE) {")?;
+            self.write_constraint_table(indent + 2, rise_constraint)?;
+            self.write_line(indent + 1, "}")?;  // rise_constraint
+
+            self.write_line(indent + 1, "fall_constraint(CONSTRAINT_TABLE) {")?;
+            self.write_constraint_table(indent + 2, fall_constraint)?;
+            self.write_line(indent + 1, "}")?;  // fall_constraint
+
+            self.write_line(indent, "}")?;  // timing
+        }
+
+        Ok(())
+    }
+
+    /// Measured table, both clk and pin are indexed by input net transitions
+    fn write_constraint_table(&mut self, indent: usize, times: &Vec<Vec<Time>>) -> YouRAMResult<()> {
+        let index: Vec<f64> = self.input_net_transitions.iter().map(|t| Self::time_value(*t)).collect();
+        self.write_index(indent, 1, &index)?;
+        self.write_index(indent, 2, &index)?;
+        self.write_values(indent, &Self::transform_times(times))?;
+        Ok(())
+    }
+
     fn write_dff_timing(&mut self, indent: usize) -> YouRAMResult<()> {
         let dff = self.pdk.get_dff(DriveStrength::X1).unwrap();
 
@@ -610,6 +1125,12 @@ impl LibertyWritor {
         Self::voltage_value(self.pvt.voltage)
     }
 
+    fn array_voltage(&self) -> f64 {
+        self.supply_voltages.get(Sram::vdd_array_pn().as_str())
+            .map(|&voltage| Self::voltage_value(voltage))
+            .unwrap_or_else(|| self.pvt_voltage())
+    }
+
     fn pvt_temp(&self) -> f64 {
         Self::temp_value(self.pvt.temperature)
     }
@@ -628,8 +1149,37 @@ impl LibertyWritor {
         cap.value().to_f64() * 1e12
     }
 
+    fn energy_value(energy: Energy) -> f64 {
+        // 1pF * 1V^2 = 1pJ
+        energy.value().to_f64() * 1e12
+    }
+
+    fn power_value(power: Power) -> f64 {
+        // 1mW
+        power.value().to_f64() * 1e3
+    }
+
     fn temp_value(temp: Temperature) -> f64 {
         temp.value().to_f64()
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use crate::pdk::{Process, Pvt};
+    use super::{corner_liberty_path, operating_conditions_name};
+
+    #[test]
+    fn test_corner_liberty_path() {
+        let pvt = Pvt::new(Process::SlowSlow, 0.9, 125.0);
+        let path = corner_liberty_path(Path::new("output/sram.lib"), &pvt);
+        assert_eq!(path, PathBuf::from(format!("output/sram_{}.lib", pvt)));
+    }
+
+    #[test]
+    fn test_operating_conditions_name() {
+        assert_eq!(operating_conditions_name(&Pvt::new(Process::SlowSlow, 0.9, 125.0)), "SS_0p9V_125C");
+        assert_eq!(operating_conditions_name(&Pvt::new(Process::FastFast, 1.25, -40.0)), "FF_1p25V_m40C");
+    }
+}
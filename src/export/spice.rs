@@ -1,20 +1,36 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use reda_sp::ToSpice;
 use tracing::{debug, info};
-use crate::circuit::{CircuitError, ShrCircuit, ShrString};
+use crate::circuit::{CircuitError, Modular, PortDirection, ShrCircuit, ShrString};
+use crate::pdk::{ParasiticConfig, ParasiticMode, Pdk};
 use crate::YouRAMResult;
 
 pub fn write_spice<P: AsRef<Path>, C: Into<ShrCircuit>>(circuit: C, path: P) -> YouRAMResult<()> {
+    write_spice_with_parasitics(circuit, path, None)
+}
+
+/// Write a netlist to simulate, with the wire RC of the pdk if it has
+pub fn write_simulation_spice<P: AsRef<Path>, C: Into<ShrCircuit>>(circuit: C, path: P, pdk: &Pdk) -> YouRAMResult<()> {
+    write_spice_with_parasitics(circuit, path, pdk.parasitics())
+}
+
+/// Like `write_spice`, and insert wire RC in leafcell arrays by `parasitics.mode`
+///
+/// An array module, whose leaves are all instances of one leafcell, is written
+/// flatten. Each of its signal ports connected to many cells is a wire through
+/// these cells, in the order they are instanced, from the port side.
+pub fn write_spice_with_parasitics<P: AsRef<Path>, C: Into<ShrCircuit>>(circuit: C, path: P, parasitics: Option<&ParasiticConfig>) -> YouRAMResult<()> {
     let circuit = circuit.into();
     let path = path.as_ref();
     info!("write circuit {} to {:?}", circuit.name(), path);
     let file = File::create(path)?;
     let mut writer = BufWriter::new(file);
     let mut exported = HashSet::new();
-    write_spice_recursive(&mut writer, &circuit, &mut exported)?;
+    let parasitics = parasitics.filter(|p| p.mode != ParasiticMode::Ideal);
+    write_spice_recursive(&mut writer, &circuit, &mut exported, parasitics)?;
     Ok(())
 }
 
@@ -23,6 +39,7 @@ fn write_spice_recursive<W: Write>(
     writer: &mut W,
     circuit: &ShrCircuit,
     exported: &mut HashSet<ShrString>,
+    parasitics: Option<&ParasiticConfig>,
 ) -> YouRAMResult<()> {
     if exported.get(&circuit.name()).is_some() {
         return Ok(());
@@ -32,20 +49,29 @@ fn write_spice_recursive<W: Write>(
         ShrCircuit::Module(module) => {
             let module_ref = module.read();
             debug!("write module {}", module_ref.name());
-        
+
+            if let Some(parasitics) = parasitics
+                && let Some(cells) = array_cells(&*module_ref)
+            {
+                write_array_with_parasitics(writer, &*module_ref, &cells, exported, parasitics)?;
+                write!(writer, "\n\n")?;
+                exported.insert(circuit.name());
+                return Ok(());
+            }
+
             for sub_circuit in module_ref.sub_circuits() {
-                write_spice_recursive(writer, sub_circuit, exported)?;
+                write_spice_recursive(writer, sub_circuit, exported, parasitics)?;
             }
-        
+
             // .SUBCKT header
             let ports = module_ref.ports();
             let port_names: Vec<_> = ports.iter().map(|p| p.read().name.to_string()).collect();
             writeln!(writer, ".SUBCKT {} {}", module_ref.name(), port_names.join(" "))?;
-        
+
             // instance
-            for inst in module_ref.instances() {            
+            for inst in module_ref.instances() {
                 let inst = inst.read();
-                
+
                 let mut pin_nets = Vec::new();
                 for pin in inst.pins.iter() {
                     let pin_ref = pin.read();
@@ -55,19 +81,19 @@ fn write_spice_recursive<W: Write>(
                         None => return Err(CircuitError::InstanceNotConnected(inst.name.to_string()))?,
                     }
                 }
-        
+
                 let subckt_name = inst.template_circuit.name();
                 writeln!(writer, "X{} {} {}", inst.name, pin_nets.join(" "), subckt_name)?;
             }
-        
+
             // connect net
             for (i, (net1, net2)) in module_ref.connected_nets().iter().enumerate() {
-                writeln!(writer, 
-                    "Rconnect{} {} {} {}", 
+                writeln!(writer,
+                    "Rconnect{} {} {} {}",
                     i, net1.read().name, net2.read().name, 0.001
                 )?;
             }
-        
+
             writeln!(writer, ".ENDS {}", module_ref.name())?;
         }
         ShrCircuit::Primitive(primitive) => {
@@ -81,3 +107,190 @@ fn write_spice_recursive<W: Write>(
     Ok(())
 }
 
+/// A leafcell instance of a flatten array, `nets` are named in the array module
+struct ArrayCell {
+    name: String,
+    template: ShrCircuit,
+    pins: Vec<String>,
+    nets: Vec<String>,
+}
+
+/// Leafcells of an array module in instance order, none if it is not
+/// an array of one leafcell
+fn array_cells(module: &dyn Modular) -> Option<Vec<ArrayCell>> {
+    let mut cells = vec![];
+    flatten_array(module, "", &HashMap::new(), &mut cells)?;
+    let template = &cells.first()?.template;
+    (cells.len() >= 2 && cells.iter().all(|cell| &cell.template == template)).then_some(cells)
+}
+
+fn flatten_array(module: &dyn Modular, prefix: &str, port_nets: &HashMap<String, String>, cells: &mut Vec<ArrayCell>) -> Option<()> {
+    if !module.connected_nets().is_empty() {
+        return None;
+    }
+    for inst in module.instances() {
+        let inst = inst.read();
+        let mut pins = vec![];
+        let mut nets = vec![];
+        for pin in inst.pins.iter() {
+            let pin = pin.read();
+            let net = pin.net.as_ref()?.read().name.to_string();
+            pins.push(pin.name.to_string());
+            nets.push(port_nets.get(&net).cloned().unwrap_or_else(|| format!("{}{}", prefix, net)));
+        }
+
+        let name = format!("{}{}", prefix, inst.name);
+        match &inst.template_circuit {
+            ShrCircuit::Module(template) => {
+                let port_nets = pins.into_iter().zip(nets).collect();
+                flatten_array(&*template.read(), &format!("{}_", name), &port_nets, cells)?;
+            }
+            ShrCircuit::Primitive(_) => {
+                cells.push(ArrayCell { name, template: inst.template_circuit.clone(), pins, nets });
+            }
+        }
+    }
+    Some(())
+}
+
+/// Write a flatten array with RC on each signal port connected to many cells
+fn write_array_with_parasitics<W: Write>(
+    writer: &mut W,
+    module: &dyn Modular,
+    cells: &[ArrayCell],
+    exported: &mut HashSet<ShrString>,
+    parasitics: &ParasiticConfig,
+) -> YouRAMResult<()> {
+    write_spice_recursive(writer, &cells[0].template, exported, None)?;
+
+    let port_names: Vec<_> = module.ports().iter().map(|p| p.read().name.to_string()).collect();
+    writeln!(writer, ".SUBCKT {} {}", module.name(), port_names.join(" "))?;
+
+    // net of each cell pin after the wire is inserted
+    let mut cell_nets: Vec<Vec<String>> = cells.iter().map(|cell| cell.nets.clone()).collect();
+    for port in module.ports().iter() {
+        let port = port.read();
+        if matches!(port.direction, PortDirection::Vdd | PortDirection::Gnd) {
+            continue;
+        }
+        let taps: Vec<(usize, usize)> = cells.iter()
+            .enumerate()
+            .flat_map(|(c, cell)| cell.nets.iter().enumerate().filter(|(_, net)| **net == *port.name).map(move |(p, _)| (c, p)))
+            .collect();
+        if taps.len() < 2 {
+            continue;
+        }
+
+        let (cell_index, pin_index) = taps[0];
+        let (resistance, capacitance) = parasitics.wire_rc(parasitics.pitch(&cells[cell_index].pins[pin_index]));
+
+        match parasitics.mode {
+            ParasiticMode::Ideal => {}
+            ParasiticMode::Lumped => {
+                let node = format!("{}_rc", port.name);
+                let count = taps.len() as f64;
+                writeln!(writer, "R{} {} {} {:.4e}", node, port.name, node, resistance * count)?;
+                writeln!(writer, "C{} {} 0 {:.4e}", node, node, capacitance * count)?;
+                for &(c, p) in taps.iter() {
+                    cell_nets[c][p] = node.clone();
+                }
+            }
+            ParasiticMode::Distributed => {
+                let mut previous = port.name.to_string();
+                for (segment, &(c, p)) in taps.iter().enumerate() {
+                    let node = format!("{}_rc{}", port.name, segment);
+                    writeln!(writer, "R{} {} {} {:.4e}", node, previous, node, resistance)?;
+                    writeln!(writer, "C{} {} 0 {:.4e}", node, node, capacitance)?;
+                    cell_nets[c][p] = node.clone();
+                    previous = node;
+                }
+            }
+        }
+    }
+
+    for (cell, nets) in cells.iter().zip(cell_nets) {
+        writeln!(writer, "X{} {} {}", cell.name, nets.join(" "), cell.template.name())?;
+    }
+    writeln!(writer, ".ENDS {}", module.name())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+    use reda_sp::Spice;
+    use crate::{circuit::{Leafcell, Module, Shr}, pdk::PdkCells};
+    use super::*;
+
+    const BITCELL: &str = "\
+.SUBCKT bitcell bl br wl vdd gnd
+MM0 Q_bar Q gnd gnd NMOS_VTL W=205n L=50n
+MM3 bl wl Q gnd NMOS_VTL W=135n L=50n
+MM2 br wl Q_bar gnd NMOS_VTL W=135n L=50n
+.ENDS bitcell
+";
+
+    /// 3 rows and 2 columns of bitcells
+    fn bitcell_array(folder: &Path) -> ShrCircuit {
+        let path = folder.join("bitcell.sp");
+        std::fs::write(&path, BITCELL).unwrap();
+        let spice = Spice::load_from(&path).unwrap();
+        let bitcell = Shr::new(Leafcell::from(PdkCells::extract_bitcell(&spice).unwrap()));
+
+        let mut module = Module::new("array_3_2", ());
+        for name in ["bl0", "bl1", "br0", "br1", "wl0", "wl1", "wl2"] {
+            let direction = if name.starts_with('w') { PortDirection::Input } else { PortDirection::InOut };
+            module.add_port(name, direction).unwrap();
+        }
+        module.add_port("vdd", PortDirection::Vdd).unwrap();
+        module.add_port("gnd", PortDirection::Gnd).unwrap();
+        for row in 0..3 {
+            for col in 0..2 {
+                let instance = module.add_instance(format!("bitcell_{}_{}", row, col), bitcell.clone()).unwrap();
+                let nets = [format!("bl{}", col), format!("br{}", col), format!("wl{}", row), "vdd".into(), "gnd".into()];
+                module.connect_instance(instance, nets.into_iter()).unwrap();
+            }
+        }
+        let module: Arc<RwLock<dyn Modular>> = Arc::new(RwLock::new(module));
+        ShrCircuit::Module(Shr::from_inner(module))
+    }
+
+    fn write(mode: ParasiticMode) -> String {
+        let temp = tempfile::tempdir().unwrap();
+        let parasitics = ParasiticConfig {
+            mode,
+            wire_resistance: 0.8,
+            wire_capacitance: 0.2,
+            bitcell_width: 0.8,
+            bitcell_height: 0.4,
+            column_pins: vec!["bl".into(), "br".into()],
+        };
+        let path = temp.path().join("array.sp");
+        write_spice_with_parasitics(bitcell_array(temp.path()), &path, Some(&parasitics)).unwrap();
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_parasitics() {
+        let ideal = write(ParasiticMode::Ideal);
+        assert!(ideal.contains("Xbitcell_2_1 bl1 br1 wl2 vdd gnd bitcell\n"));
+        assert!(!ideal.contains("\nR"));
+
+        // 3 rows of 0.4um bitline, 2 columns of 0.8um wordline
+        let lumped = write(ParasiticMode::Lumped);
+        assert!(lumped.contains("Rbl0_rc bl0 bl0_rc 9.6000e-1\n"));
+        assert!(lumped.contains("Cbl0_rc bl0_rc 0 2.4000e-16\n"));
+        assert!(lumped.contains("Rwl2_rc wl2 wl2_rc 1.2800e0\n"));
+        assert!(lumped.contains("Xbitcell_2_1 bl1_rc br1_rc wl2_rc vdd gnd bitcell\n"));
+
+        let distributed = write(ParasiticMode::Distributed);
+        assert!(distributed.contains("Rbl1_rc0 bl1 bl1_rc0 3.2000e-1\n"));
+        assert!(distributed.contains("Rbl1_rc2 bl1_rc1 bl1_rc2 3.2000e-1\n"));
+        assert!(distributed.contains("Cwl0_rc1 wl0_rc1 0 1.6000e-16\n"));
+        assert!(distributed.contains("Xbitcell_0_0 bl0_rc0 br0_rc0 wl0_rc0 vdd gnd bitcell\n"));
+        assert!(distributed.contains("Xbitcell_2_1 bl1_rc2 br1_rc2 wl2_rc1 vdd gnd bitcell\n"));
+        // 4 bitlines of 3 segments, 3 wordlines of 2 segments
+        assert_eq!(distributed.lines().filter(|l| l.starts_with('R')).count(), 18);
+        assert_eq!(distributed.matches(".SUBCKT bitcell ").count(), 1);
+    }
+}
@@ -1,14 +1,14 @@
-use std::{path::{Path, PathBuf}, sync::Arc};
-use reda_unit::{t, Time};
+use std::{collections::BTreeMap, path::{Path, PathBuf}, sync::Arc};
+use reda_unit::{t, Time, Voltage};
 use serde::{Deserialize, Serialize};
 use tracing::{info, Level};
 use clap::Parser;
 use youram::{
-    charz::{FunctionCharz, FunctionCharzPolicy, RandomPolicy}, 
-    circuit::{CircuitFactory, SramArg}, 
+    charz::{BitcellMarginCharz, FunctionCharz, FunctionCharzPolicy, RandomPolicy, TimingAddressMode, VminCharz}, 
+    circuit::{CircuitFactory, Sram, SramArg}, 
     export, 
-    pdk::{Enviroment, Pdk}, 
-    simulate::{SpiceCommand, NgSpice}, 
+    pdk::{Enviroment, ParasiticMode, Pdk, Pvt, TimingIndices}, 
+    simulate::{CachedCommand, HSpice, NgSpice, RetryCommand, RetryPolicy, SimulateCache, Spectre, SpiceCommand, SwitchLevel, Xyce}, 
     ErrorContext
 };
 
@@ -28,24 +28,51 @@ fn main_result() -> Result<(), Box<dyn std::error::Error>> {
     };
     config.create_output_path()?;
 
+    // simulate results are reused between runs unless `--no-cache`
+    let cache = SimulateCache::new(config.cache_folder_path());
+    if args.clear_cache {
+        cache.clear()?;
+        info!("cleared simulate cache: {:?}", cache.folder);
+    }
+    let spice_command = || -> Result<Box<dyn SpiceCommand>, Box<dyn std::error::Error>> {
+        let command = Box::new(RetryCommand::new(config.spice_command()?, config.retry.clone()));
+        if args.no_cache {
+            Ok(command)
+        } else {
+            Ok(Box::new(CachedCommand::new(command, cache.clone())))
+        }
+    };
+
     // load pdk
-    let pdk = Arc::new(Pdk::load(&config.pdk_path).context("load pdk")?);
+    let mut pdk = Pdk::load(&config.pdk_path).context("load pdk")?;
+    if let Some(timing_indices) = &config.timing_indices {
+        pdk.override_timing_indices(timing_indices).context("override timing indices")?;
+    }
+    if let Some(mode) = config.parasitic_mode {
+        pdk.override_parasitic_mode(mode).context("override parasitic mode")?;
+    }
+    let pdk = Arc::new(pdk);
     
     // create sram
     let mut factory = CircuitFactory::new(pdk.clone());
-    let sram = factory.module(SramArg::new(config.address_width, config.word_width)).context("create sram")?;
+    let sram_arg = SramArg {
+        dual_supply: config.array_voltage.is_some(),
+        ..SramArg::new(config.address_width, config.word_width)
+    };
+    let sram = factory.module(sram_arg).context("create sram")?;
 
     // test sram
     if let Some(function_test) = &config.function_test {
         let policy = parse_function_test_policy(&function_test)?;
         let period = config.period;
-        let command = config.spice_command()?;
+        let command = spice_command()?;
 
         // load simulate config in pdk
         let pvt = pdk.pvt();
         let output_load = pdk.default_fanout_load().unwrap_or(0.0.into());
         let input_slew = period / 20.0;
-        let env = Enviroment::new(pvt.clone(), input_slew, output_load);
+        let env = Enviroment::new(pvt.clone(), input_slew, output_load)
+            .with_supply_voltages(config.supply_voltages());
 
         // run functional test
         FunctionCharz::config()
@@ -59,6 +86,39 @@ fn main_result() -> Result<(), Box<dyn std::error::Error>> {
             .test()?;
     }
 
+    // vmin of read and write, by the function test policy
+    if config.characterize_vmin {
+        let mut vmin = VminCharz::config()
+            .sram(sram.clone())
+            .pdk(pdk.clone())
+            .pvt(pdk.pvt().clone())
+            .period(config.period)
+            .supply_voltages(config.supply_voltages())
+            .command_box(spice_command()?)
+            .temp_folder(config.temp_folder_path());
+        if let Some(function_test) = &config.function_test {
+            vmin = vmin.policy_box(parse_function_test_policy(function_test)?);
+        }
+        vmin.analyze()?;
+    }
+
+    // bitcell margins
+    if config.analyze_bitcell_margin {
+        let mut margin = BitcellMarginCharz::config()
+            .pdk(pdk.clone())
+            .pvt(pdk.pvt().clone())
+            .command_box(spice_command()?)
+            .temp_folder(config.temp_folder_path())
+            .csv_folder(config.join_output("margin"));
+        if let Some(array_voltage) = config.array_voltage {
+            margin = margin.cell_voltage(array_voltage);
+        }
+        if let Some(jobs) = config.jobs {
+            margin = margin.jobs(jobs);
+        }
+        margin.analyze()?;
+    }
+
     // write
     if config.export_spice {
         let spice_file = config.join_output(format!("{}.sp", sram.read().name));
@@ -72,14 +132,32 @@ fn main_result() -> Result<(), Box<dyn std::error::Error>> {
 
     if config.export_liberty {
         let liberty_file = config.join_output(format!("{}.lib", sram.read().name));
-        let command = config.spice_command()?;
-        export::write_liberty(
+        let command = spice_command()?;
+        let mut options = export::LibertyOptions::default().supply_voltages(config.supply_voltages());
+        if let Some(jobs) = config.jobs {
+            options = options.jobs(jobs);
+        }
+        options = options
+            .characterize_constraints(config.characterize_constraints)
+            .characterize_power(config.characterize_power)
+            .characterize_leakage(config.characterize_leakage)
+            .characterize_min_period(config.characterize_min_period)
+            .characterize_capacitance(config.characterize_capacitance)
+            .pvts(config.pvts.iter().cloned());
+        if let Some(margin) = config.min_period_margin {
+            options = options.min_period_margin(margin);
+        }
+        if config.average_timing {
+            options = options.timing_address_mode(TimingAddressMode::RandomAverage);
+        }
+        export::write_liberty_with_options(
             sram.clone(), 
             liberty_file, 
             config.period, 
             pdk.clone(), 
             command, 
-            config.temp_folder_path()
+            config.temp_folder_path(),
+            options,
         )?;
     }
 
@@ -104,6 +182,14 @@ struct Args {
     /// Enable verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Always run the simulator, ignore and don't update the simulate cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Remove all cached simulate results before running
+    #[arg(long)]
+    clear_cache: bool,
 }
 
 impl Args {
@@ -136,6 +222,63 @@ pub struct Config {
 
     #[serde(default = "const_false")]
     pub export_liberty: bool,
+
+    /// Characterize setup/hold of sram inputs for liberty, instead of copying the dff's tables
+    #[serde(default = "const_false")]
+    pub characterize_constraints: bool,
+
+    /// Characterize read/write energy as liberty internal power
+    #[serde(default = "const_false")]
+    pub characterize_power: bool,
+
+    /// Characterize idle supply power as liberty leakage power
+    #[serde(default = "const_false")]
+    pub characterize_leakage: bool,
+
+    /// Search the min clock period under `period` and write it to liberty
+    #[serde(default = "const_false")]
+    pub characterize_min_period: bool,
+
+    /// Characterize timing tables at the searched min period times this margin, at least 1
+    pub min_period_margin: Option<f64>,
+
+    /// Characterize input pin capacitance for liberty pins
+    #[serde(default = "const_false")]
+    pub characterize_capacitance: bool,
+
+    /// Average delays of random addresses for liberty tables, instead of the max of the
+    /// farthest cell in each core
+    #[serde(default = "const_false")]
+    pub average_timing: bool,
+
+    /// Characterize liberty at these corners into `<name>_<pvt>.lib` each, instead of
+    /// only the pdk's pvt into `<name>.lib`
+    #[serde(default)]
+    pub pvts: Vec<Pvt>,
+
+    /// Search the lowest vdd where reads and writes of `function_test` policy still pass
+    #[serde(default = "const_false")]
+    pub characterize_vmin: bool,
+
+    /// Analyze hold/read SNM and write margin of the pdk bitcell, curves are written in `<output>/margin`
+    #[serde(default = "const_false")]
+    pub analyze_bitcell_margin: bool,
+
+    /// Override the pdk's slew/load indices of timing tables
+    pub timing_indices: Option<TimingIndices>,
+
+    /// Override how the pdk's wire RC is written in simulated netlists
+    pub parasitic_mode: Option<ParasiticMode>,
+
+    /// Power bitcell array by a separate `vdd_array` supply with this voltage
+    pub array_voltage: Option<Voltage>,
+
+    /// Max concurrent simulations of characterization, default is number of cpu
+    pub jobs: Option<usize>,
+
+    /// Timeout of each simulation and relaxed options to retry a failed convergence
+    #[serde(default)]
+    pub retry: RetryPolicy,
 }
 
 fn parse_function_test_policy(policy: &str) -> Result<Box<dyn FunctionCharzPolicy>, Box<dyn std::error::Error>> {
@@ -167,13 +310,27 @@ impl Config {
         self.output_path.join("temp")
     }
 
+    pub fn cache_folder_path(&self) -> PathBuf {
+        self.output_path.join("cache")
+    }
+
     pub fn join_output(&self, path: impl AsRef<Path>) -> PathBuf {
         self.output_path.join(path.as_ref())
     }
 
+    pub fn supply_voltages(&self) -> BTreeMap<String, Voltage> {
+        self.array_voltage.iter()
+            .map(|&voltage| (Sram::vdd_array_pn().to_string(), voltage))
+            .collect()
+    }
+
     pub fn spice_command(&self) -> Result<Box<dyn SpiceCommand>, Box<dyn std::error::Error>> {
         match self.spice_command.as_str() {
             "ngspice" => Ok(Box::new(NgSpice)),
+            "spectre" => Ok(Box::new(Spectre::default())),
+            "xyce" => Ok(Box::new(Xyce::default())),
+            "hspice" => Ok(Box::new(HSpice::default())),
+            "switch" => Ok(Box::new(SwitchLevel)),
             _ => Err(format!("Un support spice executor: {}", self.spice_command.as_str()))?,
         }
     }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use reda_lib::model::{LibCell, LibExpr, LibLibrary, LibPgType, LibPinDirection, LibTimingType};
 use reda_sp::Spice;
-use crate::{circuit::{Bitcell, ColumnTriGate, Dff, DriveStrength, Leafcell, LogicGate, LogicGateKind, Port, PortDirection, Precharge, SenseAmp, Shr, WriteDriver, BITCELL_NAME, COLUMN_TRI_GATE_NAME, PRECHARGE_NAME, SENSE_AMP_NAME, WRITE_DRIVER_NAME}, ErrorContext, YouRAMResult};
+use crate::{circuit::{Bitcell, ColumnTriGate, Dff, DriveStrength, Leafcell, LevelShifter, LogicGate, LogicGateKind, Port, PortDirection, Precharge, SenseAmp, Shr, WriteDriver, BITCELL_NAME, COLUMN_TRI_GATE_NAME, LEVEL_SHIFTER_NAME, PRECHARGE_NAME, SENSE_AMP_NAME, WRITE_DRIVER_NAME}, ErrorContext, YouRAMResult};
 use super::PdkError;
 
 pub struct PdkCells {
@@ -12,6 +12,8 @@ pub struct PdkCells {
     pub write_driver: Shr<Leafcell>,
     pub column_trigate: Shr<Leafcell>,
     pub precharge: Shr<Leafcell>,
+    /// Only needed by srams with a separate array supply
+    pub level_shifter: Option<Shr<Leafcell>>,
 }
 
 impl PdkCells {
@@ -40,6 +42,8 @@ impl PdkCells {
             = Shr::new(Self::extract_column_trigate(&leafcell_spice).context("extract column_trigate")?.into());    
         let precharge
             = Shr::new(Self::extract_precharge(&leafcell_spice).context("extract precharge")?.into()); 
+        let level_shifter
+            = Self::extract_level_shifter(leafcell_spice).context("extract level_shifter")?.map(|l| Shr::new(l.into()));
 
         Ok(Self {
             logicgates,
@@ -49,6 +53,7 @@ impl PdkCells {
             write_driver,
             column_trigate,
             precharge,
+            level_shifter,
         })   
     }
 }
@@ -151,6 +156,26 @@ impl PdkCells {
 
         Ok(Precharge::new(bl, br, enable, vdd, subckt))   
     }
+
+    /// Level shifter is optional, return `None` if pdk doesn't provide it
+    pub fn extract_level_shifter(spice: &Spice) -> Result<Option<LevelShifter>, PdkError> {
+        let subckt = match spice.subckts.iter().find(|s| s.name == LEVEL_SHIFTER_NAME) {
+            Some(subckt) => subckt.clone(),
+            None => return Ok(None),
+        };
+
+        if subckt.ports.len() != 5 {
+            return Err(PdkError::UnmatchLeafCellPinSize(5, subckt.ports.len(), LEVEL_SHIFTER_NAME));
+        }
+
+        let input    = Port::new(subckt.ports[0].clone(), PortDirection::Input);
+        let output   = Port::new(subckt.ports[1].clone(), PortDirection::Output);
+        let vdd      = Port::new(subckt.ports[2].clone(), PortDirection::Vdd);
+        let vdd_high = Port::new(subckt.ports[3].clone(), PortDirection::Vdd);
+        let gnd      = Port::new(subckt.ports[4].clone(), PortDirection::Gnd);
+
+        Ok(Some(LevelShifter::new(input, output, vdd, vdd_high, gnd, subckt)))
+    }
 }
 
 impl PdkCells {
@@ -427,4 +452,15 @@ mod tests {
         // 嵌套 NOR
         assert_eq!(str_to_kind("!((A1 | A2) | A3)").unwrap(), LogicGateKind::Nor(3));
     }
+
+    #[test]
+    fn test_extract_level_shifter() {
+        let spice = Spice::load_from("./platforms/nangate45/leafcell.cdl").unwrap();
+        let level_shifter = PdkCells::extract_level_shifter(&spice).unwrap().unwrap();
+        assert_eq!(level_shifter.ports.len(), 5);
+        assert_eq!(level_shifter.ports[3].read().direction, PortDirection::Vdd);
+
+        let spice = Spice::load_from("./platforms/nangate45/stdcell.cdl").unwrap();
+        assert!(PdkCells::extract_level_shifter(&spice).unwrap().is_none());
+    }
 }
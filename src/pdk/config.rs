@@ -1,7 +1,8 @@
-use std::{collections::HashMap, path::{Path, PathBuf}};
+use std::{collections::{BTreeMap, HashMap}, path::{Path, PathBuf}};
+use reda_unit::{Capacitance, Time, Unit, UnitNumber, Voltage};
 use serde::{Deserialize, Serialize};
-use crate::{ErrorContext, YouRAMResult};
-use super::Process;
+use crate::{circuit::{BITCELL_NAME, SENSE_AMP_NAME}, ErrorContext, YouRAMResult};
+use super::{PdkError, Process};
 
 pub const PDK_CONFIG: &'static str = "config.json";
 
@@ -12,14 +13,273 @@ pub struct PdkConfig {
 
     pub stdcell_liberty: PathBuf,
     pub stdcell_spice: PathBuf,
-    pub leafcell_spice: PathBuf,
-    pub models: HashMap<Process, PdkModelPath>,
+    /// Hand-written leafcells, cells here replace the generated ones
+    #[serde(default)]
+    pub leafcell_spice: Option<PathBuf>,
+    /// Transistor sizing to generate leafcells, see `LeafcellSizing`
+    #[serde(default)]
+    pub leafcell_sizing: Option<PathBuf>,
+    pub models: HashMap<Process, PdkModel>,
+
+    /// Slew/load indices of characterization tables, extracted from `INV_X1` if not set
+    #[serde(default)]
+    pub timing_indices: Option<TimingIndices>,
+
+    /// Transistor mismatch of leafcells for Monte Carlo, see `MismatchConfig`
+    #[serde(default)]
+    pub mismatch: Option<MismatchConfig>,
+
+    /// Wire RC of arrays in simulated netlists, see `ParasiticConfig`
+    #[serde(default)]
+    pub parasitics: Option<ParasiticConfig>,
 }
 
+/// Model description of one process corner
+/// 
+/// ```json
+/// "TT" : { "nmos": "model/nom/nmos.sp", "pmos": "model/nom/pmos.sp" }
+/// "TT" : { "library": "models.lib", "section": "tt", "includes": ["extra.sp"], "params": { "mismatch": "0" } }
+/// ```
 #[derive(Debug, Serialize, Deserialize)]
-pub struct PdkModelPath {
-    pub nmos: PathBuf,
-    pub pmos: PathBuf, 
+pub struct PdkModel {
+    #[serde(flatten)]
+    pub source: PdkModelSource,
+
+    /// Extra files included after the model source
+    #[serde(default)]
+    pub includes: Vec<PathBuf>,
+
+    /// Extra `.param` values defined before the model source
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PdkModelSource {
+    /// Separate include file for nmos and pmos
+    Includes { nmos: PathBuf, pmos: PathBuf },
+    /// A single model library with `.lib` sections
+    Library { library: PathBuf, section: String },
+}
+
+/// A statement to load the models of a corner in the simulate deck
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelStatement {
+    Param { name: String, value: String },
+    Include(PathBuf),
+    Library { path: PathBuf, section: String },
+}
+
+/// Slew/load indices used by timing characterization
+/// 
+/// Explicit vectors replace the extracted ones, counts resample them
+/// between their first and last value:
+/// 
+/// ```json
+/// "timing_indices": {
+///     "input_net_transitions": ["5ps", "50ps", "200ps"],
+///     "output_net_capacitance_count": 4
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimingIndices {
+    #[serde(default)]
+    pub input_net_transitions: Option<Vec<Time>>,
+    #[serde(default)]
+    pub output_net_capacitances: Option<Vec<Capacitance>>,
+    #[serde(default)]
+    pub input_net_transition_count: Option<usize>,
+    #[serde(default)]
+    pub output_net_capacitance_count: Option<usize>,
+}
+
+impl TimingIndices {
+    /// Whether both vectors are given, so no base indices are needed
+    pub fn is_explicit(&self) -> bool {
+        self.input_net_transitions.is_some() && self.output_net_capacitances.is_some()
+    }
+
+    /// Apply this config on the base indices
+    pub fn resolve(&self, input_net_transitions: &[Time], output_net_capacitances: &[Capacitance]) -> Result<(Vec<Time>, Vec<Capacitance>), PdkError> {
+        let input_net_transitions = Self::resolve_index(
+            "input_net_transitions",
+            input_net_transitions, 
+            self.input_net_transitions.as_deref(), 
+            self.input_net_transition_count,
+        )?;
+        let output_net_capacitances = Self::resolve_index(
+            "output_net_capacitances",
+            output_net_capacitances, 
+            self.output_net_capacitances.as_deref(), 
+            self.output_net_capacitance_count,
+        )?;
+
+        Ok((input_net_transitions, output_net_capacitances))
+    }
+
+    fn resolve_index<U: Unit>(
+        name: &'static str,
+        base: &[UnitNumber<U>], 
+        explicit: Option<&[UnitNumber<U>]>, 
+        count: Option<usize>
+    ) -> Result<Vec<UnitNumber<U>>, PdkError> {
+        let values = explicit.unwrap_or(base);
+        if values.is_empty() {
+            return Err(PdkError::InvalidTimingIndex(name, "no index value".into()));
+        }
+        if values.windows(2).any(|w| w[0].to_f64() >= w[1].to_f64()) {
+            return Err(PdkError::InvalidTimingIndex(name, "index values must be strictly increasing".into()));
+        }
+
+        match count {
+            None => Ok(values.to_vec()),
+            Some(0) => Err(PdkError::InvalidTimingIndex(name, "index count is 0".into())),
+            Some(1) => Ok(vec![values[0]]),
+            Some(count) => {
+                // linear resample over the position of the original values
+                let last = (values.len() - 1) as f64;
+                let resampled = (0..count).map(|i| {
+                    let position = i as f64 * last / (count - 1) as f64;
+                    let low = position.floor() as usize;
+                    let high = position.ceil() as usize;
+                    let ratio = position - low as f64;
+                    let (low, high) = (values[low].to_f64(), values[high].to_f64());
+                    UnitNumber::from(low + (high - low) * ratio)
+                }).collect();
+                Ok(resampled)
+            }
+        }
+    }
+}
+
+/// Local threshold voltage variation of leafcell transistors, used by Monte Carlo
+///
+/// Each transistor in `cells` gets a gaussian vth shift, its sigma is `sigma_vth`,
+/// or Pelgrom's `avt / sqrt(W * L)` with `avt` in mV·µm:
+///
+/// ```json
+/// "mismatch": {
+///     "cells": ["bitcell", "sense_amp"],
+///     "models": {
+///         "nmos_vtl": { "avt": 3.5 },
+///         "pmos_vtl": { "sigma_vth": "20mV" }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MismatchConfig {
+    /// Leafcells to vary, default bitcell and sense amp
+    #[serde(default = "MismatchConfig::default_cells")]
+    pub cells: Vec<String>,
+    /// Transistor instance parameter shifting vth, default `delvto` of bsim models
+    #[serde(default = "MismatchConfig::default_parameter")]
+    pub parameter: String,
+    /// Variation of each transistor model, case insensitive. Transistors of other models are not varied
+    pub models: BTreeMap<String, ModelMismatch>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelMismatch {
+    #[serde(default)]
+    pub sigma_vth: Option<Voltage>,
+    /// Pelgrom coefficient in mV·µm, used when `sigma_vth` is not set
+    #[serde(default)]
+    pub avt: Option<f64>,
+}
+
+impl MismatchConfig {
+    fn default_cells() -> Vec<String> {
+        vec![BITCELL_NAME.to_string(), SENSE_AMP_NAME.to_string()]
+    }
+
+    fn default_parameter() -> String {
+        "delvto".to_string()
+    }
+
+    pub fn is_varied_cell(&self, name: &str) -> bool {
+        self.cells.iter().any(|cell| cell.eq_ignore_ascii_case(name))
+    }
+
+    /// Sigma of vth shift in volt of a `model` transistor, `width` and `length` in meter.
+    /// None if the model is not varied or has no size for Pelgrom's law
+    pub fn sigma_vth(&self, model: &str, width: Option<f64>, length: Option<f64>) -> Option<f64> {
+        let mismatch = self.models.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(model))
+            .map(|(_, mismatch)| mismatch)?;
+        if let Some(sigma) = mismatch.sigma_vth {
+            return Some(sigma.to_f64());
+        }
+        match (mismatch.avt, width, length) {
+            // mV·µm -> V·m
+            (Some(avt), Some(w), Some(l)) if w > 0.0 && l > 0.0 => Some(avt * 1e-9 / (w * l).sqrt()),
+            _ => None,
+        }
+    }
+}
+
+/// Wire RC of bitlines, wordlines and array control nets
+///
+/// A wire crossing `n` cells is `n` cell pitches long, wires of `column_pins`
+/// (default the bitlines `bl` and `br`) run along `bitcell_height` and others along `bitcell_width`:
+///
+/// ```json
+/// "parasitics": {
+///     "mode": "distributed",
+///     "wire_resistance": 0.8,
+///     "wire_capacitance": 0.2,
+///     "bitcell_width": 0.8,
+///     "bitcell_height": 0.4
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParasiticConfig {
+    #[serde(default)]
+    pub mode: ParasiticMode,
+    /// Ω per µm
+    pub wire_resistance: f64,
+    /// fF per µm
+    pub wire_capacitance: f64,
+    /// µm
+    pub bitcell_width: f64,
+    /// µm
+    pub bitcell_height: f64,
+    /// Cell pins whose wires run along the column
+    #[serde(default = "ParasiticConfig::default_column_pins")]
+    pub column_pins: Vec<String>,
+}
+
+/// How wire RC is written in a netlist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParasiticMode {
+    /// Ideal nets, no RC
+    #[default]
+    Ideal,
+    /// One resistor and capacitor of the whole wire, cells at its far end
+    Lumped,
+    /// A RC segment of each cell pitch, each cell at its own segment
+    Distributed,
+}
+
+impl ParasiticConfig {
+    fn default_column_pins() -> Vec<String> {
+        vec!["bl".to_string(), "br".to_string()]
+    }
+
+    /// Pitch in µm of a wire connecting `pin` of each cell
+    pub fn pitch(&self, pin: &str) -> f64 {
+        if self.column_pins.iter().any(|column_pin| column_pin == pin) {
+            self.bitcell_height
+        } else {
+            self.bitcell_width
+        }
+    }
+
+    /// Resistance in Ω and capacitance in F of a wire `length` µm long
+    pub fn wire_rc(&self, length: f64) -> (f64, f64) {
+        (self.wire_resistance * length, self.wire_capacitance * length * 1e-15)
+    }
 }
 
 impl PdkConfig {
@@ -34,15 +294,60 @@ impl PdkConfig {
     }
 
     pub fn nmos_model_path(&self, process: Process) -> Option<PathBuf> {
-        let models = self.models.get(&process)?;
-        Some(self.json_to_pdk(&models.nmos))
+        match &self.models.get(&process)?.source {
+            PdkModelSource::Includes { nmos, .. } => Some(self.json_to_pdk(nmos)),
+            PdkModelSource::Library { .. } => None,
+        }
     }
 
     pub fn pmos_model_path(&self, process: Process) -> Option<PathBuf> {
-        let models = self.models.get(&process)?;
-        Some(self.json_to_pdk(&models.pmos))
+        match &self.models.get(&process)?.source {
+            PdkModelSource::Includes { pmos, .. } => Some(self.json_to_pdk(pmos)),
+            PdkModelSource::Library { .. } => None,
+        }
     }
 
+    /// All statements to load the models of `process`, in writing order: params, model source, extra includes
+    pub fn model_statements(&self, process: Process) -> Option<Vec<ModelStatement>> {
+        let model = self.models.get(&process)?;
+        let mut statements = vec![];
+
+        for (name, value) in model.params.iter() {
+            statements.push(ModelStatement::Param { name: name.clone(), value: value.clone() });
+        }
+
+        match &model.source {
+            PdkModelSource::Includes { nmos, pmos } => {
+                statements.push(ModelStatement::Include(self.json_to_pdk(nmos)));
+                statements.push(ModelStatement::Include(self.json_to_pdk(pmos)));
+            }
+            PdkModelSource::Library { library, section } => {
+                statements.push(ModelStatement::Library { path: self.json_to_pdk(library), section: section.clone() });
+            }
+        }
+
+        for include in model.includes.iter() {
+            statements.push(ModelStatement::Include(self.json_to_pdk(include)));
+        }
+
+        Some(statements)
+    }
+
+    /// Names of the nmos and pmos `.model` in the model files of `process`
+    pub fn mos_model_names(&self, process: Process) -> Option<(String, String)> {
+        let (mut nmos, mut pmos) = (None, None);
+        for statement in self.model_statements(process)? {
+            let path = match statement {
+                ModelStatement::Include(path) | ModelStatement::Library { path, .. } => path,
+                ModelStatement::Param { .. } => continue,
+            };
+            let Ok(content) = std::fs::read_to_string(&path) else { continue };
+            let (n, p) = parse_mos_model_names(&content);
+            nmos = nmos.or(n);
+            pmos = pmos.or(p);
+        }
+        Some((nmos?, pmos?))
+    }
 
     pub fn stdcell_liberty_path(&self) -> PathBuf {
         self.json_to_pdk(&self.stdcell_liberty)
@@ -52,12 +357,178 @@ impl PdkConfig {
         self.json_to_pdk(&self.stdcell_spice)
     }
 
-    pub fn leafcell_spice_path(&self) -> PathBuf {
-        self.json_to_pdk(&self.leafcell_spice)
+    pub fn leafcell_spice_path(&self) -> Option<PathBuf> {
+        self.leafcell_spice.as_ref().map(|path| self.json_to_pdk(path))
+    }
+
+    pub fn leafcell_sizing_path(&self) -> Option<PathBuf> {
+        self.leafcell_sizing.as_ref().map(|path| self.json_to_pdk(path))
     }
 
     #[inline]
     fn json_to_pdk(&self, sub_path: impl AsRef<Path>) -> PathBuf {
         self.pdk_path.join(sub_path.as_ref())
     }
-}
\ No newline at end of file
+}
+
+/// First `.model <name> nmos|pmos` of each kind in a spice model file
+fn parse_mos_model_names(content: &str) -> (Option<String>, Option<String>) {
+    let (mut nmos, mut pmos) = (None, None);
+    for line in content.lines() {
+        let mut tokens = line.split_whitespace();
+        if !tokens.next().is_some_and(|t| t.eq_ignore_ascii_case(".model")) {
+            continue;
+        }
+        let (Some(name), Some(kind)) = (tokens.next(), tokens.next()) else { continue };
+        let kind = kind.split('(').next().unwrap_or_default().to_ascii_lowercase();
+        match kind.as_str() {
+            "nmos" => { nmos.get_or_insert_with(|| name.to_string()); }
+            "pmos" => { pmos.get_or_insert_with(|| name.to_string()); }
+            _ => {}
+        }
+    }
+    (nmos, pmos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_config(content: &str) -> PdkConfig {
+        let mut config: PdkConfig = serde_json::from_str(content).unwrap();
+        config.pdk_path = "/pdk".into();
+        config
+    }
+
+    #[test]
+    fn test_include_models() {
+        let config = parse_config(r#"{
+            "stdcell_spice": "stdcell.cdl",
+            "stdcell_liberty": "stdcell.lib",
+            "leafcell_spice": "leafcell.cdl",
+            "models" : {
+                "TT" : { "nmos": "model/nom/nmos.sp", "pmos": "model/nom/pmos.sp" }
+            }
+        }"#);
+
+        assert_eq!(config.nmos_model_path(Process::TypeType), Some("/pdk/model/nom/nmos.sp".into()));
+        assert_eq!(config.pmos_model_path(Process::TypeType), Some("/pdk/model/nom/pmos.sp".into()));
+        assert_eq!(config.model_statements(Process::TypeType).unwrap(), vec![
+            ModelStatement::Include("/pdk/model/nom/nmos.sp".into()),
+            ModelStatement::Include("/pdk/model/nom/pmos.sp".into()),
+        ]);
+        assert!(config.model_statements(Process::FastFast).is_none());
+        assert!(config.timing_indices.is_none());
+        assert!(config.mismatch.is_none());
+        assert!(config.parasitics.is_none());
+    }
+
+    #[test]
+    fn test_parasitics() {
+        let parasitics: ParasiticConfig = serde_json::from_str(r#"{
+            "wire_resistance": 0.8,
+            "wire_capacitance": 0.2,
+            "bitcell_width": 0.8,
+            "bitcell_height": 0.4
+        }"#).unwrap();
+        assert_eq!(parasitics.mode, ParasiticMode::Ideal);
+
+        assert_eq!(parasitics.pitch("bl"), 0.4);
+        assert_eq!(parasitics.pitch("wl"), 0.8);
+
+        let (r, c) = parasitics.wire_rc(10.0);
+        assert!((r - 8.0).abs() < 1e-12);
+        assert!((c - 2e-15).abs() < 1e-27);
+
+        let mode: ParasiticMode = serde_json::from_str(r#""distributed""#).unwrap();
+        assert_eq!(mode, ParasiticMode::Distributed);
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let config = parse_config(r#"{
+            "stdcell_spice": "stdcell.cdl",
+            "stdcell_liberty": "stdcell.lib",
+            "models" : {
+                "TT" : { "nmos": "model/nom/nmos.sp", "pmos": "model/nom/pmos.sp" }
+            },
+            "mismatch": {
+                "models": {
+                    "nmos_vtl": { "avt": 3.5 },
+                    "pmos_vtl": { "sigma_vth": "20mV" }
+                }
+            }
+        }"#);
+
+        let mismatch = config.mismatch.unwrap();
+        assert!(mismatch.is_varied_cell("bitcell"));
+        assert!(mismatch.is_varied_cell("SENSE_AMP"));
+        assert!(!mismatch.is_varied_cell("precharge"));
+        assert_eq!(mismatch.parameter, "delvto");
+
+        // 3.5mV·µm / sqrt(0.1µm * 0.05µm)
+        let sigma = mismatch.sigma_vth("NMOS_VTL", Some(100e-9), Some(50e-9)).unwrap();
+        assert!((sigma - 3.5e-3 / (0.1f64 * 0.05).sqrt()).abs() < 1e-12);
+        assert!(mismatch.sigma_vth("nmos_vtl", None, Some(50e-9)).is_none());
+        assert!((mismatch.sigma_vth("pmos_vtl", None, None).unwrap() - 0.02).abs() < 1e-12);
+        assert!(mismatch.sigma_vth("nmos_hvt", Some(100e-9), Some(50e-9)).is_none());
+    }
+
+    #[test]
+    fn test_timing_indices() {
+        let base_slews = [Time::from(1e-12), Time::from(2e-12), Time::from(4e-12)];
+        let base_loads = [Capacitance::from(1e-15), Capacitance::from(3e-15)];
+
+        let indices: TimingIndices = serde_json::from_str(r#"{ "output_net_capacitance_count": 3 }"#).unwrap();
+        let (slews, loads) = indices.resolve(&base_slews, &base_loads).unwrap();
+        assert_eq!(slews, base_slews.to_vec());
+        assert_eq!(loads.len(), 3);
+        assert!((loads[1].to_f64() - 2e-15).abs() < 1e-24);
+
+        let indices: TimingIndices = serde_json::from_str(r#"{ 
+            "input_net_transitions": ["5ps", "50ps", "200ps"],
+            "input_net_transition_count": 5
+        }"#).unwrap();
+        let (slews, _) = indices.resolve(&base_slews, &base_loads).unwrap();
+        assert_eq!(slews.len(), 5);
+        assert!((slews[1].to_f64() - 27.5e-12).abs() < 1e-21);
+        assert!((slews[4].to_f64() - 200e-12).abs() < 1e-21);
+
+        let indices: TimingIndices = serde_json::from_str(r#"{ "input_net_transitions": ["50ps", "5ps"] }"#).unwrap();
+        assert!(indices.resolve(&base_slews, &base_loads).is_err());
+
+        let indices: TimingIndices = serde_json::from_str(r#"{ "output_net_capacitance_count": 0 }"#).unwrap();
+        assert!(indices.resolve(&base_slews, &base_loads).is_err());
+    }
+
+    #[test]
+    fn test_library_models() {
+        let config = parse_config(r#"{
+            "stdcell_spice": "stdcell.cdl",
+            "stdcell_liberty": "stdcell.lib",
+            "leafcell_spice": "leafcell.cdl",
+            "models" : {
+                "SS" : { 
+                    "library": "models.lib", 
+                    "section": "ss", 
+                    "includes": ["extra.sp"], 
+                    "params": { "vth_shift": "0.01" } 
+                }
+            }
+        }"#);
+
+        assert_eq!(config.nmos_model_path(Process::SlowSlow), None);
+        assert_eq!(config.model_statements(Process::SlowSlow).unwrap(), vec![
+            ModelStatement::Param { name: "vth_shift".into(), value: "0.01".into() },
+            ModelStatement::Library { path: "/pdk/models.lib".into(), section: "ss".into() },
+            ModelStatement::Include("/pdk/extra.sp".into()),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_mos_model_names() {
+        let content = "* nom\n.model  NMOS_VTL  nmos  level = 54\n.MODEL pch_tt PMOS(level=54)\n.model NMOS_HVT nmos level = 54\n";
+        assert_eq!(parse_mos_model_names(content), (Some("NMOS_VTL".into()), Some("pch_tt".into())));
+        assert_eq!(parse_mos_model_names("* empty\n"), (None, None));
+    }
+}
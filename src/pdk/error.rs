@@ -7,12 +7,27 @@ pub enum PdkError {
     #[error("un exit leaf cell '{0}'")]
     UnexitLeafCell(&'static str),
 
+    #[error("neither leafcell_spice nor leafcell_sizing is given")]
+    LeafcellSourceNotFound,
+
     #[error("expect {0} pins but got {1} in leaf cell '{2}'")]
     UnmatchLeafCellPinSize(usize, usize, &'static str),
 
     #[error("nmos model in process {0} not found")]
     NmosModelNotFound(Process),
 
+    #[error("nmos or pmos model name in process {0} not found")]
+    MosModelNameNotFound(Process),
+
+    #[error("models in process {0} not found")]
+    ModelNotFound(Process),
+
+    #[error("mismatch not found in pdk config")]
+    MismatchNotFound,
+
+    #[error("parasitics not found in pdk config")]
+    ParasiticsNotFound,
+
     #[error("default operating conditions '{0}' not found")]
     DefaultOperatingConditionsNotFound(String),
 
@@ -34,6 +49,15 @@ pub enum PdkError {
     #[error("expect attr {0} but no exit")]
     ExpectAttrButNotFound(&'static str),
 
+    #[error("timing index cell '{0}' not found in liberty")]
+    TimingIndexCellNotFound(String),
+
+    #[error("no timing table found in cell '{0}'")]
+    TimingTableNotFound(String),
+
+    #[error("invalid timing index '{0}': {1}")]
+    InvalidTimingIndex(&'static str, String),
+
     #[error(transparent)]
     Liberty(#[from] LibError),
 }
\ No newline at end of file
@@ -0,0 +1,274 @@
+use std::path::Path;
+use reda_sp::{Component, MosFETBuilder, Spice, Subckt};
+use reda_unit::{Capacitance, Length};
+use serde::{Deserialize, Serialize};
+use crate::{circuit::{BITCELL_NAME, COLUMN_TRI_GATE_NAME, PRECHARGE_NAME, SENSE_AMP_NAME, WRITE_DRIVER_NAME}, ErrorContext, YouRAMResult};
+
+/// Transistor sizing of all leafcells, all widths share the same `length`
+///
+/// `nmos_model`/`pmos_model` are optional overrides, by default they are the `.model` names in the TT models of pdk
+///
+/// ```json
+/// {
+///     "length": "50nm",
+///     "bitcell": { "pull_down": "205nm", "pull_up": "90nm", "access": "135nm" },
+///     ...
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeafcellSizing {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nmos_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pmos_model: Option<String>,
+    pub length: Length,
+
+    pub bitcell: BitcellSizing,
+    pub sense_amp: SenseAmpSizing,
+    pub write_driver: WriteDriverSizing,
+    pub column_trigate: ColumnTriGateSizing,
+    pub precharge: PrechargeSizing,
+
+    /// Capacitance of bitcell `wl` measured by `SizingOptimizer`, wordline drivers are sized by it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitcell_wordline_capacitance: Option<Capacitance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitcellSizing {
+    pub pull_down: Length,
+    pub pull_up: Length,
+    pub access: Length,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SenseAmpSizing {
+    pub latch_pmos: Length,
+    pub latch_nmos: Length,
+    /// Pmos pass transistors from bitlines to latch
+    pub access: Length,
+    /// Tail nmos controlled by `en`
+    pub enable: Length,
+    pub buffer_pmos: Length,
+    pub buffer_nmos: Length,
+    pub output_pmos: Length,
+    pub output_nmos: Length,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteDriverSizing {
+    pub inv_pmos: Length,
+    pub inv_nmos: Length,
+    pub driver_pmos: Length,
+    pub driver_nmos: Length,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnTriGateSizing {
+    pub pass_pmos: Length,
+    pub pass_nmos: Length,
+    pub inv_pmos: Length,
+    pub inv_nmos: Length,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrechargeSizing {
+    pub precharge: Length,
+    pub equalizer: Length,
+}
+
+impl LeafcellSizing {
+    pub fn load<P: AsRef<Path>>(path: P) -> YouRAMResult<Self> {
+        let content = std::fs::read_to_string(path.as_ref()).context("read leafcell sizing")?;
+        let sizing = serde_json::from_str(&content).context("parse leafcell sizing")?;
+        Ok(sizing)
+    }
+
+    /// Fill the models not overridden in the sizing
+    pub fn with_default_models(self, (nmos_model, pmos_model): (String, String)) -> Self {
+        let mut sizing = self;
+        sizing.nmos_model.get_or_insert(nmos_model);
+        sizing.pmos_model.get_or_insert(pmos_model);
+        sizing
+    }
+}
+
+/// Generate leafcell subckts from `LeafcellSizing`
+pub struct LeafcellGenerator<'a> {
+    sizing: &'a LeafcellSizing,
+}
+
+#[derive(Clone, Copy)]
+enum MosKind {
+    Nmos,
+    Pmos,
+}
+
+impl<'a> LeafcellGenerator<'a> {
+    pub fn new(sizing: &'a LeafcellSizing) -> Self {
+        Self { sizing }
+    }
+
+    /// All generated leafcells in a spice, can be loaded by `PdkCells` like `leafcell.cdl`
+    pub fn spice(&self) -> Spice {
+        let mut spice = Spice::new();
+        spice.subckts = vec![
+            self.bitcell(),
+            self.sense_amp(),
+            self.write_driver(),
+            self.column_trigate(),
+            self.precharge(),
+        ];
+        spice
+    }
+
+    pub fn bitcell(&self) -> Subckt {
+        let s = &self.sizing.bitcell;
+        self.subckt(BITCELL_NAME, &["bl", "br", "wl", "vdd", "gnd"], vec![
+            // cross-coupled inverters
+            self.mos("M0", MosKind::Nmos, "Q_bar", "Q", "gnd", s.pull_down),
+            self.mos("M4", MosKind::Pmos, "Q_bar", "Q", "vdd", s.pull_up),
+            self.mos("M1", MosKind::Nmos, "Q", "Q_bar", "gnd", s.pull_down),
+            self.mos("M5", MosKind::Pmos, "Q", "Q_bar", "vdd", s.pull_up),
+            // access transistors
+            self.mos("M3", MosKind::Nmos, "bl", "wl", "Q", s.access),
+            self.mos("M2", MosKind::Nmos, "br", "wl", "Q_bar", s.access),
+        ])
+    }
+
+    pub fn sense_amp(&self) -> Subckt {
+        let s = &self.sizing.sense_amp;
+        self.subckt(SENSE_AMP_NAME, &["bl", "br", "dout", "en", "vdd", "gnd"], vec![
+            // latch
+            self.mos("_1", MosKind::Pmos, "dint", "net_1", "vdd", s.latch_pmos),
+            self.mos("_3", MosKind::Pmos, "net_1", "dint", "vdd", s.latch_pmos),
+            self.mos("_2", MosKind::Nmos, "dint", "net_1", "net_2", s.latch_nmos),
+            self.mos("_8", MosKind::Nmos, "net_1", "dint", "net_2", s.latch_nmos),
+            // bitline access & enable
+            self.mos("_5", MosKind::Pmos, "bl", "en", "dint", s.access),
+            self.mos("_6", MosKind::Pmos, "br", "en", "net_1", s.access),
+            self.mos("_7", MosKind::Nmos, "net_2", "en", "gnd", s.enable),
+            // output buffer
+            self.mos("_9", MosKind::Pmos, "dout_bar", "dint", "vdd", s.buffer_pmos),
+            self.mos("_10", MosKind::Nmos, "dout_bar", "dint", "gnd", s.buffer_nmos),
+            self.mos("_11", MosKind::Pmos, "dout", "dout_bar", "vdd", s.output_pmos),
+            self.mos("_12", MosKind::Nmos, "dout", "dout_bar", "gnd", s.output_nmos),
+        ])
+    }
+
+    pub fn write_driver(&self) -> Subckt {
+        let s = &self.sizing.write_driver;
+        self.subckt(WRITE_DRIVER_NAME, &["din", "bl", "br", "en", "vdd", "gnd"], vec![
+            // inverters for enable and data input
+            self.mos("inP", MosKind::Pmos, "bl_bar", "din", "vdd", s.inv_pmos),
+            self.mos("inN", MosKind::Nmos, "bl_bar", "din", "gnd", s.inv_nmos),
+            self.mos("outP", MosKind::Pmos, "en_bar", "en", "vdd", s.inv_pmos),
+            self.mos("outN", MosKind::Nmos, "en_bar", "en", "gnd", s.inv_nmos),
+            // tristate for bl
+            self.mos("out0P", MosKind::Pmos, "int1", "bl_bar", "vdd", s.driver_pmos),
+            self.mos("out0P2", MosKind::Pmos, "bl", "en_bar", "int1", s.driver_pmos),
+            self.mos("out0N", MosKind::Nmos, "bl", "en", "int2", s.driver_nmos),
+            self.mos("out0N2", MosKind::Nmos, "int2", "bl_bar", "gnd", s.driver_nmos),
+            // tristate for br
+            self.mos("out1P", MosKind::Pmos, "int3", "din", "vdd", s.driver_pmos),
+            self.mos("out1P2", MosKind::Pmos, "br", "en_bar", "int3", s.driver_pmos),
+            self.mos("out1N", MosKind::Nmos, "br", "en", "int4", s.driver_nmos),
+            self.mos("out1N2", MosKind::Nmos, "int4", "din", "gnd", s.driver_nmos),
+        ])
+    }
+
+    pub fn column_trigate(&self) -> Subckt {
+        let s = &self.sizing.column_trigate;
+        self.subckt(COLUMN_TRI_GATE_NAME, &["bl", "br", "bl_out", "br_out", "sel", "vdd", "gnd"], vec![
+            // transmission gates
+            self.mos("1000", MosKind::Pmos, "bl", "sel_bar", "bl_out", s.pass_pmos),
+            self.mos("1001", MosKind::Nmos, "bl", "sel", "bl_out", s.pass_nmos),
+            self.mos("1002", MosKind::Pmos, "br", "sel_bar", "br_out", s.pass_pmos),
+            self.mos("1003", MosKind::Nmos, "br", "sel", "br_out", s.pass_nmos),
+            // select inverter
+            self.mos("inv_nmos", MosKind::Nmos, "sel_bar", "sel", "gnd", s.inv_nmos),
+            self.mos("inv_pmos", MosKind::Pmos, "sel_bar", "sel", "vdd", s.inv_pmos),
+        ])
+    }
+
+    pub fn precharge(&self) -> Subckt {
+        let s = &self.sizing.precharge;
+        self.subckt(PRECHARGE_NAME, &["bl", "br", "en_bar", "vdd"], vec![
+            self.mos("M1", MosKind::Pmos, "vdd", "en_bar", "bl", s.precharge),
+            self.mos("M2", MosKind::Pmos, "vdd", "en_bar", "br", s.precharge),
+            self.mos("M3", MosKind::Pmos, "bl", "en_bar", "br", s.equalizer),
+        ])
+    }
+
+    fn subckt(&self, name: &str, ports: &[&str], components: Vec<Component>) -> Subckt {
+        Subckt {
+            name: name.into(),
+            ports: ports.iter().map(|p| p.to_string()).collect(),
+            components,
+            instances: vec![],
+        }
+    }
+
+    /// Bulk of nmos is `gnd` and pmos is `vdd`, model is the generic `nmos`/`pmos` if not filled by `with_default_models`
+    fn mos(&self, name: &str, kind: MosKind, drain: &str, gate: &str, source: &str, width: Length) -> Component {
+        let (model_name, bulk) = match kind {
+            MosKind::Nmos => (self.sizing.nmos_model.as_deref().unwrap_or("nmos"), "gnd"),
+            MosKind::Pmos => (self.sizing.pmos_model.as_deref().unwrap_or("pmos"), "vdd"),
+        };
+
+        let mos = MosFETBuilder::default()
+            .name(name)
+            .drain(drain)
+            .gate(gate)
+            .source(source)
+            .bulk(bulk)
+            .model_name(model_name)
+            .length(self.sizing.length)
+            .width(width)
+            .build()
+            .expect("all mosfet fields are set");
+
+        Component::M(mos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_sp::MosFET;
+    use super::*;
+
+    fn device_key(mos: &MosFET) -> (String, String, String, String, i64) {
+        (
+            mos.drain.clone(), 
+            mos.gate.clone(), 
+            mos.source.clone(), 
+            mos.model_name.to_lowercase(), 
+            (mos.width.to_f64() * 1e10).round() as i64,
+        )
+    }
+
+    fn device_keys(subckt: &Subckt) -> Vec<(String, String, String, String, i64)> {
+        let mut keys: Vec<_> = subckt.components.iter()
+            .filter_map(|c| match c {
+                Component::M(mos) => Some(device_key(mos)),
+                _ => None,
+            })
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_generate_nangate45_leafcells() {
+        let sizing = LeafcellSizing::load("./platforms/nangate45/leafcell_sizing.json").unwrap()
+            .with_default_models(("NMOS_VTL".into(), "PMOS_VTL".into()));
+        let generated = LeafcellGenerator::new(&sizing).spice();
+        let hand_written = Spice::load_from("./platforms/nangate45/leafcell.cdl").unwrap();
+
+        for subckt in generated.subckts.iter() {
+            let expect = hand_written.subckts.iter().find(|s| s.name == subckt.name).unwrap();
+            assert_eq!(subckt.ports, expect.ports, "ports of {}", subckt.name);
+            assert_eq!(device_keys(subckt), device_keys(expect), "devices of {}", subckt.name);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use reda_lib::model::{LibLibrary, LibOperatingConditions, LibPinDirection};
 use reda_unit::{Capacitance, Temperature, Time, Voltage};
 use crate::{circuit::{DriveStrength, LogicGateKind}, ErrorContext, YouRAMResult};
-use super::{cells::PdkCells, PdkError, Process, Pvt};
+use super::{cells::PdkCells, PdkError, Process, Pvt, TimingIndices};
 
 #[derive(Debug, Clone)]
 pub struct PdkInformation {
@@ -34,7 +34,7 @@ pub struct PdkInformation {
 }
 
 impl PdkInformation {
-    pub fn load(library: &LibLibrary, cells: &PdkCells) -> YouRAMResult<Self> {
+    pub fn load(library: &LibLibrary, cells: &PdkCells, timing_indices: Option<&TimingIndices>) -> YouRAMResult<Self> {
         let time_unit = library.time_unit;
         let capacitance_unit = library.capacitive_load_unit.unwrap_or_default();
 
@@ -55,7 +55,8 @@ impl PdkInformation {
 
         let pvt = Self::extract_pvt(library).context("extract pvt")?;
 
-        let (timing_input_net_transitions, timing_output_net_capacitances) = Self::extract_timings(library, cells)?;
+        let (timing_input_net_transitions, timing_output_net_capacitances) 
+            = Self::extract_timings(library, cells, timing_indices).context("extract timing indices")?;
 
         Ok(Self {
             name: library.name.clone(),
@@ -82,31 +83,47 @@ impl PdkInformation {
         
     }
 
-    fn extract_timings(library: &LibLibrary, cells: &PdkCells) -> YouRAMResult<(Vec<Time>, Vec<Capacitance>)> {
+    fn extract_timings(library: &LibLibrary, cells: &PdkCells, timing_indices: Option<&TimingIndices>) -> Result<(Vec<Time>, Vec<Capacitance>), PdkError> {
+        match timing_indices {
+            Some(timing_indices) if timing_indices.is_explicit() => timing_indices.resolve(&[], &[]),
+            Some(timing_indices) => {
+                let (input_net_transitions, output_net_capacitances) = Self::extract_inv_timings(library, cells)?;
+                timing_indices.resolve(&input_net_transitions, &output_net_capacitances)
+            }
+            None => Self::extract_inv_timings(library, cells),
+        }
+    }
+
+    fn extract_inv_timings(library: &LibLibrary, cells: &PdkCells) -> Result<(Vec<Time>, Vec<Capacitance>), PdkError> {
         let time_unit = library.time_unit;
         let capacitance_unit = library.capacitive_load_unit.unwrap_or_default();
         
         // MARK: use inv1 cell's timing info
-        let inv_x1_name = cells.logicgates.get(&(LogicGateKind::Inv, DriveStrength::X1)).unwrap().read().name.to_string();
-        let cell = library.cell(&inv_x1_name).unwrap();
-        for pin in cell.pins.iter() {
-            if let LibPinDirection::Output = pin.direction {
-                let timing = &pin.timings[0];
-                let cell_fall = timing.cell_fall.as_ref().unwrap();
-                let input_net_transitions = cell_fall.index_1.as_ref().unwrap();
-                let total_output_net_capacitances = cell_fall.index_2.as_ref().unwrap();
-                
-                let timing_input_net_transitions = input_net_transitions.iter()
-                    .map(|v| Time::from(time_unit.value() * v))
-                    .collect();
-                let timing_output_net_capacitances = total_output_net_capacitances.iter()
-                    .map(|v| Capacitance::from(capacitance_unit.value() * v))
-                    .collect();
-
-                return Ok((timing_input_net_transitions, timing_output_net_capacitances));
-            }
-        }
-        unreachable!()
+        let inv_x1_name = cells.logicgates.get(&(LogicGateKind::Inv, DriveStrength::X1))
+            .ok_or_else(|| PdkError::TimingIndexCellNotFound(format!("{} {}", LogicGateKind::Inv, DriveStrength::X1)))?
+            .read().name.to_string();
+        let cell = library.cell(&inv_x1_name)
+            .ok_or_else(|| PdkError::TimingIndexCellNotFound(inv_x1_name.clone()))?;
+
+        let cell_fall = cell.pins.iter()
+            .filter(|pin| matches!(pin.direction, LibPinDirection::Output))
+            .flat_map(|pin| pin.timings.iter())
+            .find_map(|timing| timing.cell_fall.as_ref())
+            .ok_or_else(|| PdkError::TimingTableNotFound(inv_x1_name.clone()))?;
+
+        let input_net_transitions = cell_fall.index_1.as_ref()
+            .ok_or(PdkError::InvalidTimingIndex("input_net_transitions", format!("no index_1 in cell '{}'", inv_x1_name)))?;
+        let total_output_net_capacitances = cell_fall.index_2.as_ref()
+            .ok_or(PdkError::InvalidTimingIndex("output_net_capacitances", format!("no index_2 in cell '{}'", inv_x1_name)))?;
+        
+        let timing_input_net_transitions = input_net_transitions.iter()
+            .map(|v| Time::from(time_unit.value() * v))
+            .collect();
+        let timing_output_net_capacitances = total_output_net_capacitances.iter()
+            .map(|v| Capacitance::from(capacitance_unit.value() * v))
+            .collect();
+
+        Ok((timing_input_net_transitions, timing_output_net_capacitances))
     }
 
     fn extract_pvt(library: &LibLibrary) -> YouRAMResult<Pvt> {
@@ -3,22 +3,26 @@ mod cells;
 mod types;
 mod config;
 mod information;
-use cells::PdkCells;
+mod generator;
+pub(crate) use cells::PdkCells;
 pub use error::*;
 use information::PdkInformation;
 use reda_unit::{Capacitance, Temperature, Time, Voltage};
 pub use types::*;
 pub use config::*;
+pub use generator::*;
 
 use std::path::{Path, PathBuf};
 use reda_lib::model::LibLibrary;
 use reda_sp::Spice;
-use crate::{circuit::{Dff, DriveStrength, Leafcell, LogicGate, LogicGateKind, Shr}, ErrorContext, YouRAMError, YouRAMResult};
+use crate::{circuit::{Dff, BITCELL_NAME, DriveStrength, Leafcell, LogicGate, LogicGateKind, Shr}, ErrorContext, YouRAMError, YouRAMResult};
 
 pub struct Pdk {
     config: PdkConfig,
     infomation: PdkInformation,
     cells: PdkCells,
+    /// Measured wordline capacitance of the generated bitcell
+    bitcell_wordline_capacitance: Option<Capacitance>,
 }
 
 // Interface for config
@@ -33,6 +37,34 @@ impl Pdk {
             .ok_or_else(|| PdkError::NmosModelNotFound(process))
     }
 
+    /// Names of the nmos and pmos `.model` in the model files of `process`
+    pub fn mos_model_names(&self, process: Process) -> Result<(String, String), PdkError> {
+        self.config.mos_model_names(process)
+            .ok_or(PdkError::MosModelNameNotFound(process))
+    }
+
+    pub fn model_statements(&self, process: Process) -> Result<Vec<ModelStatement>, PdkError> {
+        self.config.model_statements(process)
+            .ok_or(PdkError::ModelNotFound(process))
+    }
+
+    pub fn mismatch(&self) -> Result<&MismatchConfig, PdkError> {
+        self.config.mismatch.as_ref()
+            .ok_or(PdkError::MismatchNotFound)
+    }
+
+    pub fn parasitics(&self) -> Option<&ParasiticConfig> {
+        self.config.parasitics.as_ref()
+    }
+
+    /// Override how wire RC is written, e.g. by the compile config
+    pub fn override_parasitic_mode(&mut self, mode: ParasiticMode) -> Result<(), PdkError> {
+        let parasitics = self.config.parasitics.as_mut()
+            .ok_or(PdkError::ParasiticsNotFound)?;
+        parasitics.mode = mode;
+        Ok(())
+    }
+
     #[inline]
     pub fn pdk_root_path(&self) -> &Path {
         &self.config.pdk_path
@@ -49,9 +81,14 @@ impl Pdk {
     }
 
     #[inline]
-    pub fn leafcell_spice_path(&self) -> PathBuf {
+    pub fn leafcell_spice_path(&self) -> Option<PathBuf> {
         self.config.leafcell_spice_path()
     }
+
+    #[inline]
+    pub fn leafcell_sizing_path(&self) -> Option<PathBuf> {
+        self.config.leafcell_sizing_path()
+    }
 }
 
 // Interface for infomation
@@ -101,6 +138,12 @@ impl Pdk {
         self.infomation.default_fanout_load
     }
 
+    /// Capacitance of bitcell `wl`, only known for a bitcell generated from a characterized sizing
+    #[inline]
+    pub fn bitcell_wordline_capacitance(&self) -> Option<Capacitance> {
+        self.bitcell_wordline_capacitance
+    }
+
     #[inline]
     pub fn default_max_transition(&self) -> Option<Time> {
         self.infomation.default_max_transition
@@ -155,6 +198,17 @@ impl Pdk {
     pub fn timing_output_net_capacitances(&self) -> &[Capacitance] {
         &self.infomation.timing_output_net_capacitances
     }
+
+    /// Override the characterization indices, e.g. by the compile config
+    pub fn override_timing_indices(&mut self, timing_indices: &TimingIndices) -> Result<(), PdkError> {
+        let (input_net_transitions, output_net_capacitances) = timing_indices.resolve(
+            &self.infomation.timing_input_net_transitions, 
+            &self.infomation.timing_output_net_capacitances,
+        )?;
+        self.infomation.timing_input_net_transitions = input_net_transitions;
+        self.infomation.timing_output_net_capacitances = output_net_capacitances;
+        Ok(())
+    }
 }
 
 // Interface for cells
@@ -218,6 +272,11 @@ impl Pdk {
     pub fn get_precharge(&self) -> Shr<Leafcell> {
         self.cells.precharge.clone()
     }
+
+    #[inline]
+    pub fn get_level_shifter(&self) -> Option<Shr<Leafcell>> {
+        self.cells.level_shifter.clone()
+    }
 }
 
 impl Pdk {
@@ -229,20 +288,53 @@ impl Pdk {
         // load file
         let library = LibLibrary::load_file(config.stdcell_liberty_path()).map_err(PdkError::Liberty)?;
         let stdcell_spice = Spice::load_from(config.stdcell_spice_path()).map_err(|e| YouRAMError::Message(e.to_string()))?;
-        let leafcell_spice = Spice::load_from(config.leafcell_spice_path()).map_err(|e| YouRAMError::Message(e.to_string()))?;
+        let (leafcell_spice, bitcell_wordline_capacitance) = Self::load_leafcell_spice(&config)?;
 
         // extract logicgates & dff
         let cells = PdkCells::load(&library, &stdcell_spice, &leafcell_spice).context("load cells")?;
 
         // extract infomation 
-        let infomation = PdkInformation::load(&library, &cells)?;
+        let infomation = PdkInformation::load(&library, &cells, config.timing_indices.as_ref())?;
 
         Ok(Self {
             config,
             cells,
             infomation,
+            bitcell_wordline_capacitance,
         })
     }
+
+    /// Generate leafcells from sizing, then replace them by the hand-written ones.
+    /// The measured bitcell wordline capacitance is dropped if the bitcell is replaced
+    fn load_leafcell_spice(config: &PdkConfig) -> YouRAMResult<(Spice, Option<Capacitance>)> {
+        let (mut spice, mut bitcell_wordline_capacitance) = match config.leafcell_sizing_path() {
+            Some(path) => {
+                let mut sizing = LeafcellSizing::load(path)?;
+                if sizing.nmos_model.is_none() || sizing.pmos_model.is_none() {
+                    let models = config.mos_model_names(Process::TypeType)
+                        .ok_or(PdkError::MosModelNameNotFound(Process::TypeType))?;
+                    sizing = sizing.with_default_models(models);
+                }
+                (LeafcellGenerator::new(&sizing).spice(), sizing.bitcell_wordline_capacitance)
+            }
+            None => (Spice::new(), None),
+        };
+
+        match config.leafcell_spice_path() {
+            Some(path) => {
+                let hand_written = Spice::load_from(path).map_err(|e| YouRAMError::Message(e.to_string()))?;
+                spice.subckts.retain(|generated| hand_written.subckts.iter().all(|s| s.name != generated.name));
+                if hand_written.subckts.iter().any(|s| s.name == BITCELL_NAME) {
+                    bitcell_wordline_capacitance = None;
+                }
+                spice.subckts.extend(hand_written.subckts);
+            }
+            None if config.leafcell_sizing.is_none() => return Err(PdkError::LeafcellSourceNotFound.into()),
+            None => {}
+        }
+
+        Ok((spice, bitcell_wordline_capacitance))
+    }
 }
 
 #[cfg(test)]
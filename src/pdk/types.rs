@@ -1,4 +1,4 @@
-use std::{fmt::Display, hash::Hash};
+use std::{collections::BTreeMap, fmt::Display, hash::Hash};
 
 use reda_unit::{Capacitance, Temperature, Time, Voltage};
 use serde::{Deserialize, Serialize};
@@ -84,15 +84,71 @@ pub struct Enviroment {
     pvt: Pvt,
     input_slew: Time,
     output_load: Capacitance,
+
+    /// Supply ports driven by their own voltage, others use the pvt voltage
+    #[serde(default)]
+    supply_voltages: BTreeMap<String, Voltage>,
+
+    /// Seed of leafcell transistor mismatch, nominal transistors if not set
+    #[serde(default)]
+    mismatch_seed: Option<u64>,
+
+    /// Piecewise linear vdd, constant pvt voltage if empty
+    #[serde(default)]
+    vdd_waveform: Vec<(Time, Voltage)>,
 }
 
 impl Enviroment {
     pub fn new(pvt: Pvt, input_slew: Time, output_load: Capacitance) -> Self {
         Self {
-            pvt, input_slew, output_load
+            pvt, input_slew, output_load, supply_voltages: BTreeMap::new(), mismatch_seed: None, vdd_waveform: vec![]
         }
     }
 
+    pub fn with_supply_voltage(mut self, port_name: impl Into<String>, voltage: impl Into<Voltage>) -> Self {
+        self.supply_voltages.insert(port_name.into(), voltage.into());
+        self
+    }
+
+    pub fn with_supply_voltages(mut self, supply_voltages: BTreeMap<String, Voltage>) -> Self {
+        self.supply_voltages.extend(supply_voltages);
+        self
+    }
+
+    /// Voltage of supply port, fallback to pvt voltage 
+    pub fn supply_voltage(&self, port_name: &str) -> Voltage {
+        self.supply_voltages.get(port_name).copied().unwrap_or(self.pvt.voltage)
+    }
+
+    pub fn has_independent_supply(&self, port_name: &str) -> bool {
+        self.supply_voltages.contains_key(port_name)
+    }
+
+    pub fn supply_voltages(&self) -> &BTreeMap<String, Voltage> {
+        &self.supply_voltages
+    }
+
+    /// Simulate with leafcell transistors varied by the pdk mismatch, see `MismatchConfig`
+    pub fn with_mismatch_seed(mut self, seed: u64) -> Self {
+        self.mismatch_seed = Some(seed);
+        self
+    }
+
+    pub fn mismatch_seed(&self) -> Option<u64> {
+        self.mismatch_seed
+    }
+
+    /// Drive vdd by `(time, voltage)` points instead of the pvt voltage, logic levels
+    /// of `CircuitSimulator` still follow the pvt voltage
+    pub fn with_vdd_waveform(mut self, points: Vec<(Time, Voltage)>) -> Self {
+        self.vdd_waveform = points;
+        self
+    }
+
+    pub fn vdd_waveform(&self) -> &[(Time, Voltage)] {
+        &self.vdd_waveform
+    }
+
     pub fn process(&self) -> Process {
         self.pvt.process
     }
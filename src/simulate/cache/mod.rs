@@ -0,0 +1,257 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, io::Write, path::{Path, PathBuf}, time::Duration};
+use reda_unit::Number;
+use sha2::{Digest, Sha256};
+use tracing::debug;
+use crate::{ErrorContext, YouRAMResult};
+use super::{Meas, MeasError, SimulateError, SpiceCommand, SpiceDialect};
+
+/// On disk cache of parsed meas results, `<folder>/<key>.json`
+///
+/// Key is sha256 of the simulator command, the deck and all files it
+/// `.include`s or `.lib`s (recursively), so any change in netlist, models,
+/// stimulus or measurements misses the cache.
+#[derive(Debug, Clone)]
+pub struct SimulateCache {
+    pub folder: PathBuf,
+}
+
+impl SimulateCache {
+    pub fn new(folder: impl Into<PathBuf>) -> Self {
+        Self { folder: folder.into() }
+    }
+
+    /// Remove all cached results
+    pub fn clear(&self) -> YouRAMResult<()> {
+        if self.folder.exists() {
+            std::fs::remove_dir_all(&self.folder)
+                .with_context(|| format!("clear simulate cache '{}'", self.folder.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn key(&self, simulate_path: &Path, execute: &dyn SpiceCommand) -> YouRAMResult<String> {
+        let mut hasher = Sha256::new();
+
+        // command without the real paths, they differ between jobs
+        let command = execute.simulate_command(Path::new("deck.sp"), Path::new("temp"))?;
+        hasher.update(command.as_bytes());
+
+        let mut visited = HashSet::new();
+        Self::hash_file(&mut hasher, simulate_path, &mut visited)?;
+
+        Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<HashMap<String, Number>> {
+        let content = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        let values: BTreeMap<String, f64> = serde_json::from_str(&content).ok()?;
+        Some(values.into_iter().map(|(name, value)| (name, Number::from_f64(value))).collect())
+    }
+
+    pub fn put(&self, key: &str, results: &HashMap<String, Number>) -> YouRAMResult<()> {
+        std::fs::create_dir_all(&self.folder)
+            .with_context(|| format!("create simulate cache '{}'", self.folder.display()))?;
+
+        let values: BTreeMap<&str, f64> = results.iter().map(|(name, value)| (name.as_str(), value.to_f64())).collect();
+        let content = serde_json::to_string_pretty(&values)?;
+
+        // write a unique temp file then rename, concurrent jobs never see a half entry
+        let entry_path = self.entry_path(key);
+        let mut temp_file = tempfile::NamedTempFile::new_in(&self.folder)?;
+        temp_file.write_all(content.as_bytes())?;
+        if let Err(e) = temp_file.persist(&entry_path) {
+            // another job stored the same deck first, its results are as good
+            if !entry_path.exists() {
+                Err(e.error)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.folder.join(format!("{}.json", key))
+    }
+
+    fn hash_file(hasher: &mut Sha256, path: &Path, visited: &mut HashSet<PathBuf>) -> YouRAMResult<()> {
+        if !visited.insert(path.to_path_buf()) {
+            return Ok(());
+        }
+
+        let content = std::fs::read(path).with_context(|| format!("read '{}' for cache key", path.display()))?;
+        hasher.update(&content);
+
+        for include in Self::referenced_files(&String::from_utf8_lossy(&content)) {
+            match resolve_include(path, &include) {
+                Some(include) => Self::hash_file(hasher, &include, visited)?,
+                // simulator will fail on it anyway
+                None => hasher.update(include.as_bytes()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `.include <path>` and `.lib <path> <section>`, path may be quoted
+    fn referenced_files(content: &str) -> Vec<String> {
+        let mut files = vec![];
+        for line in content.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let file = match tokens.as_slice() {
+                [command, file, ..] if command.eq_ignore_ascii_case(".include") || command.eq_ignore_ascii_case(".inc") => file,
+                // `.lib <section>` inside a library only opens a section
+                [command, file, _section] if command.eq_ignore_ascii_case(".lib") => file,
+                _ => continue,
+            };
+            files.push(file.trim_matches(|c| c == '"' || c == '\'').to_string());
+        }
+        files
+    }
+}
+
+/// Path of an `.include`d file, relative to working directory like the deck writes them,
+/// or else to the including file
+pub(crate) fn resolve_include(from: &Path, include: &str) -> Option<PathBuf> {
+    let include = PathBuf::from(include);
+    if include.exists() {
+        return Some(include);
+    }
+    let include = from.parent()?.join(include);
+    include.exists().then_some(include)
+}
+
+/// Wrap a simulator so `SpiceExector::simulate` reuse results in `cache`
+pub struct CachedCommand {
+    pub command: Box<dyn SpiceCommand>,
+    pub cache: SimulateCache,
+}
+
+impl CachedCommand {
+    pub fn new(command: Box<dyn SpiceCommand>, cache: SimulateCache) -> Self {
+        Self { command, cache }
+    }
+}
+
+impl SpiceCommand for CachedCommand {
+    fn dialect(&self) -> SpiceDialect {
+        self.command.dialect()
+    }
+
+    fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+        self.command.simulate_command(sim_filepath, temp_folder)
+    }
+
+    fn meas_result_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.command.meas_result_filepath(sim_filepath, temp_folder)
+    }
+
+    fn parse_meas_result(&self, meas: &dyn Meas, content: &str) -> Result<Number, MeasError> {
+        self.command.parse_meas_result(meas, content)
+    }
+
+    fn log_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.command.log_filepath(sim_filepath, temp_folder)
+    }
+
+    fn diagnose(&self, output: &str) -> Option<SimulateError> {
+        self.command.diagnose(output)
+    }
+
+    fn execute(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        debug!("simulate cache miss '{}'", sim_filepath.display());
+        self.command.execute(sim_filepath, temp_folder)
+    }
+
+    fn execute_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<PathBuf> {
+        debug!("simulate cache miss '{}'", sim_filepath.display());
+        self.command.execute_timeout(sim_filepath, temp_folder, timeout)
+    }
+
+    fn execute_waveforms(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<(PathBuf, PathBuf)> {
+        self.command.execute_waveforms(sim_filepath, temp_folder)
+    }
+
+    fn execute_waveforms_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<(PathBuf, PathBuf)> {
+        self.command.execute_waveforms_timeout(sim_filepath, temp_folder, timeout)
+    }
+
+    fn cache(&self) -> Option<&SimulateCache> {
+        Some(&self.cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulate::{SpiceWritor, VoltageAtMeas};
+    use super::*;
+
+    /// Write `v_out = 0.5` as meas result, and log each run to `<temp>/runs`
+    struct FakeCommand;
+
+    impl SpiceCommand for FakeCommand {
+        fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+            Ok(format!(
+                "echo run >> {} && echo 'v_out = 0.5' > {}",
+                temp_folder.join("runs").display(),
+                self.meas_result_filepath(sim_filepath, temp_folder)?.display(),
+            ))
+        }
+    }
+
+    fn runs(temp: &Path) -> usize {
+        std::fs::read_to_string(temp.join("runs")).map(|s| s.lines().count()).unwrap_or(0)
+    }
+
+    fn simulate(command: &CachedCommand, temp: &Path, model: &Path) -> HashMap<String, Number> {
+        let sim_path = temp.join("cache.sp");
+        let mut writor = SpiceWritor::open(&sim_path).unwrap();
+        writor.write_include(model).unwrap();
+        writor.write_measurement(Box::new(VoltageAtMeas::new("v_out", "out", 1e-9))).unwrap();
+        writor.write_end().unwrap();
+        writor.close().unwrap().simulate(command, temp).unwrap()
+    }
+
+    #[test]
+    fn test_simulate_cache() {
+        let temp = tempfile::tempdir().unwrap();
+        let model = temp.path().join("model.sp");
+        std::fs::write(&model, ".model nmos nmos level=1\n").unwrap();
+
+        let cache = SimulateCache::new(temp.path().join("cache"));
+        let command = CachedCommand::new(Box::new(FakeCommand), cache.clone());
+
+        let result = simulate(&command, temp.path(), &model);
+        assert_eq!(result["v_out"].to_f64(), 0.5);
+        assert_eq!(runs(temp.path()), 1);
+
+        // same deck and model, hit
+        let result = simulate(&command, temp.path(), &model);
+        assert_eq!(result["v_out"].to_f64(), 0.5);
+        assert_eq!(runs(temp.path()), 1);
+
+        // included model changed, miss
+        std::fs::write(&model, ".model nmos nmos level=2\n").unwrap();
+        simulate(&command, temp.path(), &model);
+        assert_eq!(runs(temp.path()), 2);
+
+        cache.clear().unwrap();
+        simulate(&command, temp.path(), &model);
+        assert_eq!(runs(temp.path()), 3);
+    }
+
+    #[test]
+    fn test_concurrent_put() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = SimulateCache::new(temp.path().join("cache"));
+        let results: HashMap<String, Number> = [("v_out".to_string(), Number::from_f64(0.5))].into_iter().collect();
+
+        // jobs of one runner finishing the same deck together
+        std::thread::scope(|scope| {
+            for _ in 0..16 {
+                scope.spawn(|| cache.put("same", &results).unwrap());
+            }
+        });
+
+        assert_eq!(cache.get("same").unwrap()["v_out"].to_f64(), 0.5);
+        assert_eq!(std::fs::read_dir(&cache.folder).unwrap().count(), 1);
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 use super::MeasError;
 
 #[derive(Debug, thiserror::Error)]
@@ -12,12 +12,59 @@ pub enum SimulateError {
     #[error("execute command '{0}' failed for '{1}'")]
     ExecuteError(String, String),
 
+    #[error("execute command '{0}' timeout after {1:?}")]
+    Timeout(String, Duration),
+
     #[error("invalid path '{0}'")]
     InvalidPath(PathBuf),
 
+    #[error("syntax error in '{0}' line {1}: {2}")]
+    DeckSyntax(PathBuf, usize, String),
+
+    #[error("switch-level simulate unsupport '{0}'")]
+    UnsupportSwitchLevel(String),
+
+    #[error("switch-level simulate not settled at {0}s")]
+    NotSettled(f64),
+
+    #[error("invalid rawfile '{0}': {1}")]
+    InvalidRawfile(PathBuf, String),
+
+    #[error("waveform output unsupported by '{0}'")]
+    UnsupportWaveform(String),
+
+    #[error("unknown subckt '{0}'")]
+    UnknownSubckt(String),
+
+    #[error("unknown model '{0}'")]
+    UnknownModel(String),
+
+    #[error("singular matrix{}", .0.as_ref().map(|n| format!(" at node '{}'", n)).unwrap_or_default())]
+    SingularMatrix(Option<String>),
+
+    #[error("timestep too small{}", .0.map(|t| format!(" at {}s", t)).unwrap_or_default())]
+    TimestepTooSmall(Option<f64>),
+
+    #[error("meas '{0}' failed")]
+    MeasFailed(String),
+
+    #[error("simulate '{deck}' failed, log '{log}':\n{excerpt}\n >> {err}")]
+    SimulateFailed { deck: PathBuf, log: PathBuf, excerpt: String, err: Box<SimulateError> },
+
     #[error("meas error: '{0}'")]
     MeasError(#[from] MeasError),
 
     #[error("{msg} >> {err}")]
     Context { msg: String, err: Box<SimulateError> }
-}   
+}
+
+impl SimulateError {
+    /// Failed to converge or hung, may pass with relaxed simulator options
+    pub fn is_convergence_failure(&self) -> bool {
+        match self {
+            Self::TimestepTooSmall(_) | Self::SingularMatrix(_) | Self::Timeout(..) => true,
+            Self::SimulateFailed { err, .. } | Self::Context { err, .. } => err.is_convergence_failure(),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::{path::Path, sync::LazyLock};
+use regex::Regex;
+use super::super::SimulateError;
+
+/// Max lines of simulator output kept in an error
+const EXCERPT_LINES: usize = 12;
+
+/// Find the cause of a failed ngspice run in its output
+pub fn diagnose_ngspice(output: &str) -> Option<SimulateError> {
+    static UNKNOWN_SUBCKT: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?im)unknown subckt:?\s*(.+)$").unwrap()
+    });
+    static UNKNOWN_MODEL: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?im)(?:unable to find definition of model|could not find a valid modelname)\s*'?([^'\s]*)").unwrap()
+    });
+    static SINGULAR_MATRIX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?im)singular matrix(?::\s*check node\s+(\S+))?").unwrap()
+    });
+    static TIMESTEP_TOO_SMALL: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?im)timestep too small(?:;\s*time\s*=\s*([-+0-9.eE]+))?").unwrap()
+    });
+    static MEAS_FAILED: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?im)(?:measure\s+(\S+)\s+failed|^\s*(\S+)\s*=\s*failed)").unwrap()
+    });
+
+    // the deck is wrong before any analysis runs, so these go first
+    if let Some(caps) = UNKNOWN_SUBCKT.captures(output) {
+        // `<instance> <nets...> <subckt>`
+        let line = caps[1].trim();
+        let subckt = line.split_whitespace().last().unwrap_or(line);
+        return Some(SimulateError::UnknownSubckt(subckt.to_string()));
+    }
+    if let Some(caps) = UNKNOWN_MODEL.captures(output) {
+        return Some(SimulateError::UnknownModel(caps[1].to_string()));
+    }
+    if let Some(caps) = TIMESTEP_TOO_SMALL.captures(output) {
+        let time = caps.get(1).and_then(|m| m.as_str().parse().ok());
+        return Some(SimulateError::TimestepTooSmall(time));
+    }
+    if let Some(caps) = SINGULAR_MATRIX.captures(output) {
+        return Some(SimulateError::SingularMatrix(caps.get(1).map(|m| m.as_str().to_string())));
+    }
+    if let Some(caps) = MEAS_FAILED.captures(output) {
+        let name = caps.get(1).or(caps.get(2)).map(|m| m.as_str()).unwrap_or_default();
+        return Some(SimulateError::MeasFailed(name.to_string()));
+    }
+    None
+}
+
+/// Error and warning lines of `output`, or its last lines if there is none
+pub fn log_excerpt(output: &str) -> String {
+    let lines: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
+    let marked: Vec<&str> = lines.iter()
+        .copied()
+        .filter(|l| {
+            let l = l.to_ascii_lowercase();
+            l.contains("error") || l.contains("warning") || l.contains("fail") || l.contains("too small")
+        })
+        .take(EXCERPT_LINES)
+        .collect();
+    if !marked.is_empty() {
+        return marked.join("\n");
+    }
+    lines[lines.len().saturating_sub(EXCERPT_LINES)..].join("\n")
+}
+
+/// Wrap `err` of simulating `deck` with the cause found in `output`, and where to look
+pub(crate) fn simulate_failed(deck: &Path, log: &Path, output: &str, cause: Option<SimulateError>, err: SimulateError) -> SimulateError {
+    SimulateError::SimulateFailed {
+        deck: deck.to_path_buf(),
+        log: log.to_path_buf(),
+        excerpt: log_excerpt(output),
+        err: Box::new(cause.unwrap_or(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{simulate::SpiceCommand, YouRAMError, YouRAMResult};
+    use super::*;
+
+    /// Fails like ngspice loading a deck with an undefined subckt
+    struct FailCommand;
+
+    impl SpiceCommand for FailCommand {
+        fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+            Ok(format!(
+                "echo 'Error: unknown subckt: x1 a z inv' > {}; exit 1",
+                self.log_filepath(sim_filepath, temp_folder)?.display(),
+            ))
+        }
+
+        fn diagnose(&self, output: &str) -> Option<SimulateError> {
+            diagnose_ngspice(output)
+        }
+    }
+
+    #[test]
+    fn test_diagnose_failure() {
+        let temp = tempfile::tempdir().unwrap();
+        let sim_path = temp.path().join("fail.sp");
+        let err = FailCommand.execute(&sim_path, temp.path()).unwrap_err();
+
+        let YouRAMError::Simulate(SimulateError::SimulateFailed { deck, log, excerpt, err }) = err else {
+            panic!("unexpected error '{}'", err);
+        };
+        assert_eq!(deck, sim_path);
+        assert_eq!(log, temp.path().join("fail.log"));
+        assert_eq!(excerpt, "Error: unknown subckt: x1 a z inv");
+        assert!(matches!(*err, SimulateError::UnknownSubckt(s) if s == "inv"));
+    }
+
+    #[test]
+    fn test_diagnose_ngspice() {
+        let output = "\
+Circuit: * youram simulation deck
+
+Error: unknown subckt: xsram addr0 addr1 clk vdd vss sram_2x4
+Error: there aren't any circuits loaded.
+";
+        assert!(matches!(diagnose_ngspice(output), Some(SimulateError::UnknownSubckt(s)) if s == "sram_2x4"));
+
+        let output = "\
+Warning: singular matrix:  check node xsram.bl0
+
+doAnalyses: TRAN:  Timestep too small; time = 1.2345e-09, timestep = 1.25e-21: trouble with node \"xsram.bl0\"
+run simulation(s) aborted
+";
+        assert!(matches!(diagnose_ngspice(output), Some(SimulateError::TimestepTooSmall(Some(t))) if t == 1.2345e-9));
+
+        let output = "Warning: singular matrix:  check node q\nrun simulation(s) aborted\n";
+        assert!(matches!(diagnose_ngspice(output), Some(SimulateError::SingularMatrix(Some(n))) if n == "q"));
+
+        let output = "read_d0_b0          =  1.100000e+00\nmeasure  read_d1_b0  failed!\n";
+        assert!(matches!(diagnose_ngspice(output), Some(SimulateError::MeasFailed(n)) if n == "read_d1_b0"));
+
+        let output = "read_d0_b0 = 1.100000e+00\nread_d1_b0 = failed\n";
+        assert!(matches!(diagnose_ngspice(output), Some(SimulateError::MeasFailed(n)) if n == "read_d1_b0"));
+
+        assert!(diagnose_ngspice("read_d0_b0 = 1.100000e+00\n").is_none());
+    }
+
+    #[test]
+    fn test_log_excerpt() {
+        let output = "Circuit: test\n\nError: unknown subckt: x1 a b inv\nDone\n";
+        assert_eq!(log_excerpt(output), "Error: unknown subckt: x1 a b inv");
+
+        let output = (0..20).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+        assert!(log_excerpt(&output).starts_with("line 8\n"));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{simulate::{Meas, MeasError, SimulateError}, YouRAMResult};
+use super::SpiceCommand;
+use reda_unit::Number;
+use std::path::{Path, PathBuf};
+
+/// HSPICE writes `.meas tran` results to `<temp>/<stem>.mt0` as a table:
+///
+/// ```text
+/// $DATA1 SOURCE='HSPICE' VERSION='...'
+/// .TITLE '...'
+///  read_q           delay_hl         temper           alter#
+///  2.505e-01        1.234e-10        25.0000          1
+/// ```
+///
+/// Names and values may wrap into several lines, `alter#` is always the last column
+#[derive(Clone)]
+pub struct HSpice {
+    pub executable: String,
+}
+
+impl HSpice {
+    pub fn new(executable: impl Into<String>) -> Self {
+        Self { executable: executable.into() }
+    }
+}
+
+impl Default for HSpice {
+    fn default() -> Self {
+        Self::new("hspice")
+    }
+}
+
+impl SpiceCommand for HSpice {
+    fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+        let stem = sim_filepath
+            .file_stem()
+            .ok_or_else(|| SimulateError::InvalidPath(sim_filepath.to_path_buf()))?;
+
+        Ok(format!(
+            "{} -i {} -o {} > {} 2>&1",
+            self.executable,
+            sim_filepath.display(),
+            temp_folder.join(stem).display(),
+            self.log_filepath(sim_filepath, temp_folder)?.display(),
+        ))
+    }
+
+    fn meas_result_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        let stem = sim_filepath
+            .file_stem()
+            .ok_or_else(|| SimulateError::InvalidPath(sim_filepath.to_path_buf()))?;
+
+        Ok(temp_folder.join(stem).with_extension("mt0"))
+    }
+
+    fn parse_meas_result(&self, meas: &dyn Meas, content: &str) -> Result<Number, MeasError> {
+        let tokens: Vec<&str> = content
+            .lines()
+            .filter(|line| !line.starts_with('$') && !line.starts_with(".TITLE"))
+            .flat_map(|line| line.split_whitespace())
+            .collect();
+
+        let not_found = || MeasError::NoMeasResultFound(meas.name().to_string());
+        let names_len = tokens.iter().position(|t| *t == "alter#").ok_or_else(not_found)? + 1;
+        let index = tokens[..names_len]
+            .iter()
+            .position(|t| t.eq_ignore_ascii_case(meas.name()))
+            .ok_or_else(not_found)?;
+
+        // only the first row, later rows come from `.alter`
+        let value = tokens
+            .get(names_len + index)
+            .ok_or_else(|| MeasError::NoMeasValueFound(meas.name().to_string()))?;
+        if value.eq_ignore_ascii_case("failed") {
+            return Err(MeasError::NoMeasValueFound(meas.name().to_string()));
+        }
+
+        value.parse::<f64>()
+            .map(Number::from_f64)
+            .map_err(|e| MeasError::ParseValue(value.to_string(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulate::VoltageAtMeas;
+    use super::*;
+
+    #[test]
+    fn test_parse_mt0() {
+        let content = "\
+$DATA1 SOURCE='HSPICE' VERSION='U-2023.03'
+.TITLE '* read test'
+ read_q           read_qb          delay_hl         
+ temper           alter#           
+ 2.505e-01        failed           1.234e-10        
+ 25.0000          1                
+";
+        let hspice = HSpice::default();
+
+        let read_q = VoltageAtMeas::new("READ_Q", "q", 1e-9);
+        assert_eq!(hspice.parse_meas_result(&read_q, content).unwrap().to_f64(), 0.2505);
+
+        let read_qb = VoltageAtMeas::new("read_qb", "qb", 1e-9);
+        assert!(matches!(hspice.parse_meas_result(&read_qb, content), Err(MeasError::NoMeasValueFound(_))));
+
+        let missing = VoltageAtMeas::new("write_q", "q", 1e-9);
+        assert!(matches!(hspice.parse_meas_result(&missing, content), Err(MeasError::NoMeasResultFound(_))));
+    }
+}
@@ -1,14 +1,27 @@
 mod ngspice;
 mod spectre;
+mod xyce;
+mod hspice;
+mod diagnose;
 pub use ngspice::*;
 use reda_unit::Number;
 pub use spectre::*;
+pub use xyce::*;
+pub use hspice::*;
+pub use diagnose::*;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::os::unix::process::CommandExt;
 use std::process::Command;
+use std::time::{Duration, Instant};
+use tracing::debug;
 use crate::{ErrorContext, YouRAMResult};
 use super::error::SimulateError;
-use super::Meas;
+use super::{Meas, MeasError, RawFile, SimulateCache, SpiceDialect};
+
+/// How often a command with timeout is checked
+const TIMEOUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 pub struct SpiceExector {
     pub simulate_path: PathBuf,
@@ -17,16 +30,48 @@ pub struct SpiceExector {
 
 impl SpiceExector {
     pub fn simulate(&mut self, execute: &impl SpiceCommand, temp_folder: &Path) -> YouRAMResult<HashMap<String, Number>> {
+        let cached = match execute.cache() {
+            Some(cache) => Some((cache, cache.key(&self.simulate_path, execute).context("Hash simulate deck")?)),
+            None => None,
+        };
+        if let Some((cache, key)) = &cached
+            && let Some(results) = cache.get(key)
+            && self.measurements.iter().all(|meas| results.contains_key(meas.name()))
+        {
+            debug!("simulate cache hit '{}'", self.simulate_path.display());
+            return Ok(results);
+        }
+
         let result_path = execute.execute(&self.simulate_path, temp_folder).context("Execute simualte")?;
-        self.get_meas_results(&result_path).context("Get meas result")
+        let results = self.get_meas_results(execute, &result_path, temp_folder).context("Get meas result")?;
+
+        if let Some((cache, key)) = &cached {
+            cache.put(key, &results).context("Write simulate cache")?;
+        }
+        Ok(results)
     }   
 
-    fn get_meas_results(&mut self, result_path: &Path) -> YouRAMResult<HashMap<String, Number>> {
-        let content = std::fs::read_to_string(result_path).context(format!("read result file '{:?}'", result_path))?;
+    /// Simulate and load the saved waveforms, never cached as the rawfile
+    /// is too large to keep
+    pub fn simulate_waveforms(&mut self, execute: &impl SpiceCommand, temp_folder: &Path) -> YouRAMResult<(HashMap<String, Number>, RawFile)> {
+        let (result_path, raw_path) = execute.execute_waveforms(&self.simulate_path, temp_folder).context("Execute simualte")?;
+        let results = self.get_meas_results(execute, &result_path, temp_folder).context("Get meas result")?;
+        let rawfile = RawFile::load(&raw_path)?;
+        Ok((results, rawfile))
+    }
+
+    /// A missing or failed meas is diagnosed from simulator output, the simulator
+    /// may end normally after a failed analysis
+    fn get_meas_results(&mut self, execute: &impl SpiceCommand, result_path: &Path, temp_folder: &Path) -> YouRAMResult<HashMap<String, Number>> {
+        let content = std::fs::read_to_string(result_path)
+            .map_err(|e| execute.diagnose_failure(&self.simulate_path, temp_folder, SimulateError::ExecuteError(
+                format!("read result file '{}'", result_path.display()), e.to_string()
+            )))?;
 
         let mut results = HashMap::new();
         for meas in self.measurements.iter() {
-            let value = meas.get_result(&content).map_err(SimulateError::MeasError)?;
+            let value = execute.parse_meas_result(meas.as_ref(), &content)
+                .map_err(|e| execute.diagnose_failure(&self.simulate_path, temp_folder, SimulateError::MeasError(e)))?;
             results.insert(meas.name().to_string(), value);
         }
 
@@ -34,7 +79,13 @@ impl SpiceExector {
     }
 }
 
-pub trait SpiceCommand {
+/// Simulator backend, shared by concurrent jobs of `JobRunner`
+pub trait SpiceCommand: Send + Sync {
+    /// Netlist syntax this simulator reads
+    fn dialect(&self) -> SpiceDialect {
+        SpiceDialect::NgSpice
+    }
+
     /// Return the simulate command to execute 
     fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String>;
 
@@ -51,27 +102,191 @@ pub trait SpiceCommand {
         Ok(temp_folder.join(filename)) 
     }
 
+    /// Return the file simulator output is captured in
+    fn log_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        let stem = sim_filepath
+            .file_stem()
+            .ok_or_else(|| SimulateError::InvalidPath(sim_filepath.to_path_buf()))?;
+
+        Ok(temp_folder.join(stem).with_extension("log"))
+    }
+
+    /// Cause of a failed simulate found in simulator output, like `diagnose_ngspice`
+    fn diagnose(&self, _output: &str) -> Option<SimulateError> {
+        None
+    }
+
+    /// Turn `err` of simulating `sim_filepath` into an error with its cause,
+    /// the deck and log path and an excerpt of the log and meas result file
+    fn diagnose_failure(&self, sim_filepath: &Path, temp_folder: &Path, err: SimulateError) -> SimulateError {
+        let log_path = self.log_filepath(sim_filepath, temp_folder).unwrap_or_default();
+        let output = [Some(log_path.clone()), self.meas_result_filepath(sim_filepath, temp_folder).ok()]
+            .into_iter()
+            .flatten()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .collect::<Vec<_>>()
+            .join("\n");
+        simulate_failed(sim_filepath, &log_path, &output, self.diagnose(&output), err)
+    }
+
+    /// Get value of `meas` from content of the meas result file,
+    /// default accepts `<name> = <value>` lines written by ngspice and spectre
+    fn parse_meas_result(&self, meas: &dyn Meas, content: &str) -> Result<Number, MeasError> {
+        meas.get_result(content)
+    }
+
+    /// Results cache consulted by `SpiceExector::simulate`, see `CachedCommand`
+    fn cache(&self) -> Option<&SimulateCache> {
+        None
+    }
+
     fn execute(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
-        let sim_filepath = sim_filepath.as_ref();
-        let temp_folder = temp_folder.as_ref();
+        self.execute_timeout(sim_filepath, temp_folder, None)
+    }
 
+    /// Same as `execute`, but kill the simulator after `timeout` of wall-clock time
+    fn execute_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<PathBuf> {
         let command = self.simulate_command(sim_filepath, temp_folder)?;
-        let status = Command::new("sh")
-            .arg("-c")
-            .arg(&command)
-            .status()
-            .map_err(|e| SimulateError::ExecuteError(command.clone(), e.to_string()))?;
-
-        match status.code() {
-            Some(0) => Ok(self.meas_result_filepath(sim_filepath, temp_folder)?),
-            Some(code) => Err(SimulateError::ExecuteError(command.clone(), format!("Command returns '{}'", code)))?,
-            None => Err(SimulateError::ExecuteError(command.clone(), "Command quit unnormal".into()))?,
+        run_command(&command, timeout).map_err(|e| self.diagnose_failure(sim_filepath, temp_folder, e))?;
+        self.meas_result_filepath(sim_filepath, temp_folder)
+    }
+
+    /// Simulate and also save all waveforms, return the meas result file and the rawfile,
+    /// see `RawFile`
+    fn execute_waveforms(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<(PathBuf, PathBuf)> {
+        self.execute_waveforms_timeout(sim_filepath, temp_folder, None)
+    }
+
+    /// Same as `execute_waveforms`, but kill the simulator after `timeout`. Default is unsupported
+    fn execute_waveforms_timeout(&self, sim_filepath: &Path, _temp_folder: &Path, _timeout: Option<Duration>) -> YouRAMResult<(PathBuf, PathBuf)> {
+        Err(SimulateError::UnsupportWaveform(sim_filepath.display().to_string()))?
+    }
+}
+
+/// Run `command` by shell, error when it fails or runs longer than `timeout`
+pub fn run_command(command: &str, timeout: Option<Duration>) -> Result<(), SimulateError> {
+    let execute_error = |e: std::io::Error| SimulateError::ExecuteError(command.to_string(), e.to_string());
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+
+    let status = match timeout {
+        None => shell.status().map_err(execute_error)?,
+        Some(timeout) => {
+            // own process group, so the simulator started by shell is killed with it
+            let mut child = shell.process_group(0).spawn().map_err(execute_error)?;
+            let start = Instant::now();
+            loop {
+                if let Some(status) = child.try_wait().map_err(execute_error)? {
+                    break status;
+                }
+                let elapsed = start.elapsed();
+                if elapsed >= timeout {
+                    let _ = Command::new("kill").arg("-KILL").arg("--").arg(format!("-{}", child.id())).status();
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(SimulateError::Timeout(command.to_string(), timeout));
+                }
+                std::thread::sleep(TIMEOUT_POLL_INTERVAL.min(timeout - elapsed));
+            }
         }
+    };
+
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(SimulateError::ExecuteError(command.to_string(), format!("Command returns '{}'", code))),
+        None => Err(SimulateError::ExecuteError(command.to_string(), "Command quit unnormal".into())),
     }
 }
 
 impl SpiceCommand for Box<dyn SpiceCommand> {
+    fn dialect(&self) -> SpiceDialect {
+        self.as_ref().dialect()
+    }
+
+    fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+        self.as_ref().simulate_command(sim_filepath, temp_folder)
+    }
+
+    fn meas_result_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.as_ref().meas_result_filepath(sim_filepath, temp_folder)
+    }
+
+    fn parse_meas_result(&self, meas: &dyn Meas, content: &str) -> Result<Number, MeasError> {
+        self.as_ref().parse_meas_result(meas, content)
+    }
+
+    fn log_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.as_ref().log_filepath(sim_filepath, temp_folder)
+    }
+
+    fn diagnose(&self, output: &str) -> Option<SimulateError> {
+        self.as_ref().diagnose(output)
+    }
+
+    fn execute(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.as_ref().execute(sim_filepath, temp_folder)
+    }
+
+    fn execute_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<PathBuf> {
+        self.as_ref().execute_timeout(sim_filepath, temp_folder, timeout)
+    }
+
+    fn execute_waveforms(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<(PathBuf, PathBuf)> {
+        self.as_ref().execute_waveforms(sim_filepath, temp_folder)
+    }
+
+    fn execute_waveforms_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<(PathBuf, PathBuf)> {
+        self.as_ref().execute_waveforms_timeout(sim_filepath, temp_folder, timeout)
+    }
+
+    fn cache(&self) -> Option<&SimulateCache> {
+        self.as_ref().cache()
+    }
+}
+
+/// Same command used by many charz, e.g. timing and constraint charz of liberty
+impl<T: SpiceCommand + ?Sized> SpiceCommand for Arc<T> {
+    fn dialect(&self) -> SpiceDialect {
+        self.as_ref().dialect()
+    }
+
     fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
         self.as_ref().simulate_command(sim_filepath, temp_folder)
     }
-}
\ No newline at end of file
+
+    fn meas_result_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.as_ref().meas_result_filepath(sim_filepath, temp_folder)
+    }
+
+    fn parse_meas_result(&self, meas: &dyn Meas, content: &str) -> Result<Number, MeasError> {
+        self.as_ref().parse_meas_result(meas, content)
+    }
+
+    fn log_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.as_ref().log_filepath(sim_filepath, temp_folder)
+    }
+
+    fn diagnose(&self, output: &str) -> Option<SimulateError> {
+        self.as_ref().diagnose(output)
+    }
+
+    fn execute(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.as_ref().execute(sim_filepath, temp_folder)
+    }
+
+    fn execute_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<PathBuf> {
+        self.as_ref().execute_timeout(sim_filepath, temp_folder, timeout)
+    }
+
+    fn execute_waveforms(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<(PathBuf, PathBuf)> {
+        self.as_ref().execute_waveforms(sim_filepath, temp_folder)
+    }
+
+    fn execute_waveforms_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<(PathBuf, PathBuf)> {
+        self.as_ref().execute_waveforms_timeout(sim_filepath, temp_folder, timeout)
+    }
+
+    fn cache(&self) -> Option<&SimulateCache> {
+        self.as_ref().cache()
+    }
+}
@@ -1,19 +1,57 @@
-use std::path::Path;
+use std::{path::{Path, PathBuf}, time::Duration};
 use crate::YouRAMResult;
-use super::SpiceCommand;
+use super::super::SimulateError;
+use super::{diagnose_ngspice, run_command, SpiceCommand};
 
 #[derive(Clone)]
 pub struct NgSpice;
 
-impl SpiceCommand for NgSpice {
-    fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
-        let sim_filepath = sim_filepath.as_ref();
-        let temp_folder = temp_folder.as_ref();
+impl NgSpice {
+    const EXECUTABLE: &'static str = "ngspice";
 
+    /// Batch command of both `execute` and `execute_waveforms`, `options` go before the deck
+    fn command(&self, options: &str, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
         Ok(format!(
-            "ngspice -b -o {} {} > /dev/null 2>&1",
+            "{} -b {}-o {} {} > {} 2>&1",
+            Self::EXECUTABLE,
+            options,
             self.meas_result_filepath(sim_filepath, temp_folder)?.display(),
-            sim_filepath.display()
+            sim_filepath.display(),
+            self.log_filepath(sim_filepath, temp_folder)?.display(),
         ))
     }
-}
\ No newline at end of file
+}
+
+impl SpiceCommand for NgSpice {
+    fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+        self.command("", sim_filepath, temp_folder)
+    }
+
+    fn diagnose(&self, output: &str) -> Option<SimulateError> {
+        diagnose_ngspice(output)
+    }
+
+    /// Save all waveforms by `-r <temp>/<name>.raw`, ngspice writes a binary rawfile
+    fn execute_waveforms_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<(PathBuf, PathBuf)> {
+        let result_path = self.meas_result_filepath(sim_filepath, temp_folder)?;
+        let raw_path = result_path.with_extension("raw");
+        let command = self.command(&format!("-r {} ", raw_path.display()), sim_filepath, temp_folder)?;
+        run_command(&command, timeout).map_err(|e| self.diagnose_failure(sim_filepath, temp_folder, e))?;
+        Ok((result_path, raw_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waveform_command() {
+        let (deck, temp) = (Path::new("temp/read.sp"), Path::new("temp"));
+        let command = NgSpice.simulate_command(deck, temp).unwrap();
+        assert_eq!(command, "ngspice -b -o temp/read.meas temp/read.sp > temp/read.log 2>&1");
+
+        let waveform = NgSpice.command("-r temp/read.raw ", deck, temp).unwrap();
+        assert_eq!(waveform, "ngspice -b -r temp/read.raw -o temp/read.meas temp/read.sp > temp/read.log 2>&1");
+    }
+}
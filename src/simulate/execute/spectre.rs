@@ -1,16 +1,46 @@
-use crate::YouRAMResult;
+use crate::{simulate::{SimulateError, SpiceDialect}, YouRAMResult};
 use super::SpiceCommand;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+/// Spectre reading the spice deck in `simulator lang=spice` mode,
+/// `.measure` results are written to `<temp>/<stem>.measure`
 #[derive(Clone)]
-pub struct Spectre;
+pub struct Spectre {
+    pub executable: String,
+}
+
+impl Spectre {
+    pub fn new(executable: impl Into<String>) -> Self {
+        Self { executable: executable.into() }
+    }
+}
+
+impl Default for Spectre {
+    fn default() -> Self {
+        Self::new("spectre")
+    }
+}
 
 impl SpiceCommand for Spectre {
+    fn dialect(&self) -> SpiceDialect {
+        SpiceDialect::Spectre
+    }
+
     fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
         Ok(format!(
-            "spectre {} -outdir {} > /dev/null 2>&1",
+            "{} {} -raw {} +log {} > /dev/null 2>&1",
+            self.executable,
             sim_filepath.display(),
             temp_folder.display(),
+            self.log_filepath(sim_filepath, temp_folder)?.display(),
         ))
     }
-}
\ No newline at end of file
+
+    fn meas_result_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        let stem = sim_filepath
+            .file_stem()
+            .ok_or_else(|| SimulateError::InvalidPath(sim_filepath.to_path_buf()))?;
+
+        Ok(temp_folder.join(stem).with_extension("measure"))
+    }
+}
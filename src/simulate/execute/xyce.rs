@@ -0,0 +1,93 @@
+use crate::{simulate::{Meas, MeasError, SimulateError, SpiceDialect}, YouRAMResult};
+use super::SpiceCommand;
+use reda_unit::Number;
+use std::path::{Path, PathBuf};
+
+/// Xyce writes `.measure` results next to the netlist as `<netlist>.mt0`,
+/// one `<NAME> = <value>` per line with upper case name
+#[derive(Clone)]
+pub struct Xyce {
+    pub executable: String,
+}
+
+impl Xyce {
+    pub fn new(executable: impl Into<String>) -> Self {
+        Self { executable: executable.into() }
+    }
+}
+
+impl Default for Xyce {
+    fn default() -> Self {
+        Self::new("Xyce")
+    }
+}
+
+impl SpiceCommand for Xyce {
+    fn dialect(&self) -> SpiceDialect {
+        SpiceDialect::Xyce
+    }
+
+    fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+        Ok(format!(
+            "{} -l {} {} > /dev/null 2>&1",
+            self.executable,
+            self.log_filepath(sim_filepath, temp_folder)?.display(),
+            sim_filepath.display(),
+        ))
+    }
+
+    fn meas_result_filepath(&self, sim_filepath: &Path, _temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        let filename = sim_filepath
+            .file_name()
+            .ok_or_else(|| SimulateError::InvalidPath(sim_filepath.to_path_buf()))?;
+        let mut filename = filename.to_os_string();
+        filename.push(".mt0");
+
+        Ok(sim_filepath.with_file_name(filename))
+    }
+
+    fn parse_meas_result(&self, meas: &dyn Meas, content: &str) -> Result<Number, MeasError> {
+        for line in content.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            if !name.trim().eq_ignore_ascii_case(meas.name()) {
+                continue;
+            }
+
+            // failed measure is reported as `FAILED`
+            let value = value.trim();
+            if value.eq_ignore_ascii_case("failed") {
+                return Err(MeasError::NoMeasValueFound(meas.name().to_string()));
+            }
+            return value.parse::<f64>()
+                .map(Number::from_f64)
+                .map_err(|e| MeasError::ParseValue(value.into(), e));
+        }
+
+        Err(MeasError::NoMeasResultFound(meas.name().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulate::VoltageAtMeas;
+    use super::*;
+
+    #[test]
+    fn test_parse_mt0() {
+        let content = "READ_Q = 2.505000e-01\nREAD_QB = FAILED\n";
+        let xyce = Xyce::default();
+
+        let read_q = VoltageAtMeas::new("read_q", "q", 1e-9);
+        assert_eq!(xyce.parse_meas_result(&read_q, content).unwrap().to_f64(), 0.2505);
+
+        let read_qb = VoltageAtMeas::new("read_qb", "qb", 1e-9);
+        assert!(matches!(xyce.parse_meas_result(&read_qb, content), Err(MeasError::NoMeasValueFound(_))));
+
+        assert_eq!(
+            xyce.meas_result_filepath(Path::new("temp/read.sp"), Path::new("temp")).unwrap(),
+            PathBuf::from("temp/read.sp.mt0"),
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use derive_builder::Builder;
+use reda_unit::{Time, Voltage};
+use super::{Edge, Meas};
+
+/// Time of the `count`th `edge` crossing of `net_name` through `voltage` after `time_delay`
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct CrossMeas {
+    pub name: String,
+    pub net_name: String,
+    pub edge: Edge,
+    pub voltage: Voltage,
+    #[builder(default = "1")]
+    pub count: usize,
+    pub time_delay: Time,
+}
+
+impl Meas for CrossMeas {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn write_command(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        let command = format!(
+            ".meas tran {} WHEN v({})={} {}={} TD={}\n",
+            self.name,
+            self.net_name,
+            self.voltage,
+            self.edge,
+            self.count,
+            self.time_delay,
+        );
+        out.write_all(command.as_bytes())
+    }
+}
@@ -0,0 +1,85 @@
+use derive_builder::Builder;
+use reda_unit::{Time, Voltage};
+use super::Meas;
+
+/// How a current is reduced over the window
+#[derive(Debug, Clone, Copy)]
+pub enum CurrentStatistic {
+    Average,
+    Rms,
+    /// Charge in the window
+    Integral,
+}
+
+/// Current through the zero volt source `V<probe_name>` over `[from, to]`,
+/// like `CircuitSimulator::VDD_PROBE_NAME` for the current drawn from supply
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct SupplyCurrentMeas {
+    pub name: String,
+    pub probe_name: String,
+    pub statistic: CurrentStatistic,
+    pub from: Time,
+    pub to: Time,
+}
+
+impl Meas for SupplyCurrentMeas {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn write_command(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        let command = format!(
+            ".meas tran {} {} i(V{}) FROM={} TO={}\n",
+            self.name,
+            self.statistic,
+            self.probe_name,
+            self.from,
+            self.to,
+        );
+        out.write_all(command.as_bytes())
+    }
+}
+
+/// Energy drawn through `V<probe_name>` from a dc supply of `voltage` over `[from, to]`,
+/// the charge is measured as `<name>_charge` first
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct EnergyMeas {
+    pub name: String,
+    pub probe_name: String,
+    pub voltage: Voltage,
+    pub from: Time,
+    pub to: Time,
+}
+
+impl Meas for EnergyMeas {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn write_command(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        let command = format!(
+            ".meas tran {}_charge INTEG i(V{}) FROM={} TO={}\n.meas tran {} PARAM='{}_charge*{}'\n",
+            self.name,
+            self.probe_name,
+            self.from,
+            self.to,
+            self.name,
+            self.name,
+            // a bare number, unit suffix is not allowed in expression
+            self.voltage.to_f64(),
+        );
+        out.write_all(command.as_bytes())
+    }
+}
+
+impl std::fmt::Display for CurrentStatistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Average => write!(f, "AVG"),
+            Self::Rms => write!(f, "RMS"),
+            Self::Integral => write!(f, "INTEG"),
+        }
+    }
+}
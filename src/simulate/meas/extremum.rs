@@ -0,0 +1,47 @@
+use derive_builder::Builder;
+use reda_unit::Time;
+use super::Meas;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Extremum {
+    Min,
+    Max,
+}
+
+/// Min or max voltage of `net_name` over `[from, to]`
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct ExtremumMeas {
+    pub name: String,
+    pub net_name: String,
+    pub extremum: Extremum,
+    pub from: Time,
+    pub to: Time,
+}
+
+impl Meas for ExtremumMeas {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn write_command(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        let command = format!(
+            ".meas tran {} {} v({}) FROM={} TO={}\n",
+            self.name,
+            self.extremum,
+            self.net_name,
+            self.from,
+            self.to,
+        );
+        out.write_all(command.as_bytes())
+    }
+}
+
+impl std::fmt::Display for Extremum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Min => write!(f, "MIN"),
+            Self::Max => write!(f, "MAX"),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use derive_builder::Builder;
+use reda_unit::{Time, Voltage};
+use super::{Edge, Meas};
+
+/// Voltage of `find_net_name` at the first `when_edge` crossing of `when_net_name` through `when_voltage`
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct FindWhenMeas {
+    pub name: String,
+
+    pub find_net_name: String,
+
+    pub when_net_name: String,
+    pub when_edge: Edge,
+    pub when_voltage: Voltage,
+    pub time_delay: Time,
+}
+
+impl Meas for FindWhenMeas {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn write_command(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        let command = format!(
+            ".meas tran {} FIND v({}) WHEN v({})={} {}=1 TD={}\n",
+            self.name,
+            self.find_net_name,
+            self.when_net_name,
+            self.when_voltage,
+            self.when_edge,
+            self.time_delay,
+        );
+        out.write_all(command.as_bytes())
+    }
+}
@@ -1,10 +1,18 @@
 mod voltageat;
 mod delay;
+mod findwhen;
+mod current;
+mod extremum;
+mod cross;
 
 pub use voltageat::*;
 pub use delay::*;
+pub use findwhen::*;
+pub use current::*;
+pub use extremum::*;
+pub use cross::*;
 
-use std::num::ParseFloatError;
+use std::{num::ParseFloatError, sync::{Arc, LazyLock}};
 use regex::Regex;
 use reda_unit::Number;
 
@@ -23,28 +31,133 @@ pub enum MeasError {
 pub trait Meas {
     fn name(&self) -> &str; 
     fn write_command(&self, out: &mut dyn std::io::Write) -> std::io::Result<()>;
+    /// Find `<name> = <value>` in the simulator output, name is case insensitive(spectre/hspice lower it) 
+    /// and value may have a scale suffix like `1.2n`
     fn get_result(&self, context: &str) -> Result<Number, MeasError> {
-        let pattern = format!(r"{}\s*=\s*-?\d+\.?\d*[eE]?[-+]?\d+", regex::escape(&self.name()));
+        let pattern = format!(
+            r"(?im)(?:^|[^\w.]){}\s*=\s*([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)([a-zA-Z]*)", 
+            regex::escape(self.name())
+        );
         let re = Regex::new(&pattern).unwrap();
 
-        let mat = match re.find(context) {
-            Some(m) => m,
+        // "Vout = -1.2345e-3" or "vout = -1.2345m"
+        let caps = match re.captures(context) {
+            Some(caps) => caps,
             None => return Err(MeasError::NoMeasResultFound(self.name().to_string())),
         };
-        // "Vout = -1.2345e-3"
-        let name_value = mat.as_str();
 
-        let eq_pos = name_value.find('=').unwrap();
-        let after_eq = &name_value[eq_pos + 1..].trim_start();
-
-        let val = match after_eq.split_whitespace().next() {
-            Some(num_str) => match num_str.parse::<f64>() {
-                Ok(v) => v,
-                Err(e) => return Err(MeasError::ParseValue(num_str.into(), e))
-            },
+        let num_str = match caps.get(1) {
+            Some(m) => m.as_str(),
             None => return Err(MeasError::NoMeasValueFound(self.name().to_string())),
         };
+        let val = match num_str.parse::<f64>() {
+            Ok(v) => v,
+            Err(e) => return Err(MeasError::ParseValue(num_str.into(), e))
+        };
+        let scale = caps.get(2).map(|m| scale_suffix(m.as_str())).unwrap_or(1.0);
+
+        Ok(Number::from_f64(val * scale))
+    }
+}
+
+/// A meas shared by clones of a transaction generator
+impl<M: Meas + ?Sized> Meas for Arc<M> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn write_command(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        (**self).write_command(out)
+    }
+
+    fn get_result(&self, context: &str) -> Result<Number, MeasError> {
+        (**self).get_result(context)
+    }
+}
+
+
+/// Parse a spice number like `1.1V`, `0.05um` or `2.5e-10`
+pub(crate) fn parse_spice_number(s: &str) -> Option<f64> {
+    static RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)([a-zA-Z]*)$").unwrap()
+    });
+    let caps = RE.captures(s.trim())?;
+    let value = caps.get(1)?.as_str().parse::<f64>().ok()?;
+    let scale = caps.get(2).map(|m| scale_suffix(m.as_str())).unwrap_or(1.0);
+    Some(value * scale)
+}
+
+/// Scale of spice number suffix, unknown suffix(like a unit 'V') is 1
+fn scale_suffix(suffix: &str) -> f64 {
+    let suffix = suffix.to_ascii_lowercase();
+    if suffix.starts_with("meg") {
+        return 1e6;
+    }
+    match suffix.chars().next() {
+        Some('t') => 1e12,
+        Some('g') => 1e9,
+        Some('k') => 1e3,
+        Some('m') => 1e-3,
+        Some('u') => 1e-6,
+        Some('n') => 1e-9,
+        Some('p') => 1e-12,
+        Some('f') => 1e-15,
+        Some('a') => 1e-18,
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_unit::{t, v};
+    use super::*;
+
+    #[test]
+    fn test_get_result() {
+        let meas = VoltageAtMeas::new("read_d1_b1", "dout1", 1e-9);
+
+        let ngspice = "read_d1_b10 = 0.000000e+00\nread_d1_b1 = 1.100000e+00\n";
+        assert_eq!(meas.get_result(ngspice).unwrap().to_f64(), 1.1);
+
+        let spectre = "xread_d1_b1 = 0\nREAD_D1_B1 = 250.5m\n";
+        assert!((meas.get_result(spectre).unwrap().to_f64() - 0.2505).abs() < 1e-12);
+
+        assert!(meas.get_result("read_d1_b1 = failed").is_err());
+        assert!(meas.get_result("other = 1").is_err());
+    }
+
+    #[test]
+    fn test_write_command() {
+        let command = |meas: &dyn Meas| {
+            let mut out = vec![];
+            meas.write_command(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let current = SupplyCurrentMeasBuilder::default()
+            .name("idd").probe_name("VDD_PROBE").statistic(CurrentStatistic::Average)
+            .from(t!(1 n)).to(t!(2 n))
+            .build().unwrap();
+        assert_eq!(command(&current), ".meas tran idd AVG i(VVDD_PROBE) FROM=1ns TO=2ns\n");
+
+        let energy = EnergyMeasBuilder::default()
+            .name("e_read").probe_name("VDD_PROBE").voltage(v!(1.1))
+            .from(t!(1 n)).to(t!(2 n))
+            .build().unwrap();
+        assert_eq!(
+            command(&energy),
+            ".meas tran e_read_charge INTEG i(VVDD_PROBE) FROM=1ns TO=2ns\n.meas tran e_read PARAM='e_read_charge*1.1'\n",
+        );
+
+        let max = ExtremumMeasBuilder::default()
+            .name("q_max").net_name("q").extremum(Extremum::Max)
+            .from(t!(0)).to(t!(2 n))
+            .build().unwrap();
+        assert_eq!(command(&max), ".meas tran q_max MAX v(q) FROM=0s TO=2ns\n");
 
-        Ok(Number::from_f64(val))
+        let cross = CrossMeasBuilder::default()
+            .name("bl_fall").net_name("bl").edge(Edge::Fall).voltage(v!(0.55)).time_delay(t!(1 n))
+            .build().unwrap();
+        assert_eq!(command(&cross), ".meas tran bl_fall WHEN v(bl)=0.55V FALL=1 TD=1ns\n");
     }
 }
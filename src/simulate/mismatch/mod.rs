@@ -0,0 +1,204 @@
+use std::{collections::{HashMap, HashSet}, path::Path};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tracing::debug;
+use crate::{pdk::MismatchConfig, ErrorContext, YouRAMResult};
+use super::parse_spice_number;
+
+/// Write the netlist `circuit_path` to `output_path` with leafcell transistors varied,
+/// see `vary_netlist`
+pub fn write_mismatch_netlist(circuit_path: &Path, output_path: &Path, config: &MismatchConfig, seed: u64) -> YouRAMResult<()> {
+    let content = std::fs::read_to_string(circuit_path)
+        .with_context(|| format!("read circuit netlist '{}'", circuit_path.display()))?;
+    std::fs::write(output_path, vary_netlist(&content, config, seed))
+        .with_context(|| format!("write mismatch netlist '{}'", output_path.display()))?;
+    Ok(())
+}
+
+/// Give every instance of the mismatch cells its own copy `<cell>_mc<index>`,
+/// each transistor of a copy has a vth shift sampled from its sigma.
+/// The same `seed` always samples the same shifts
+///
+/// An instance line is varied once, so instances of a module instanced
+/// many times share their shifts.
+pub fn vary_netlist(content: &str, config: &MismatchConfig, seed: u64) -> String {
+    let lines = join_continuation_lines(content);
+    let cells = mismatch_cells(&lines, config);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut output = vec![];
+    let mut copies = vec![];
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for line in lines.iter() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let is_instance = tokens.first().is_some_and(|t| t.starts_with(['x', 'X']));
+        // `X<name> <nets...> <subckt> <params...>`
+        let position = tokens.iter().rposition(|t| !t.contains('='));
+        let cell = position.and_then(|p| cells.get_key_value(&tokens[p].to_ascii_lowercase()));
+
+        match (is_instance, position, cell) {
+            (true, Some(position), Some((key, (name, body)))) => {
+                let index = indices.entry(key.clone()).or_default();
+                let copy_name = format!("{}_mc{}", name, index);
+                *index += 1;
+
+                let mut tokens = tokens.clone();
+                tokens[position] = &copy_name;
+                output.push(tokens.join(" "));
+                copies.push(vary_subckt(&copy_name, body, config, &mut rng));
+            }
+            _ => output.push(line.clone()),
+        }
+    }
+    debug!("vary {} instances of mismatch cells", copies.len());
+
+    // copies are top level, before `.end` if any
+    let end = output.iter()
+        .position(|line| line.trim().eq_ignore_ascii_case(".end"))
+        .unwrap_or(output.len());
+    output.splice(end..end, copies);
+    output.join("\n") + "\n"
+}
+
+/// `.SUBCKT` header and body lines of each mismatch cell, by lowercase name
+fn mismatch_cells(lines: &[String], config: &MismatchConfig) -> HashMap<String, (String, Vec<String>)> {
+    let mut cells = HashMap::new();
+    let mut current: Option<(String, Vec<String>)> = None;
+    let mut defined = HashSet::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let keyword = tokens.first().map(|t| t.to_ascii_lowercase());
+        match keyword.as_deref() {
+            Some(".subckt") if tokens.len() > 1 && config.is_varied_cell(tokens[1]) => {
+                current = Some((tokens[1].to_string(), vec![line.clone()]));
+            }
+            Some(".ends") => if let Some((name, mut body)) = current.take() {
+                body.push(line.clone());
+                // the first definition is used, like spice does
+                if defined.insert(name.to_ascii_lowercase()) {
+                    cells.insert(name.to_ascii_lowercase(), (name, body));
+                }
+            }
+            _ => if let Some((_, body)) = current.as_mut() {
+                body.push(line.clone());
+            }
+        }
+    }
+    cells
+}
+
+/// Copy of subckt `body` named `copy_name`, with a sampled vth shift on each varied transistor
+fn vary_subckt(copy_name: &str, body: &[String], config: &MismatchConfig, rng: &mut StdRng) -> String {
+    let mut lines = vec![];
+    for (index, line) in body.iter().enumerate() {
+        let mut tokens: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        let is_end = index + 1 == body.len();
+        if index == 0 || is_end {
+            // `.SUBCKT <name> ...` and `.ENDS <name>`
+            if tokens.len() > 1 {
+                tokens[1] = copy_name.to_string();
+            }
+        } else if tokens.len() >= 6 && tokens[0].starts_with(['m', 'M']) {
+            let size = |key: &str| tokens[6..].iter()
+                .filter_map(|t| t.split_once('='))
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .and_then(|(_, v)| parse_spice_number(v));
+            if let Some(sigma) = config.sigma_vth(&tokens[5], size("w"), size("l")) {
+                tokens.push(format!("{}={:.6e}", config.parameter, sigma * standard_normal(rng)));
+            }
+        }
+        lines.push(tokens.join(" "));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Box-Muller transform
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Lines with `+` continuations joined
+fn join_continuation_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in content.lines() {
+        match (line.trim_start().strip_prefix('+'), lines.last_mut()) {
+            (Some(rest), Some(last)) => {
+                last.push(' ');
+                last.push_str(rest.trim());
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETLIST: &str = "\
+.SUBCKT bitcell bl br wl vdd gnd
+MM0 Q_bar Q gnd gnd NMOS_VTL L=50nm W=205nm
+MM4 Q_bar Q vdd vdd PMOS_VTL L=50nm
++ W=90nm
+MM3 bl wl Q gnd NMOS_HVT L=50nm W=135nm
+.ENDS bitcell
+
+.SUBCKT array bl br wl0 wl1 vdd gnd
+Xbitcell0 bl br wl0 vdd gnd bitcell
+Xbitcell1 bl br wl1 vdd gnd bitcell
+Rconnect0 bl bl_int 0.001
+.ENDS array
+";
+
+    fn config() -> MismatchConfig {
+        serde_json::from_str(r#"{
+            "models": {
+                "nmos_vtl": { "avt": 3.5 },
+                "pmos_vtl": { "sigma_vth": "20mV" }
+            }
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn test_vary_netlist() {
+        let varied = vary_netlist(NETLIST, &config(), 7);
+        assert_eq!(varied, vary_netlist(NETLIST, &config(), 7));
+        assert_ne!(varied, vary_netlist(NETLIST, &config(), 8));
+
+        assert!(varied.contains("Xbitcell0 bl br wl0 vdd gnd bitcell_mc0\n"));
+        assert!(varied.contains("Xbitcell1 bl br wl1 vdd gnd bitcell_mc1\n"));
+        assert!(varied.contains(".SUBCKT bitcell_mc1 bl br wl vdd gnd\n"));
+        assert!(varied.contains(".ENDS bitcell_mc1\n"));
+        // nominal definition is kept
+        assert!(varied.contains("MM0 Q_bar Q gnd gnd NMOS_VTL L=50nm W=205nm\n"));
+
+        let shifts: Vec<&str> = varied.lines().filter(|l| l.contains("delvto=")).collect();
+        assert_eq!(shifts.len(), 4);
+        assert!(shifts.iter().all(|l| l.starts_with("MM0 ") || l.starts_with("MM4 Q_bar Q vdd vdd PMOS_VTL L=50nm W=90nm ")));
+        assert!(varied.lines().any(|l| l == "MM3 bl wl Q gnd NMOS_HVT L=50nm W=135nm"));
+    }
+
+    #[test]
+    fn test_shift_distribution() {
+        let netlist: String = std::iter::once("Xtop a b c d e sense_amp".to_string())
+            .chain((0..2000).map(|i| format!("Xbitcell{i} bl br wl vdd gnd bitcell")))
+            .chain(NETLIST.lines().take(6).map(str::to_string))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let varied = vary_netlist(&netlist, &config(), 1);
+        let shifts: Vec<f64> = varied.lines()
+            .filter(|l| l.starts_with("MM4") && l.contains("delvto="))
+            .map(|l| l.rsplit_once("delvto=").unwrap().1.parse().unwrap())
+            .collect();
+        assert_eq!(shifts.len(), 2000);
+
+        let mean = shifts.iter().sum::<f64>() / shifts.len() as f64;
+        let sigma = (shifts.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / shifts.len() as f64).sqrt();
+        assert!(mean.abs() < 2e-3);
+        assert!((sigma - 0.02).abs() < 2e-3);
+        // not defined sense_amp is left alone
+        assert!(varied.starts_with("Xtop a b c d e sense_amp\n"));
+    }
+}
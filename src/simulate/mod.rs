@@ -2,15 +2,27 @@ mod meas;
 mod execute;
 mod write;
 mod error;
+mod runner;
+mod cache;
+mod retry;
+mod switchlevel;
+mod waveform;
+mod mismatch;
 pub use write::*;
 pub use meas::*;
 pub use error::*;
 pub use execute::*;
+pub use runner::*;
+pub use cache::*;
+pub use retry::*;
+pub use switchlevel::*;
+pub use waveform::*;
+pub use mismatch::*;
 
 use std::{collections::HashMap, path::{Path, PathBuf}, sync::Arc};
 use reda_unit::{t, v, Number, Time, Voltage};
 use itertools::Itertools;
-use crate::{circuit::{PortDirection, ShrCircuit}, pdk::{Enviroment, Pdk}, YouRAMResult};
+use crate::{circuit::{PortDirection, ShrCircuit}, pdk::{Enviroment, ModelStatement, Pdk}, YouRAMResult};
 
 pub struct CircuitSimulator {
     pub writor: SpiceWritor,
@@ -24,10 +36,15 @@ impl CircuitSimulator {
     pub const VDD_PORT_NAME: &'static str = "VDD";
     pub const GND_PORT_NAME: &'static str = "VSS";
     pub const CLOSK_PORT_NAME: &'static str = "CLK";
+    /// Net of the vdd source, connected to `VDD` through the probe
+    pub const VDD_SUPPLY_NET_NAME: &'static str = "VDD_SUPPLY";
+    /// Current probe of vdd, `i(VVDD_PROBE)` is the current drawn by circuit,
+    /// see `SupplyCurrentMeas` and `EnergyMeas`
+    pub const VDD_PROBE_NAME: &'static str = "VDD_PROBE";
 
     /// Create a circuit simulator, and write these auto:
-    /// - include file
-    /// - vdd/gnd source
+    /// - include file, a varied copy of circuit when enviroment has a mismatch seed
+    /// - vdd/gnd source, and a current probe on vdd, see `Enviroment::with_vdd_waveform`
     /// - temperature
     /// - instance of this circuit(all net has the same name with circuit's port)
     /// 
@@ -51,35 +68,74 @@ impl CircuitSimulator {
         P2: Into<PathBuf>,
         C: Into<ShrCircuit>,
     {        
-        let writor = SpiceWritor::open(simulate_path)?;
+        Self::create_with_dialect(circuit, env, pdk, simulate_path, circuit_path, SpiceDialect::default())
+    }
+
+    /// Same as `create`, but write the deck in the syntax of a specific simulator
+    pub fn create_with_dialect<P1, P2, C>(
+        circuit: C, 
+        env: Enviroment,
+        pdk: Arc<Pdk>,
+        simulate_path: P1,
+        circuit_path: P2, 
+        dialect: SpiceDialect,
+    ) -> YouRAMResult<Self> 
+    where 
+        P1: Into<PathBuf>,
+        P2: Into<PathBuf>,
+        C: Into<ShrCircuit>,
+    {        
+        let writor = SpiceWritor::open_with_dialect(simulate_path, dialect)?;
         let mut simulator = Self { writor, circuit: circuit.into(), env, pdk, circuit_path: circuit_path.into() };
         simulator.init()?;
         Ok(simulator)
     }
 
     fn init(&mut self) -> YouRAMResult<()> {
-        let nmos_model_path = self.pdk.nmos_model_path(self.env.process())?;
-        let pmos_model_path = self.pdk.pmos_model_path(self.env.process())?;
+        let model_statements = self.pdk.model_statements(self.env.process())?;
 
         // write includes 
         self.writor.write_content("\n")?;
-        self.writor.write_include(nmos_model_path)?;
-        self.writor.write_include(pmos_model_path)?;
-        self.writor.write_include(&self.circuit_path)?;
+        for statement in model_statements.iter() {
+            match statement {
+                ModelStatement::Param { name, value } => self.writor.write_param(name, value)?,
+                ModelStatement::Include(path) => self.writor.write_include(path)?,
+                ModelStatement::Library { path, section } => self.writor.write_library(path, section)?,
+            }
+        }
+        match self.env.mismatch_seed() {
+            Some(seed) => {
+                let circuit_path = self.write_mismatch_circuit(seed)?;
+                self.writor.write_comment(format!("mismatch seed {}", seed))?;
+                self.writor.write_include(circuit_path)?;
+            }
+            None => self.writor.write_include(&self.circuit_path)?,
+        }
         self.writor.write_content("\n")?;
 
         // write enviroment
-        self.write_dc_stimulate(Self::VDD_PORT_NAME, self.env.voltage())?;
+        if self.env.vdd_waveform().is_empty() {
+            self.writor.write_dc_voltage(Self::VDD_PORT_NAME, Self::VDD_SUPPLY_NET_NAME, self.env.voltage())?;
+        } else {
+            let (times, voltages): (Vec<_>, Vec<_>) = self.env.vdd_waveform().iter().copied().unzip();
+            self.writor.write_pwl_voltage(Self::VDD_PORT_NAME, Self::VDD_SUPPLY_NET_NAME, times.into_iter(), voltages.into_iter())?;
+        }
+        self.writor.write_current_probe(Self::VDD_PROBE_NAME, Self::VDD_SUPPLY_NET_NAME, Self::VDD_PORT_NAME)?;
         self.write_dc_stimulate(Self::GND_PORT_NAME, 0.0)?;
+        for (port_name, voltage) in self.independent_supplies() {
+            self.write_dc_stimulate(port_name, voltage)?;
+        }
         self.writor.write_temperature(self.env.temperature())?;
 
         // write circuit instance
         let mut nets = vec![];
         for port in self.circuit.ports().iter() {
-            match port.read().direction {
+            let port = port.read();
+            match port.direction {
+                PortDirection::Vdd if self.env.has_independent_supply(&port.name) => nets.push(port.name.to_string()),
                 PortDirection::Vdd => nets.push(Self::VDD_PORT_NAME.to_string()),
                 PortDirection::Gnd => nets.push(Self::GND_PORT_NAME.to_string()),
-                _ => nets.push(port.read().name.to_string()),
+                _ => nets.push(port.name.to_string()),
                 
             }
         }
@@ -92,10 +148,49 @@ impl CircuitSimulator {
         Ok(())
     }
 
+    /// Write circuit with varied leafcell transistors next to the deck, see `vary_netlist`
+    fn write_mismatch_circuit(&self, seed: u64) -> YouRAMResult<PathBuf> {
+        let config = self.pdk.mismatch()?;
+        let simulate_path = self.writor.simulate_path();
+        let stem = simulate_path.file_stem().unwrap_or_default().to_string_lossy();
+        let circuit_path = simulate_path.with_file_name(format!("{}_mismatch.sp", stem));
+        write_mismatch_netlist(&self.circuit_path, &circuit_path, config, seed)?;
+        Ok(circuit_path)
+    }
+
+    /// Supply ports of circuit that have their own voltage in enviroment
+    pub fn independent_supplies(&self) -> Vec<(String, Voltage)> {
+        Self::circuit_independent_supplies(&self.circuit, &self.env)
+    }
+
+    /// Current probe of every supply of `circuit` with its voltage, and the sign that turns the
+    /// probe current into the current drawn by circuit: `VDD_PROBE` is in series with vdd, while
+    /// an independent supply's own source delivers its current out of circuit
+    pub fn supply_probes(circuit: &ShrCircuit, env: &Enviroment) -> Vec<(String, Voltage, f64)> {
+        let mut probes = vec![(Self::VDD_PROBE_NAME.to_string(), env.voltage(), 1.0)];
+        probes.extend(Self::circuit_independent_supplies(circuit, env).into_iter().map(|(port, voltage)| (port, voltage, -1.0)));
+        probes
+    }
+
+    fn circuit_independent_supplies(circuit: &ShrCircuit, env: &Enviroment) -> Vec<(String, Voltage)> {
+        circuit.ports().iter()
+            .map(|port| port.read())
+            .filter(|port| port.direction == PortDirection::Vdd && env.has_independent_supply(&port.name))
+            .map(|port| (port.name.to_string(), env.supply_voltage(&port.name)))
+            .collect()
+    }
+
     pub fn simulate(self, execute: &impl SpiceCommand, temp_folder: impl AsRef<Path>) -> YouRAMResult<HashMap<String, Number>> {
         let mut executor = self.writor.close()?;
         executor.simulate(execute, temp_folder.as_ref())
     }
+
+    /// Like `simulate`, also return all waveforms of the transient for metrics
+    /// computed in rust, see `Waveform`
+    pub fn simulate_waveforms(self, execute: &impl SpiceCommand, temp_folder: impl AsRef<Path>) -> YouRAMResult<(HashMap<String, Number>, RawFile)> {
+        let mut executor = self.writor.close()?;
+        executor.simulate_waveforms(execute, temp_folder.as_ref())
+    }
 }
 
 impl CircuitSimulator {
@@ -114,16 +209,22 @@ impl CircuitSimulator {
 
 impl CircuitSimulator {
     pub fn write_clock(&mut self, period: impl Into<Time>) -> YouRAMResult<()> {
+        self.write_clock_with_slew(period, self.env.input_slew())
+    }
+
+    /// Same as `write_clock`, but the clock edges take `slew` instead of the input slew
+    pub fn write_clock_with_slew(&mut self, period: impl Into<Time>, slew: impl Into<Time>) -> YouRAMResult<()> {
         let period = period.into();
+        let slew = slew.into();
         self.writor.write_pulse_voltage(
             Self::CLOSK_PORT_NAME, 
             Self::CLOSK_PORT_NAME,
             self.env.voltage(),
             v!(0),
             t!(0),
-            self.env.input_slew(),
-            self.env.input_slew(),
-            period / 2.0 - self.env.input_slew(),
+            slew,
+            slew,
+            period / 2.0 - slew,
             period
         )?;
         Ok(())
@@ -190,6 +291,23 @@ impl CircuitSimulator {
         self.writor.write_measurement(meas)
     }
 
+    /// Sweep the dc source of `port_name`, which should be written by `write_dc_stimulate`
+    #[inline]
+    pub fn write_dc_sweep(
+        &mut self,
+        port_name: impl AsRef<str>,
+        start: impl Into<Voltage>,
+        stop: impl Into<Voltage>,
+        step: impl Into<Voltage>,
+    ) -> YouRAMResult<()> {
+        self.writor.write_dc_sweep(port_name, start, stop, step)
+    }
+
+    #[inline]
+    pub fn write_nodeset(&mut self, net_name: impl AsRef<str>, voltage: impl Into<Voltage>) -> YouRAMResult<()> {
+        self.writor.write_nodeset(net_name, voltage)
+    }
+
     #[inline]
     pub fn write_trans(&mut self, step: impl Into<Time>, start: impl Into<Time>, end: impl Into<Time>) -> YouRAMResult<()> {
         self.writor.write_trans(step, start, end)
@@ -0,0 +1,449 @@
+use std::{fmt, path::{Path, PathBuf}, sync::LazyLock, time::Duration};
+use regex::Regex;
+use reda_unit::{Number, Time};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::{ErrorContext, YouRAMError, YouRAMResult};
+use super::{parse_spice_number, Meas, MeasError, SimulateCache, SimulateError, SpiceCommand, SpiceDialect, SpiceWritor};
+
+/// Simulator options relaxed for a retry after a convergence failure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConvergenceOptions {
+    /// `.option reltol=<reltol>`
+    #[serde(default)]
+    pub reltol: Option<f64>,
+    /// `.option method=gear`
+    #[serde(default)]
+    pub gear: bool,
+    /// Scale of the `.TRAN` step
+    #[serde(default = "default_tstep_scale")]
+    pub tstep_scale: f64,
+}
+
+/// Timeout of a simulation, and options of each retry when it fails to converge
+///
+/// A run is retried when the simulator reports timestep too small or a singular
+/// matrix, or is killed by timeout. Each retry simulates a copy of the deck
+/// `<deck>_retry<n>.sp` with `.option`s and `.TRAN` step of its `ConvergenceOptions`.
+///
+/// # Default:
+/// - timeout: none
+/// - retries: `reltol=1e-3 method=gear`, then also `tstep x0.1`
+///
+/// # Example
+///
+/// ```ignore
+/// let policy = RetryPolicy::new()
+///     .timeout(Duration::from_secs(600))
+///     .retries(vec![ConvergenceOptions::gear()]);
+/// let command = RetryCommand::new(Box::new(NgSpice), policy);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Wall-clock seconds of each run
+    #[serde(default, with = "timeout_seconds")]
+    pub timeout: Option<Duration>,
+    #[serde(default = "default_retries")]
+    pub retries: Vec<ConvergenceOptions>,
+}
+
+impl ConvergenceOptions {
+    /// Only switch integration method to gear
+    pub fn gear() -> Self {
+        Self { reltol: None, gear: true, tstep_scale: 1.0 }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn retries(mut self, retries: Vec<ConvergenceOptions>) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// No retry, only the timeout
+    pub fn no_retry(mut self) -> Self {
+        self.retries.clear();
+        self
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { timeout: None, retries: default_retries() }
+    }
+}
+
+/// Simulator backend with timeout and convergence retries of `policy`
+pub struct RetryCommand {
+    pub command: Box<dyn SpiceCommand>,
+    pub policy: RetryPolicy,
+}
+
+impl RetryCommand {
+    pub fn new(command: Box<dyn SpiceCommand>, policy: RetryPolicy) -> Self {
+        Self { command, policy }
+    }
+
+    /// Run `sim_filepath` by `execute`, then retry on a convergence failure with
+    /// each `ConvergenceOptions` of policy
+    fn retry<T>(&self, sim_filepath: &Path, execute: impl Fn(&Path) -> YouRAMResult<T>) -> YouRAMResult<T> {
+        let mut result = execute(sim_filepath);
+        for (index, options) in self.policy.retries.iter().enumerate() {
+            match &result {
+                Err(YouRAMError::Simulate(err)) if err.is_convergence_failure() => {}
+                _ => break,
+            }
+            let attempt = index + 1;
+            warn!(
+                "simulate '{}' failed to converge, retry [{}/{}] with {}",
+                sim_filepath.display(), attempt, self.policy.retries.len(), options
+            );
+            let retry_path = self.write_retry_deck(sim_filepath, attempt, options)?;
+            result = execute(&retry_path);
+            if result.is_ok() {
+                info!("simulate '{}' converged with {}", sim_filepath.display(), options);
+            }
+        }
+        result
+    }
+
+    /// Check a run that ended normally, ngspice only skips the analysis on convergence
+    /// trouble. Its output is diagnosed only when a meas is missing or failed, a warning
+    /// like singular matrix of an analysis that recovered is no failure
+    fn check_run(&self, sim_filepath: &Path, temp_folder: &Path, result_path: &Path) -> YouRAMResult<()> {
+        static MEAS_FAILED: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"(?im)(?:measure\s+\S+\s+failed|^\s*\S+\s*=\s*failed\s*$)").unwrap()
+        });
+
+        let result = std::fs::read_to_string(result_path).ok();
+        if result.as_ref().is_some_and(|content| !MEAS_FAILED.is_match(content)) {
+            return Ok(());
+        }
+
+        let output = self.command.log_filepath(sim_filepath, temp_folder).ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .into_iter()
+            .chain(result)
+            .collect::<Vec<_>>()
+            .join("\n");
+        match self.command.diagnose(&output) {
+            Some(cause) if cause.is_convergence_failure() => {
+                Err(self.command.diagnose_failure(sim_filepath, temp_folder, cause))?
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Copy of deck at `sim_filepath` with `options`, next to it so relative includes still work
+    fn write_retry_deck(&self, sim_filepath: &Path, attempt: usize, options: &ConvergenceOptions) -> YouRAMResult<PathBuf> {
+        let stem = sim_filepath
+            .file_stem()
+            .ok_or_else(|| SimulateError::InvalidPath(sim_filepath.to_path_buf()))?;
+        let retry_path = sim_filepath.with_file_name(format!("{}_retry{}.sp", stem.to_string_lossy(), attempt));
+
+        let content = std::fs::read_to_string(sim_filepath)
+            .with_context(|| format!("read deck '{}'", sim_filepath.display()))?;
+        // lines are copied as they are, the deck has its own dialect header
+        let mut writor = SpiceWritor::open(&retry_path)?;
+        writor.set_dialect(self.command.dialect());
+        for (index, line) in content.lines().enumerate() {
+            let number = |token: &str| parse_spice_number(token).map(|t| Time::new(Number::from_f64(t))).ok_or_else(|| {
+                SimulateError::DeckSyntax(sim_filepath.to_path_buf(), index + 1, format!("invalid number '{}' of tran", token))
+            });
+            // `.TRAN <step> <end> [<start>]`
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                [command, step, end, rest @ ..] if command.eq_ignore_ascii_case(".tran") => {
+                    let start = match rest.first() {
+                        Some(start) => number(start)?,
+                        None => Time::new(0.0),
+                    };
+                    writor.write_options(options)?;
+                    writor.write_trans(number(step)? * options.tstep_scale, start, number(end)?)?;
+                }
+                _ => writor.write_content(format!("{}\n", line))?,
+            }
+        }
+        writor.close()?;
+
+        Ok(retry_path)
+    }
+}
+
+impl SpiceCommand for RetryCommand {
+    fn dialect(&self) -> SpiceDialect {
+        self.command.dialect()
+    }
+
+    fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+        self.command.simulate_command(sim_filepath, temp_folder)
+    }
+
+    fn meas_result_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.command.meas_result_filepath(sim_filepath, temp_folder)
+    }
+
+    fn parse_meas_result(&self, meas: &dyn Meas, content: &str) -> Result<Number, MeasError> {
+        self.command.parse_meas_result(meas, content)
+    }
+
+    fn log_filepath(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.command.log_filepath(sim_filepath, temp_folder)
+    }
+
+    fn diagnose(&self, output: &str) -> Option<SimulateError> {
+        self.command.diagnose(output)
+    }
+
+    fn execute(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        self.execute_timeout(sim_filepath, temp_folder, None)
+    }
+
+    /// `timeout` limits each attempt, the policy timeout is used if `None`
+    fn execute_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<PathBuf> {
+        let timeout = timeout.or(self.policy.timeout);
+        self.retry(sim_filepath, |sim_filepath| {
+            let result_path = self.command.execute_timeout(sim_filepath, temp_folder, timeout)?;
+            self.check_run(sim_filepath, temp_folder, &result_path)?;
+            Ok(result_path)
+        })
+    }
+
+    fn execute_waveforms(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<(PathBuf, PathBuf)> {
+        self.execute_waveforms_timeout(sim_filepath, temp_folder, None)
+    }
+
+    /// `timeout` limits each attempt, the policy timeout is used if `None`
+    fn execute_waveforms_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<(PathBuf, PathBuf)> {
+        let timeout = timeout.or(self.policy.timeout);
+        self.retry(sim_filepath, |sim_filepath| {
+            let (result_path, raw_path) = self.command.execute_waveforms_timeout(sim_filepath, temp_folder, timeout)?;
+            self.check_run(sim_filepath, temp_folder, &result_path)?;
+            Ok((result_path, raw_path))
+        })
+    }
+
+    fn cache(&self) -> Option<&SimulateCache> {
+        self.command.cache()
+    }
+}
+
+impl fmt::Display for ConvergenceOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = vec![];
+        if let Some(reltol) = self.reltol {
+            options.push(format!("reltol={}", reltol));
+        }
+        if self.gear {
+            options.push("method=gear".to_string());
+        }
+        if self.tstep_scale != 1.0 {
+            options.push(format!("tstep x{}", self.tstep_scale));
+        }
+        write!(f, "'{}'", options.join(" "))
+    }
+}
+
+fn default_tstep_scale() -> f64 {
+    1.0
+}
+
+fn default_retries() -> Vec<ConvergenceOptions> {
+    vec![
+        ConvergenceOptions { reltol: Some(1e-3), gear: true, tstep_scale: 1.0 },
+        ConvergenceOptions { reltol: Some(1e-3), gear: true, tstep_scale: 0.1 },
+    ]
+}
+
+mod timeout_seconds {
+    use std::time::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(timeout: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match timeout {
+            Some(timeout) => serializer.serialize_some(&timeout.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        let seconds: Option<f64> = Option::deserialize(deserializer)?;
+        Ok(seconds.map(Duration::from_secs_f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+    use crate::simulate::{diagnose_ngspice, VoltageAtMeas};
+    use super::*;
+
+    /// Fails like ngspice with timestep too small, unless the deck uses gear
+    struct StiffCommand;
+
+    impl SpiceCommand for StiffCommand {
+        fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+            let meas = self.meas_result_filepath(sim_filepath, temp_folder)?;
+            Ok(format!(
+                "if grep -q 'method=gear' {deck}; then echo 'v_out = 5.0e-01' > {meas}; \
+                 else echo 'Timestep too small; time = 1e-09' > {meas}; echo 'v_out = failed' >> {meas}; fi",
+                deck = sim_filepath.display(),
+                meas = meas.display(),
+            ))
+        }
+
+        fn diagnose(&self, output: &str) -> Option<SimulateError> {
+            diagnose_ngspice(output)
+        }
+    }
+
+    fn simulate(command: &RetryCommand, temp: &Path) -> YouRAMResult<std::collections::HashMap<String, Number>> {
+        let mut writor = SpiceWritor::open(temp.join("stiff.sp"))?;
+        writor.write_content("* stiff\n")?;
+        writor.write_measurement(Box::new(VoltageAtMeas::new("v_out", "out", 1e-9)))?;
+        writor.write_trans(1e-11, 0.0, 2e-9)?;
+        writor.write_end()?;
+        writor.close()?.simulate(command, temp)
+    }
+
+    #[test]
+    fn test_retry_convergence() {
+        let temp = tempfile::tempdir().unwrap();
+
+        let command = RetryCommand::new(Box::new(StiffCommand), RetryPolicy::new().no_retry());
+        assert!(simulate(&command, temp.path()).is_err());
+
+        let command = RetryCommand::new(Box::new(StiffCommand), RetryPolicy::new());
+        let results = simulate(&command, temp.path()).unwrap();
+        assert_eq!(results["v_out"].to_f64(), 0.5);
+
+        let retry_deck = std::fs::read_to_string(temp.path().join("stiff_retry1.sp")).unwrap();
+        assert!(retry_deck.contains(".option reltol=0.001\n.option method=gear\n.TRAN 10ps 2ns 0s\n"));
+        assert!(!temp.path().join("stiff_retry2.sp").exists());
+    }
+
+    #[test]
+    fn test_recovered_warning() {
+        /// Ends normally with its meas, ngspice warns of singular matrix while gmin stepping
+        struct WarnCommand;
+        impl SpiceCommand for WarnCommand {
+            fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+                Ok(format!(
+                    "echo 'Warning: singular matrix: check node xbitcell.q' > {log}; echo 'v_out = 5.0e-01' > {meas}",
+                    log = self.log_filepath(sim_filepath, temp_folder)?.display(),
+                    meas = self.meas_result_filepath(sim_filepath, temp_folder)?.display(),
+                ))
+            }
+
+            fn diagnose(&self, output: &str) -> Option<SimulateError> {
+                diagnose_ngspice(output)
+            }
+        }
+
+        let temp = tempfile::tempdir().unwrap();
+        let command = RetryCommand::new(Box::new(WarnCommand), RetryPolicy::new());
+        let results = simulate(&command, temp.path()).unwrap();
+        assert_eq!(results["v_out"].to_f64(), 0.5);
+        assert!(!temp.path().join("stiff_retry1.sp").exists());
+    }
+
+    #[test]
+    fn test_retry_dialect() {
+        struct StiffXyce;
+        impl SpiceCommand for StiffXyce {
+            fn dialect(&self) -> SpiceDialect {
+                SpiceDialect::Xyce
+            }
+
+            fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+                StiffCommand.simulate_command(sim_filepath, temp_folder)
+            }
+
+            fn diagnose(&self, output: &str) -> Option<SimulateError> {
+                diagnose_ngspice(output)
+            }
+        }
+
+        let temp = tempfile::tempdir().unwrap();
+        let command = RetryCommand::new(Box::new(StiffXyce), RetryPolicy::new());
+        simulate(&command, temp.path()).unwrap();
+
+        let retry_deck = std::fs::read_to_string(temp.path().join("stiff_retry1.sp")).unwrap();
+        assert!(retry_deck.contains(".options timeint reltol=0.001 method=gear\n.TRAN 10ps 2ns 0s\n"));
+        assert!(!retry_deck.contains(".option "));
+    }
+
+    #[test]
+    fn test_retry_waveforms() {
+        /// `StiffCommand` that also writes an empty rawfile
+        struct StiffWaveform;
+        impl SpiceCommand for StiffWaveform {
+            fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+                StiffCommand.simulate_command(sim_filepath, temp_folder)
+            }
+
+            fn diagnose(&self, output: &str) -> Option<SimulateError> {
+                diagnose_ngspice(output)
+            }
+
+            fn execute_waveforms_timeout(&self, sim_filepath: &Path, temp_folder: &Path, timeout: Option<Duration>) -> YouRAMResult<(PathBuf, PathBuf)> {
+                let result_path = self.execute_timeout(sim_filepath, temp_folder, timeout)?;
+                let raw_path = result_path.with_extension("raw");
+                std::fs::write(&raw_path, "")?;
+                Ok((result_path, raw_path))
+            }
+        }
+
+        let temp = tempfile::tempdir().unwrap();
+        let deck = temp.path().join("stiff.sp");
+        std::fs::write(&deck, "* stiff\n.meas tran v_out FIND v(out) AT=1ns\n.TRAN 10ps 2ns 0s\n.end\n").unwrap();
+
+        let command = RetryCommand::new(Box::new(StiffWaveform), RetryPolicy::new().no_retry());
+        assert!(command.execute_waveforms(&deck, temp.path()).is_err());
+
+        let command = RetryCommand::new(Box::new(StiffWaveform), RetryPolicy::new());
+        let (result_path, raw_path) = command.execute_waveforms(&deck, temp.path()).unwrap();
+        assert_eq!(result_path, temp.path().join("stiff_retry1.meas"));
+        assert_eq!(raw_path, temp.path().join("stiff_retry1.raw"));
+
+        let command = RetryCommand::new(Box::new(StiffCommand), RetryPolicy::new());
+        assert!(command.execute_waveforms(&deck, temp.path()).is_err());
+    }
+
+    #[test]
+    fn test_timeout() {
+        struct HangCommand;
+        impl SpiceCommand for HangCommand {
+            fn simulate_command(&self, _sim_filepath: &Path, _temp_folder: &Path) -> YouRAMResult<String> {
+                Ok("sleep 10".into())
+            }
+        }
+
+        let temp = tempfile::tempdir().unwrap();
+        let policy = RetryPolicy::new().timeout(Duration::from_millis(200)).no_retry();
+        let command = RetryCommand::new(Box::new(HangCommand), policy);
+
+        let start = Instant::now();
+        let err = simulate(&command, temp.path()).unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(err.to_string().contains("timeout"));
+
+        let command = RetryCommand::new(Box::new(HangCommand), RetryPolicy::new().no_retry());
+        let deck = temp.path().join("hang.sp");
+        std::fs::write(&deck, "* hang\n.end\n").unwrap();
+
+        let start = Instant::now();
+        let err = command.execute_timeout(&deck, temp.path(), Some(Duration::from_millis(200))).unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(err.to_string().contains("timeout"));
+    }
+}
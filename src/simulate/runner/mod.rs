@@ -0,0 +1,161 @@
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Mutex}};
+use tracing::info;
+use crate::{ErrorContext, YouRAMResult};
+
+/// Run independent simulation jobs concurrently
+///
+/// Each job gets its own working directory `<work_folder>/<name>_<index>`
+/// and file names, so decks and meas results never collide.
+/// Results are returned in the order of the inputs.
+///
+/// # Default:
+/// - jobs: number of cpu
+///
+/// # Example
+///
+/// ```ignore
+/// let results = JobRunner::new("./temp")
+///     .jobs(4)
+///     .run("timing", envs, |env, job| {
+///         simulate(env, job.simulate_path(), job.work_folder())
+///     })?;
+/// ```
+pub struct JobRunner {
+    pub work_folder: PathBuf,
+    pub jobs: usize,
+}
+
+/// Where a job should put its files
+pub struct JobContext {
+    pub index: usize,
+    pub name: String,
+    pub work_folder: PathBuf,
+}
+
+impl JobContext {
+    pub fn work_folder(&self) -> &Path {
+        &self.work_folder
+    }
+
+    /// `<work_folder>/<name>_<index>.sp`
+    pub fn simulate_path(&self) -> PathBuf {
+        self.work_folder.join(format!("{}_{}.sp", self.name, self.index))
+    }
+}
+
+impl JobRunner {
+    pub fn new(work_folder: impl Into<PathBuf>) -> Self {
+        Self { work_folder: work_folder.into(), jobs: default_jobs() }
+    }
+
+    /// Set max concurrent jobs, 0 means number of cpu
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = if jobs == 0 { default_jobs() } else { jobs };
+        self
+    }
+
+    /// Run `job` on each input, stop taking new inputs after the first failure
+    pub fn run<I, T, F>(&self, name: &str, inputs: Vec<I>, job: F) -> YouRAMResult<Vec<T>>
+    where
+        I: Send,
+        T: Send,
+        F: Fn(I, &JobContext) -> YouRAMResult<T> + Sync,
+    {
+        let total = inputs.len();
+        let inputs: Vec<Mutex<Option<I>>> = inputs.into_iter().map(|i| Mutex::new(Some(i))).collect();
+        let results: Vec<Mutex<Option<YouRAMResult<T>>>> = (0..total).map(|_| Mutex::new(None)).collect();
+
+        let next = AtomicUsize::new(0);
+        let finished = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+
+        let worker = || {
+            loop {
+                if failed.load(Ordering::Relaxed) {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= total {
+                    break;
+                }
+
+                let input = inputs[index].lock().unwrap().take().expect("each input is taken once");
+                let context = JobContext {
+                    index,
+                    name: name.to_string(),
+                    work_folder: self.work_folder.join(format!("{}_{}", name, index)),
+                };
+
+                let result = std::fs::create_dir_all(&context.work_folder)
+                    .with_context(|| format!("create job folder '{}'", context.work_folder.display()))
+                    .and_then(|_| job(input, &context));
+                if result.is_err() {
+                    failed.store(true, Ordering::Relaxed);
+                }
+                *results[index].lock().unwrap() = Some(result);
+
+                let finished = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                info!("{} job [{}/{}] finished", name, finished, total);
+            }
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.jobs.min(total) {
+                scope.spawn(worker);
+            }
+        });
+
+        let mut outputs = Vec::with_capacity(total);
+        // jobs after a failure may never run, but the failure comes first
+        for (index, result) in results.into_iter().enumerate() {
+            if let Some(result) = result.into_inner().unwrap() {
+                outputs.push(result.with_context(|| format!("{} job {}", name, index))?);
+            }
+        }
+
+        Ok(outputs)
+    }
+}
+
+fn default_jobs() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::YouRAMError;
+    use super::*;
+
+    #[test]
+    fn test_run_in_order() {
+        let temp = tempfile::tempdir().unwrap();
+        let runner = JobRunner::new(temp.path()).jobs(3);
+
+        let results = runner.run("square", (0..8).collect(), |i: u64, job| {
+            // later jobs finish first
+            std::thread::sleep(Duration::from_millis(10 * (8 - i)));
+            assert!(job.work_folder().is_dir());
+            assert_eq!(job.simulate_path(), temp.path().join(format!("square_{i}")).join(format!("square_{i}.sp")));
+            Ok(i * i)
+        }).unwrap();
+
+        assert_eq!(results, (0..8).map(|i| i * i).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_run_failed() {
+        let temp = tempfile::tempdir().unwrap();
+        let runner = JobRunner::new(temp.path()).jobs(2);
+
+        let result = runner.run("fail", (0..4).collect(), |i: usize, _| {
+            if i == 1 {
+                Err(YouRAMError::Message("boom".into()))
+            } else {
+                Ok(i)
+            }
+        });
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,591 @@
+use std::{collections::HashMap, path::Path, sync::LazyLock};
+use regex::Regex;
+use crate::{ErrorContext, YouRAMResult};
+use crate::simulate::{parse_spice_number, resolve_include, SimulateError};
+
+/// Max depth of subckt instances, deeper must be a recursive definition
+const MAX_HIERARCHY_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MosKind {
+    Nmos,
+    Pmos,
+}
+
+/// A transistor of the flatten netlist, `resistance` is its on resistance relative to others
+#[derive(Debug, Clone)]
+pub struct Mos {
+    pub kind: MosKind,
+    pub gate: usize,
+    pub source: usize,
+    pub drain: usize,
+    pub resistance: f64,
+}
+
+/// Flatten transistor netlist, nets are named by instance path like `xsram.xbank.bl0`
+#[derive(Debug, Default)]
+pub struct Netlist {
+    pub names: Vec<String>,
+    pub ids: HashMap<String, usize>,
+    pub mos: Vec<Mos>,
+}
+
+impl Netlist {
+    pub fn net(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+
+    /// Make `a` and `b` one net, before any of them has a transistor
+    pub fn short(&mut self, a: &str, b: &str) -> YouRAMResult<()> {
+        match (self.ids.get(a).copied(), self.ids.get(b).copied()) {
+            (Some(x), Some(y)) if x != y => Err(SimulateError::UnsupportSwitchLevel(format!("short of nets '{}' and '{}'", a, b)))?,
+            (Some(_), Some(_)) => {}
+            (Some(id), None) => { self.ids.insert(b.to_string(), id); }
+            (None, Some(id)) => { self.ids.insert(a.to_string(), id); }
+            (None, None) => {
+                let id = self.net(a);
+                self.ids.insert(b.to_string(), id);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn net_size(&self) -> usize {
+        self.names.len()
+    }
+}
+
+/// Voltage of an independent source
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stimulus {
+    Dc(f64),
+    Pwl(Vec<(f64, f64)>),
+    Pulse { v1: f64, v2: f64, delay: f64, rise: f64, fall: f64, width: f64, period: f64 },
+}
+
+impl Stimulus {
+    pub fn max_voltage(&self) -> f64 {
+        match self {
+            Self::Dc(v) => *v,
+            Self::Pwl(points) => points.iter().map(|(_, v)| *v).fold(f64::MIN, f64::max),
+            Self::Pulse { v1, v2, .. } => v1.max(*v2),
+        }
+    }
+
+    pub fn voltage_at_zero(&self) -> f64 {
+        match self {
+            Self::Dc(v) => *v,
+            Self::Pwl(points) => points.first().map(|(_, v)| *v).unwrap_or(0.0),
+            Self::Pulse { v1, delay, rise, v2, .. } => if *delay <= 0.0 && *rise <= 0.0 { *v2 } else { *v1 },
+        }
+    }
+
+    /// Times in `(0, end]` where the voltage crosses `threshold`, with the new logic value
+    pub fn crossings(&self, threshold: f64, end: f64) -> Vec<(f64, bool)> {
+        let mut crossings = vec![];
+        match self {
+            Self::Dc(_) => {}
+            Self::Pwl(points) => {
+                for window in points.windows(2) {
+                    let ((t0, v0), (t1, v1)) = (window[0], window[1]);
+                    if (v0 > threshold) == (v1 > threshold) {
+                        continue;
+                    }
+                    let time = t0 + (threshold - v0) / (v1 - v0) * (t1 - t0);
+                    if time > 0.0 && time <= end {
+                        crossings.push((time, v1 > threshold));
+                    }
+                }
+            }
+            &Self::Pulse { v1, v2, delay, rise, fall, width, period } => {
+                if (v1 > threshold) == (v2 > threshold) {
+                    return crossings;
+                }
+                let mut start = delay;
+                while start <= end {
+                    for (time, voltage) in [(start + rise / 2.0, v2), (start + rise + width + fall / 2.0, v1)] {
+                        if time > 0.0 && time <= end {
+                            crossings.push((time, voltage > threshold));
+                        }
+                    }
+                    if period <= 0.0 {
+                        break;
+                    }
+                    start += period;
+                }
+            }
+        }
+        crossings
+    }
+}
+
+/// `.meas tran <name> FIND v(<net>) AT=<time>`
+#[derive(Debug, Clone)]
+pub struct FindAt {
+    pub name: String,
+    pub net: usize,
+    pub time: f64,
+}
+
+/// Everything switch-level simulate needs from a spice deck
+#[derive(Debug, Default)]
+pub struct Deck {
+    pub netlist: Netlist,
+    pub sources: Vec<(usize, Stimulus)>,
+    pub measures: Vec<FindAt>,
+}
+
+#[derive(Debug, Default)]
+struct SubcktDef {
+    ports: Vec<String>,
+    elements: Vec<Element>,
+}
+
+#[derive(Debug)]
+enum Element {
+    Mos { nets: [String; 3], kind: MosKind, resistance: f64 },
+    Instance { name: String, nets: Vec<String>, subckt: String },
+    /// A resistor, like wire RC, connects its nets
+    Wire { nets: [String; 2] },
+}
+
+#[derive(Debug, Default)]
+struct Parser {
+    subckts: HashMap<String, SubcktDef>,
+    top_instances: Vec<(String, Vec<String>, String)>,
+    sources: Vec<(String, Stimulus)>,
+    /// Nets shorted by a zero volt source, like a current probe
+    shorts: Vec<(String, String)>,
+    measures: Vec<(String, String, f64)>,
+}
+
+impl Deck {
+    /// Parse deck and its `.include`s, and flatten the top instances down to transistors
+    pub fn load(path: &Path) -> YouRAMResult<Self> {
+        let mut parser = Parser::default();
+        parser.parse_file(path, true)?;
+        parser.build()
+    }
+}
+
+impl Parser {
+    fn parse_file(&mut self, path: &Path, is_top: bool) -> YouRAMResult<()> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("read deck '{}'", path.display()))?;
+        let syntax_error = |line: usize, msg: String| SimulateError::DeckSyntax(path.to_path_buf(), line, msg);
+
+        let mut subckt: Option<(String, SubcktDef)> = None;
+        // first line of a deck is the title
+        let skip = if is_top { 1 } else { 0 };
+        for (line_number, line) in Self::logical_lines(&content, skip) {
+            // spice is case insensitive, but file paths are not
+            let raw_tokens: Vec<&str> = line.split_whitespace().collect();
+            let line = line.to_ascii_lowercase();
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let Some(first) = tokens.first() else { continue };
+
+            match first.chars().next().unwrap() {
+                '.' => match *first {
+                    ".include" | ".inc" => {
+                        let include = raw_tokens.get(1).ok_or_else(|| syntax_error(line_number, "missing include file".into()))?;
+                        let include = include.trim_matches(|c| c == '"' || c == '\'');
+                        let include_path = resolve_include(path, include)
+                            .ok_or_else(|| syntax_error(line_number, format!("include file '{}' not found", include)))?;
+                        self.parse_file(&include_path, false)?;
+                    }
+                    ".subckt" => {
+                        let name = tokens.get(1).ok_or_else(|| syntax_error(line_number, "missing subckt name".into()))?;
+                        let ports = tokens[2..].iter().filter(|t| !t.contains('=')).map(|t| t.to_string()).collect();
+                        subckt = Some((name.to_string(), SubcktDef { ports, elements: vec![] }));
+                    }
+                    ".ends" => {
+                        if let Some((name, def)) = subckt.take() {
+                            self.subckts.insert(name, def);
+                        }
+                    }
+                    ".meas" | ".measure" if subckt.is_none() => {
+                        let measure = Self::parse_measure(&tokens).map_err(|msg| syntax_error(line_number, msg))?;
+                        self.measures.push(measure);
+                    }
+                    // models, options, params, temperature, analysis: no meaning in switch level
+                    _ => {}
+                }
+                'm' => {
+                    let element = Self::parse_mos(&tokens).map_err(|msg| syntax_error(line_number, msg))?;
+                    match subckt.as_mut() {
+                        Some((_, def)) => def.elements.push(element),
+                        None => Err(SimulateError::UnsupportSwitchLevel(format!("top level transistor '{}'", first)))?,
+                    }
+                }
+                'x' => {
+                    let nets: Vec<String> = tokens[1..].iter().filter(|t| !t.contains('=')).map(|t| t.to_string()).collect();
+                    let (subckt_name, nets) = nets.split_last()
+                        .ok_or_else(|| syntax_error(line_number, "missing subckt name".into()))?;
+                    match subckt.as_mut() {
+                        Some((_, def)) => def.elements.push(Element::Instance {
+                            name: first.to_string(), nets: nets.to_vec(), subckt: subckt_name.clone()
+                        }),
+                        None => self.top_instances.push((first.to_string(), nets.to_vec(), subckt_name.clone())),
+                    }
+                }
+                'v' if subckt.is_none() => {
+                    let (pos, neg, stimulus) = Self::parse_source(&tokens).map_err(|msg| syntax_error(line_number, msg))?;
+                    match (neg.as_str(), stimulus) {
+                        ("0", stimulus) => self.sources.push((pos, stimulus)),
+                        (_, Stimulus::Dc(0.0)) => self.shorts.push((pos, neg)),
+                        _ => Err(syntax_error(line_number, format!("source '{}' must be referenced to ground '0'", first)))?,
+                    }
+                }
+                'r' => {
+                    let (Some(a), Some(b)) = (tokens.get(1), tokens.get(2)) else {
+                        Err(syntax_error(line_number, format!("resistor '{}' needs 2 nets", first)))?
+                    };
+                    match subckt.as_mut() {
+                        Some((_, def)) => def.elements.push(Element::Wire { nets: [a.to_string(), b.to_string()] }),
+                        None => Err(SimulateError::UnsupportSwitchLevel(format!("top level resistor '{}'", first)))?,
+                    }
+                }
+                // only charge a node, the node size takes place of it
+                'c' => {}
+                // `simulator lang=spice` of spectre deck
+                's' if *first == "simulator" => {}
+                _ => Err(SimulateError::UnsupportSwitchLevel(format!("element '{}'", first)))?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lines without comments, `+` lines are joined to the previous one
+    fn logical_lines(content: &str, skip: usize) -> Vec<(usize, String)> {
+        let mut lines: Vec<(usize, String)> = vec![];
+        for (index, line) in content.lines().enumerate().skip(skip) {
+            let line = line.split(['$', ';']).next().unwrap_or("").trim().to_string();
+            if let Some(rest) = line.strip_prefix('+') {
+                if let Some((_, last)) = lines.last_mut() {
+                    last.push(' ');
+                    last.push_str(rest);
+                }
+                continue;
+            }
+            if line.is_empty() || line.starts_with('*') {
+                continue;
+            }
+            lines.push((index + 1, line));
+        }
+        lines
+    }
+
+    /// `M<name> <drain> <gate> <source> <bulk> <model> L=<l> W=<w> ...`
+    fn parse_mos(tokens: &[&str]) -> Result<Element, String> {
+        if tokens.len() < 6 {
+            return Err(format!("transistor '{}' needs 4 nets and a model", tokens[0]));
+        }
+        let model = tokens[5];
+        let kind = if model.contains("pmos") || model.contains("pfet") {
+            MosKind::Pmos
+        } else if model.contains("nmos") || model.contains("nfet") {
+            MosKind::Nmos
+        } else if model.starts_with('p') {
+            MosKind::Pmos
+        } else if model.starts_with('n') {
+            MosKind::Nmos
+        } else {
+            return Err(format!("unknown type of transistor model '{}'", model));
+        };
+
+        let mut length = None;
+        let mut width = None;
+        for token in &tokens[6..] {
+            if let Some((key, value)) = token.split_once('=') {
+                let value = parse_spice_number(value);
+                match key {
+                    "l" => length = value,
+                    "w" => width = value,
+                    _ => {}
+                }
+            }
+        }
+
+        // pmos carriers are about half as mobile
+        let mobility = match kind {
+            MosKind::Nmos => 2.0,
+            MosKind::Pmos => 1.0,
+        };
+        let ratio = match (length, width) {
+            (Some(l), Some(w)) if l > 0.0 && w > 0.0 => l / w,
+            _ => 1.0,
+        };
+
+        Ok(Element::Mos {
+            nets: [tokens[1].to_string(), tokens[2].to_string(), tokens[3].to_string()],
+            kind,
+            resistance: ratio / mobility,
+        })
+    }
+
+    /// `V<name> <pos> <neg> [DC] <v> | PWL(<t> <v> ...) | PULSE(<v1> <v2> <td> <tr> <tf> <pw> <per>)`
+    fn parse_source(tokens: &[&str]) -> Result<(String, String, Stimulus), String> {
+        if tokens.len() < 4 {
+            return Err(format!("source '{}' needs 2 nets and a value", tokens[0]));
+        }
+
+        let value = tokens[3..].join(" ");
+        let number = |s: &str| parse_spice_number(s).ok_or_else(|| format!("invalid number '{}'", s));
+        let arguments = |s: &str| -> Result<Vec<f64>, String> {
+            s.trim().trim_start_matches('(').trim_end_matches(')')
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|s| !s.is_empty())
+                .map(number)
+                .collect()
+        };
+
+        let stimulus = if let Some(rest) = value.strip_prefix("pwl") {
+            let values = arguments(rest)?;
+            if values.len() % 2 != 0 {
+                return Err(format!("pwl of '{}' needs time voltage pairs", tokens[0]));
+            }
+            Stimulus::Pwl(values.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+        } else if let Some(rest) = value.strip_prefix("pulse") {
+            let values = arguments(rest)?;
+            let arg = |i: usize| values.get(i).copied().unwrap_or(0.0);
+            if values.len() < 2 {
+                return Err(format!("pulse of '{}' needs at least 2 voltages", tokens[0]));
+            }
+            Stimulus::Pulse { v1: arg(0), v2: arg(1), delay: arg(2), rise: arg(3), fall: arg(4), width: arg(5), period: arg(6) }
+        } else {
+            Stimulus::Dc(number(value.strip_prefix("dc").unwrap_or(&value).trim())?)
+        };
+
+        Ok((tokens[1].to_string(), tokens[2].to_string(), stimulus))
+    }
+
+    /// Only `<name> FIND v(<net>) AT=<time>`, the voltage of a node at a time
+    fn parse_measure(tokens: &[&str]) -> Result<(String, String, f64), String> {
+        static RE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^find\s+v\(\s*([^)\s]+)\s*\)\s+at\s*=\s*(\S+)$").unwrap()
+        });
+        let (name, rest) = match tokens {
+            [_, _, name, rest @ ..] => (name, rest.join(" ")),
+            _ => return Err("meas needs a type and a name".into()),
+        };
+        let caps = RE.captures(&rest)
+            .ok_or_else(|| format!("only 'FIND v(<net>) AT=<time>' meas supported, got '{}'", rest))?;
+        let time = parse_spice_number(&caps[2]).ok_or_else(|| format!("invalid time '{}'", &caps[2]))?;
+        Ok((name.to_string(), caps[1].to_string(), time))
+    }
+
+    fn build(self) -> YouRAMResult<Deck> {
+        let mut deck = Deck::default();
+
+        for (a, b) in self.shorts.iter() {
+            deck.netlist.short(a, b)?;
+        }
+
+        for (name, nets, subckt) in self.top_instances.iter() {
+            let nets: Vec<usize> = nets.iter().map(|net| deck.netlist.net(net)).collect();
+            self.flatten(&mut deck.netlist, name, &nets, subckt, 0)?;
+        }
+
+        for (net, stimulus) in self.sources {
+            let net = deck.netlist.net(&net);
+            deck.sources.push((net, stimulus));
+        }
+
+        for (name, net, time) in self.measures {
+            let net = *deck.netlist.ids.get(&net)
+                .ok_or_else(|| SimulateError::UnsupportSwitchLevel(format!("meas '{}' of unknown net '{}'", name, net)))?;
+            deck.measures.push(FindAt { name, net, time });
+        }
+
+        Ok(deck)
+    }
+
+    fn flatten<'a>(&'a self, netlist: &mut Netlist, path: &str, nets: &[usize], subckt_name: &str, depth: usize) -> YouRAMResult<()> {
+        if depth > MAX_HIERARCHY_DEPTH {
+            Err(SimulateError::UnsupportSwitchLevel(format!("recursive subckt '{}'", subckt_name)))?;
+        }
+        let subckt = self.subckts.get(subckt_name)
+            .ok_or_else(|| SimulateError::UnsupportSwitchLevel(format!("undefined subckt '{}'", subckt_name)))?;
+        if subckt.ports.len() != nets.len() {
+            Err(SimulateError::UnsupportSwitchLevel(format!(
+                "instance '{}' has {} nets but subckt '{}' has {} ports", path, nets.len(), subckt_name, subckt.ports.len()
+            )))?;
+        }
+
+        let mut local: HashMap<&str, usize> = subckt.ports.iter().map(|p| p.as_str()).zip(nets.iter().copied()).collect();
+        // wires make one net of all the names they connect, resolved before any element uses the net
+        for group in wire_groups(&subckt.elements) {
+            let mut ids = group.iter().filter_map(|name| local.get(name).copied());
+            let id = match ids.next() {
+                Some(id) if ids.any(|other| other != id) => Err(SimulateError::UnsupportSwitchLevel(format!(
+                    "resistors between connected nets '{}' of subckt '{}'", group.join("', '"), subckt_name
+                )))?,
+                Some(id) => id,
+                None => netlist.net(&format!("{}.{}", path, group[0])),
+            };
+            for name in group {
+                local.insert(name, id);
+            }
+        }
+        let mut net = |netlist: &mut Netlist, name: &'a str| -> usize {
+            *local.entry(name).or_insert_with(|| netlist.net(&format!("{}.{}", path, name)))
+        };
+
+        for element in subckt.elements.iter() {
+            match element {
+                Element::Mos { nets: [drain, gate, source], kind, resistance } => {
+                    let mos = Mos {
+                        kind: *kind,
+                        drain: net(netlist, drain),
+                        gate: net(netlist, gate),
+                        source: net(netlist, source),
+                        resistance: *resistance,
+                    };
+                    netlist.mos.push(mos);
+                }
+                Element::Instance { name, nets, subckt } => {
+                    let nets: Vec<usize> = nets.iter().map(|n| net(netlist, n)).collect();
+                    self.flatten(netlist, &format!("{}.{}", path, name), &nets, subckt, depth + 1)?;
+                }
+                Element::Wire { .. } => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Names connected by wires, grouped by union-find so the order of wires doesn't matter.
+/// Groups and names in a group are in order of first appearance
+fn wire_groups(elements: &[Element]) -> Vec<Vec<&str>> {
+    fn find(parents: &mut [usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+        index
+    }
+
+    let mut names: Vec<&str> = vec![];
+    let mut indices: HashMap<&str, usize> = HashMap::new();
+    let mut parents: Vec<usize> = vec![];
+    for element in elements.iter() {
+        let Element::Wire { nets } = element else { continue };
+        let [a, b] = nets.each_ref().map(|name| *indices.entry(name.as_str()).or_insert_with(|| {
+            names.push(name.as_str());
+            parents.push(parents.len());
+            parents.len() - 1
+        }));
+        let (a, b) = (find(&mut parents, a), find(&mut parents, b));
+        parents[a.max(b)] = a.min(b);
+    }
+
+    let mut groups: Vec<Vec<&str>> = vec![];
+    let mut group_indices: HashMap<usize, usize> = HashMap::new();
+    for (index, name) in names.into_iter().enumerate() {
+        let root = find(&mut parents, index);
+        let group = *group_indices.entry(root).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[group].push(name);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        let (net, _, stimulus) = Parser::parse_source(&["vclk", "clk", "0", "pulse(1.1v", "0v", "0s", "0.5ns", "0.5ns", "4.5ns", "10ns)"]).unwrap();
+        assert_eq!(net, "clk");
+        assert_eq!(stimulus.voltage_at_zero(), 1.1);
+        let crossings = stimulus.crossings(0.55, 20e-9);
+        let expect = [(0.25e-9, false), (5.25e-9, true), (10.25e-9, false), (15.25e-9, true)];
+        assert_eq!(crossings.len(), expect.len());
+        for ((time, value), (expect_time, expect_value)) in crossings.into_iter().zip(expect) {
+            assert!((time - expect_time).abs() < 1e-15);
+            assert_eq!(value, expect_value);
+        }
+
+        let (_, _, stimulus) = Parser::parse_source(&["vwe", "we", "0", "pwl", "(0s", "1.1v", "9.5ns", "1.1v", "10.5ns", "0v", ")"]).unwrap();
+        assert_eq!(stimulus.crossings(0.55, 20e-9), vec![(10e-9, false)]);
+
+        let (_, _, stimulus) = Parser::parse_source(&["vvdd", "vdd", "0", "1.1v"]).unwrap();
+        assert_eq!(stimulus, Stimulus::Dc(1.1));
+
+        let (_, neg, stimulus) = Parser::parse_source(&["vvdd_probe", "vdd_supply", "vdd", "0"]).unwrap();
+        assert_eq!((neg.as_str(), stimulus), ("vdd", Stimulus::Dc(0.0)));
+    }
+
+    #[test]
+    fn test_parse_mos() {
+        let Element::Mos { nets, kind, resistance } = Parser::parse_mos(&["m0", "z", "a", "vdd", "vdd", "pmos_vtl", "l=0.05um", "w=0.1um"]).unwrap() else {
+            panic!("not a transistor");
+        };
+        assert_eq!(nets, ["z".to_string(), "a".to_string(), "vdd".to_string()]);
+        assert_eq!(kind, MosKind::Pmos);
+        assert!((resistance - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_wire() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("wire.sp");
+        std::fs::write(&path, "\
+* wire
+.subckt inv a z vdd gnd
+m0 z a gnd gnd nmos_vtl l=0.05um w=0.1um
+.ends inv
+.subckt line a z vdd gnd
+ra_rc0 a a_rc0 1.0
+ca_rc0 a_rc0 0 1f
+ra_rc1 a_rc0 a_rc1 1.0
+xinv a_rc1 z vdd gnd inv
+.ends line
+xline a z vdd gnd line
+vvdd vdd 0 1.1v
+").unwrap();
+        let deck = Deck::load(&path).unwrap();
+        assert_eq!(deck.netlist.mos.len(), 1);
+        assert_eq!(deck.netlist.mos[0].gate, deck.netlist.ids["a"]);
+    }
+
+    #[test]
+    fn test_wire_order() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("wire.sp");
+        std::fs::write(&path, "\
+* wire
+.subckt line a z vdd gnd
+ra_rc1 a_rc1 a_rc0 1.0
+ra_rc0 a a_rc0 1.0
+rz_rc0 z_rc0 z 1.0
+m0 z_rc0 a_rc1 gnd gnd nmos_vtl l=0.05um w=0.1um
+.ends line
+xline a z vdd gnd line
+vvdd vdd 0 1.1v
+").unwrap();
+        let deck = Deck::load(&path).unwrap();
+        assert_eq!(deck.netlist.mos[0].gate, deck.netlist.ids["a"]);
+        assert_eq!(deck.netlist.mos[0].drain, deck.netlist.ids["z"]);
+
+        std::fs::write(&path, "\
+* short
+.subckt line a z vdd gnd
+ra_rc0 a mid 1.0
+rz_rc0 mid z 1.0
+.ends line
+xline a z vdd gnd line
+").unwrap();
+        assert!(Deck::load(&path).is_err());
+    }
+}
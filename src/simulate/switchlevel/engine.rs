@@ -0,0 +1,342 @@
+use std::{cmp::Ordering, collections::{BinaryHeap, HashMap, HashSet}};
+use super::{MosKind, Netlist};
+
+/// Resistance of the charge stored on a node with a single terminal,
+/// much weaker than any transistor path so a driven node always wins
+const CHARGE_RESISTANCE: f64 = 1e4;
+
+/// Delay of a unit resistance charging a node with a single terminal
+const UNIT_DELAY: f64 = 1e-12;
+
+/// Max events between two stimuli before the circuit is taken as oscillating
+const MAX_EVENTS: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Logic {
+    Low,
+    High,
+    X,
+}
+
+impl From<bool> for Logic {
+    fn from(value: bool) -> Self {
+        if value { Self::High } else { Self::Low }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conduct {
+    On,
+    Off,
+    X,
+}
+
+/// Switch-level evaluation of a flatten netlist
+///
+/// Transistors are switches with a resistance, nodes keep their charge when
+/// nothing drives them and the bigger node wins a charge sharing. A group of
+/// nodes connected by conducting transistors is evaluated by the strongest (least
+/// resistance) path to a logic value, and a change is scheduled after the RC delay
+/// of that path (the node size, the number of terminals on it, stands for its capacitance).
+/// So races like a latch resolving or a write driver turned off while the
+/// bitline moves go the way the faster side goes.
+pub struct Engine<'a> {
+    netlist: &'a Netlist,
+    time: f64,
+    values: Vec<Logic>,
+    inputs: Vec<Option<Logic>>,
+    size: Vec<f64>,
+    /// Transistors gated by each node
+    gates: Vec<Vec<usize>>,
+    /// Transistors whose source or drain is each node
+    channels: Vec<Vec<usize>>,
+    /// Nodes whose group must be evaluated at current time
+    pending: Vec<usize>,
+    events: BinaryHeap<Event>,
+    /// Id of the live event of each node, older ones in `events` are cancelled
+    scheduled: Vec<Option<(Logic, u64)>>,
+    next_id: u64,
+}
+
+#[derive(Debug, PartialEq)]
+struct Event {
+    time: f64,
+    id: u64,
+    node: usize,
+    value: Logic,
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Earliest first in a `BinaryHeap`
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.time.total_cmp(&self.time).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+/// Min-heap entry of dijkstra
+#[derive(PartialEq)]
+struct Dist(f64, usize);
+
+impl Eq for Dist {}
+
+impl PartialOrd for Dist {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Dist {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0).then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+/// Strongest (least resistance) path from a logic value: total resistance, and the
+/// part of it that is stored charge(not counted in delay)
+#[derive(Debug, Clone, Copy)]
+struct Path {
+    resistance: f64,
+    charge: f64,
+}
+
+impl Path {
+    const NONE: Self = Self { resistance: f64::INFINITY, charge: 0.0 };
+}
+
+impl<'a> Engine<'a> {
+    pub fn new(netlist: &'a Netlist) -> Self {
+        let size = netlist.net_size();
+        let mut gates = vec![vec![]; size];
+        let mut channels = vec![vec![]; size];
+        let mut terminals = vec![0usize; size];
+        for (index, mos) in netlist.mos.iter().enumerate() {
+            gates[mos.gate].push(index);
+            channels[mos.source].push(index);
+            channels[mos.drain].push(index);
+            terminals[mos.gate] += 1;
+            terminals[mos.source] += 1;
+            terminals[mos.drain] += 1;
+        }
+
+        Self {
+            netlist,
+            time: 0.0,
+            values: vec![Logic::X; size],
+            inputs: vec![None; size],
+            size: terminals.into_iter().map(|n| n.max(1) as f64).collect(),
+            gates,
+            channels,
+            pending: (0..size).collect(),
+            events: BinaryHeap::new(),
+            scheduled: vec![None; size],
+            next_id: 0,
+        }
+    }
+
+    pub fn value(&self, node: usize) -> Logic {
+        self.values[node]
+    }
+
+    /// Drive `node` by a source from current time
+    pub fn set_input(&mut self, node: usize, value: Logic) {
+        if self.inputs[node] == Some(value) {
+            return;
+        }
+        self.inputs[node] = Some(value);
+        self.scheduled[node] = None;
+        self.set(node, value);
+        // an input is a boundary of groups, wake up the ones it drives
+        for &index in self.channels[node].iter() {
+            self.pending.push(self.other_terminal(index, node));
+        }
+    }
+
+    /// Process events until `time`, return false if the circuit keeps changing(oscillates)
+    pub fn run_until(&mut self, time: f64) -> bool {
+        for _ in 0..MAX_EVENTS {
+            self.evaluate_pending();
+
+            let Some(event) = self.events.peek() else { break };
+            if event.time > time {
+                break;
+            }
+            let event = self.events.pop().unwrap();
+            if self.scheduled[event.node] != Some((event.value, event.id)) {
+                continue;
+            }
+
+            self.time = event.time;
+            self.scheduled[event.node] = None;
+            self.set(event.node, event.value);
+        }
+
+        self.time = self.time.max(time);
+        self.pending.is_empty() && self.events.peek().is_none_or(|e| e.time > time)
+    }
+
+    /// Set value of `node`, its group and the groups of switches it gates go to pending
+    fn set(&mut self, node: usize, value: Logic) {
+        if self.values[node] == value {
+            return;
+        }
+        self.values[node] = value;
+        self.pending.push(node);
+        for &index in self.gates[node].iter() {
+            let mos = &self.netlist.mos[index];
+            self.pending.push(mos.source);
+            self.pending.push(mos.drain);
+        }
+    }
+
+    /// Evaluate groups of pending nodes, schedule or cancel their changes
+    fn evaluate_pending(&mut self) {
+        let mut evaluated: HashSet<usize> = HashSet::new();
+        while let Some(seed) = self.pending.pop() {
+            if self.inputs[seed].is_some() || evaluated.contains(&seed) {
+                continue;
+            }
+            let group = self.group(seed);
+            for (node, target, resistance) in self.targets(&group) {
+                evaluated.insert(node);
+                self.schedule(node, target, resistance);
+            }
+        }
+    }
+
+    fn schedule(&mut self, node: usize, target: Logic, resistance: f64) {
+        if target == self.values[node] {
+            self.scheduled[node] = None;
+            return;
+        }
+        // the same change is on its way
+        if let Some((value, _)) = self.scheduled[node] && value == target {
+            return;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let time = self.time + resistance * self.size[node] * UNIT_DELAY;
+        self.scheduled[node] = Some((target, id));
+        self.events.push(Event { time, id, node, value: target });
+    }
+
+    fn conduct(&self, index: usize) -> Conduct {
+        let mos = &self.netlist.mos[index];
+        match (mos.kind, self.values[mos.gate]) {
+            (_, Logic::X) => Conduct::X,
+            (MosKind::Nmos, Logic::High) | (MosKind::Pmos, Logic::Low) => Conduct::On,
+            _ => Conduct::Off,
+        }
+    }
+
+    fn other_terminal(&self, index: usize, node: usize) -> usize {
+        let mos = &self.netlist.mos[index];
+        if mos.source == node { mos.drain } else { mos.source }
+    }
+
+    /// Non-input nodes connected to `seed` by conducting(or maybe conducting) transistors
+    fn group(&self, seed: usize) -> Vec<usize> {
+        let mut visited: HashSet<usize> = HashSet::new();
+        let mut stack = vec![seed];
+        let mut group = vec![];
+        while let Some(node) = stack.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            group.push(node);
+            for &index in self.channels[node].iter() {
+                if self.conduct(index) == Conduct::Off {
+                    continue;
+                }
+                let other = self.other_terminal(index, node);
+                if self.inputs[other].is_none() && !visited.contains(&other) {
+                    stack.push(other);
+                }
+            }
+        }
+        group
+    }
+
+    /// Value each node of `group` goes to, with the resistance of the path deciding it
+    fn targets(&self, group: &[usize]) -> Vec<(usize, Logic, f64)> {
+        let local: HashMap<usize, usize> = group.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+        let definite_high = self.paths(group, &local, Logic::High, false);
+        let maybe_high = self.paths(group, &local, Logic::High, true);
+        let definite_low = self.paths(group, &local, Logic::Low, false);
+        let maybe_low = self.paths(group, &local, Logic::Low, true);
+
+        let stronger = |a: Path, b: Path| a.resistance * (1.0 + 1e-9) < b.resistance;
+        let delay = |p: Path| (p.resistance - p.charge).max(f64::MIN_POSITIVE);
+        group.iter().enumerate()
+            .map(|(i, &node)| {
+                if stronger(definite_high[i], maybe_low[i]) {
+                    (node, Logic::High, delay(definite_high[i]))
+                } else if stronger(definite_low[i], maybe_high[i]) {
+                    (node, Logic::Low, delay(definite_low[i]))
+                } else {
+                    (node, Logic::X, delay(maybe_low[i]).min(delay(maybe_high[i])))
+                }
+            })
+            .collect()
+    }
+
+    /// Strongest (least resistance) path from `value` to each node of `group`,
+    /// `maybe` counts X as `value` and transistors with X gate as conducting
+    fn paths(&self, group: &[usize], local: &HashMap<usize, usize>, value: Logic, maybe: bool) -> Vec<Path> {
+        let matches = |v: Logic| v == value || (maybe && v == Logic::X);
+        let passes = |c: Conduct| c == Conduct::On || (maybe && c == Conduct::X);
+
+        let mut paths = vec![Path::NONE; group.len()];
+        let mut heap = BinaryHeap::new();
+        for (i, &node) in group.iter().enumerate() {
+            // own charge
+            if matches(self.values[node]) {
+                let charge = CHARGE_RESISTANCE / self.size[node];
+                paths[i] = Path { resistance: charge, charge };
+            }
+            // driven from an input through one transistor
+            for &index in self.channels[node].iter() {
+                let other = self.other_terminal(index, node);
+                let resistance = self.netlist.mos[index].resistance;
+                if let Some(input) = self.inputs[other]
+                    && matches(input)
+                    && passes(self.conduct(index))
+                    && resistance < paths[i].resistance
+                {
+                    paths[i] = Path { resistance, charge: 0.0 };
+                }
+            }
+            if paths[i].resistance.is_finite() {
+                heap.push(Dist(paths[i].resistance, i));
+            }
+        }
+
+        while let Some(Dist(resistance, i)) = heap.pop() {
+            if resistance > paths[i].resistance {
+                continue;
+            }
+            let node = group[i];
+            for &index in self.channels[node].iter() {
+                if !passes(self.conduct(index)) {
+                    continue;
+                }
+                let Some(&j) = local.get(&self.other_terminal(index, node)) else { continue };
+                let next = resistance + self.netlist.mos[index].resistance;
+                if next < paths[j].resistance {
+                    paths[j] = Path { resistance: next, charge: paths[i].charge };
+                    heap.push(Dist(next, j));
+                }
+            }
+        }
+
+        paths
+    }
+}
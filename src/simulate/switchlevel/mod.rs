@@ -0,0 +1,204 @@
+mod deck;
+mod engine;
+pub use deck::*;
+pub use engine::*;
+
+use std::{io::Write, path::{Path, PathBuf}, time::Duration};
+use tracing::debug;
+use crate::{ErrorContext, YouRAMResult};
+use super::{SimulateError, SpiceCommand};
+
+/// Built-in switch-level simulator, check the logic of a deck without a SPICE simulator
+///
+/// Reads the deck written by `CircuitSimulator`, flattens its instances down to
+/// transistors and evaluates them as switches with RC delays, a source switches
+/// when it crosses half of the highest supply. Only `FIND v(<net>) AT=<time>` meas
+/// are supported, the result is the supply voltage for logic 1, 0 for logic 0 and
+/// half supply for X, so it fits function charz but not timing charz.
+///
+/// # Example
+///
+/// ```ignore
+/// let pass = FunctionCharz::config()
+///     .sram(sram)
+///     .command(SwitchLevel)
+///     .....
+///     .test()?;
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwitchLevel;
+
+impl SwitchLevel {
+    /// Simulate the deck at `sim_filepath`, return value of each meas in deck order
+    pub fn run(&self, sim_filepath: &Path) -> YouRAMResult<Vec<(String, f64)>> {
+        let deck = Deck::load(sim_filepath)?;
+
+        let vdd = deck.sources.iter().map(|(_, w)| w.max_voltage()).fold(0.0, f64::max);
+        if vdd <= 0.0 {
+            Err(SimulateError::UnsupportSwitchLevel("deck without a positive supply".into()))?;
+        }
+        let threshold = vdd / 2.0;
+        let end = deck.measures.iter().map(|m| m.time).fold(0.0, f64::max);
+
+        let mut events: Vec<(f64, usize, Logic)> = deck.sources.iter()
+            .flat_map(|(net, stimulus)| stimulus.crossings(threshold, end).into_iter().map(|(t, v)| (t, *net, v.into())))
+            .collect();
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut engine = Engine::new(&deck.netlist);
+        for (net, stimulus) in deck.sources.iter() {
+            engine.set_input(*net, (stimulus.voltage_at_zero() > threshold).into());
+        }
+
+        let mut measures: Vec<_> = deck.measures.iter().enumerate().collect();
+        measures.sort_by(|a, b| a.1.time.total_cmp(&b.1.time));
+
+        let mut results = vec![0.0; deck.measures.len()];
+        let mut events = events.into_iter().peekable();
+        for (index, measure) in measures {
+            while let Some(&(time, net, value)) = events.peek() && time <= measure.time {
+                Self::run_until(&mut engine, time)?;
+                engine.set_input(net, value);
+                events.next();
+            }
+            Self::run_until(&mut engine, measure.time)?;
+
+            results[index] = match engine.value(measure.net) {
+                Logic::High => vdd,
+                Logic::Low => 0.0,
+                Logic::X => threshold,
+            };
+        }
+
+        Ok(deck.measures.iter().map(|m| m.name.clone()).zip(results).collect())
+    }
+
+    fn run_until(engine: &mut Engine, time: f64) -> YouRAMResult<()> {
+        if !engine.run_until(time) {
+            Err(SimulateError::NotSettled(time))?;
+        }
+        Ok(())
+    }
+}
+
+impl SpiceCommand for SwitchLevel {
+    fn simulate_command(&self, sim_filepath: &Path, _temp_folder: &Path) -> YouRAMResult<String> {
+        Ok(format!("switch-level {}", sim_filepath.display()))
+    }
+
+    /// Simulate in process, write results as `<name> = <value>` like ngspice
+    fn execute(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        debug!("switch-level simulate '{}'", sim_filepath.display());
+        let results = self.run(sim_filepath).with_context(|| format!("switch-level simulate '{}'", sim_filepath.display()))?;
+
+        let result_path = self.meas_result_filepath(sim_filepath, temp_folder)?;
+        let mut file = std::fs::File::create(&result_path)?;
+        for (name, value) in results {
+            writeln!(file, "{} = {:e}", name, value)?;
+        }
+        Ok(result_path)
+    }
+
+    /// Runs in process and always settles or stops by itself, no timeout needed
+    fn execute_timeout(&self, sim_filepath: &Path, temp_folder: &Path, _timeout: Option<Duration>) -> YouRAMResult<PathBuf> {
+        self.execute(sim_filepath, temp_folder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    const CELLS: &str = "\
+.SUBCKT inv a z vdd gnd
+MN z a gnd gnd nmos_vtl L=50nm W=200nm
+MP z a vdd vdd pmos_vtl L=50nm W=300nm
+.ENDS inv
+
+.SUBCKT bitcell bl br wl vdd gnd
+MM0 q_bar q gnd gnd NMOS_VTL L=50nm W=205nm
+MM4 q_bar q vdd vdd PMOS_VTL L=50nm W=90nm
+MM1 q q_bar gnd gnd NMOS_VTL L=50nm W=205nm
+MM5 q q_bar vdd vdd PMOS_VTL L=50nm W=90nm
+MM3 bl wl q gnd NMOS_VTL L=50nm W=135nm
+MM2 br wl q_bar gnd NMOS_VTL L=50nm W=135nm
+.ENDS bitcell
+
+* precharge while `pre_b` is low, pull bl/br low while `wbl`/`wbr` is high
+.SUBCKT column bl br wl pre_b wbl wbr vdd gnd
+Xcell bl br wl vdd gnd bitcell
+MP1 bl pre_b vdd vdd pmos_vtl L=50nm W=270nm
+MP2 br pre_b vdd vdd pmos_vtl L=50nm W=270nm
+MN1 bl wbl gnd gnd nmos_vtl L=50nm W=540nm
+MN2 br wbr gnd gnd nmos_vtl L=50nm W=540nm
+.ENDS column
+";
+
+    fn simulate(deck: &str) -> YouRAMResult<Vec<(String, f64)>> {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("cells.sp"), CELLS).unwrap();
+        let sim_path = temp.path().join("test.sp");
+        let deck = format!("* title\n.include {}\n{}", temp.path().join("cells.sp").display(), deck);
+        std::fs::write(&sim_path, deck).unwrap();
+        SwitchLevel.run(&sim_path)
+    }
+
+    #[test]
+    fn test_inverter_chain() {
+        let results = simulate("\
+VVDD vdd_supply 0 1.1V
+VVDD_PROBE vdd_supply vdd 0
+VVSS vss 0 0V
+Xinv0 in mid vdd vss inv
+Xinv1 mid
++ out vdd vss inv
+Vin in 0 PWL (0s 0V 1ns 0V 1.1ns 1.1V)
+.meas tran out_0 FIND v(out) AT=0.5ns
+.meas tran mid_0 FIND v(mid) AT=0.5ns
+.meas tran out_1 FIND v(out) AT=2ns
+.TRAN 10ps 3ns 0
+.end
+").unwrap();
+
+        assert_eq!(results, vec![("out_0".into(), 0.0), ("mid_0".into(), 1.1), ("out_1".into(), 1.1)]);
+    }
+
+    #[test]
+    fn test_bitcell_write_read() {
+        // write 0, read, write 1, read, one operation each 10ns
+        let results = simulate("\
+VVDD vdd 0 1.1V
+VVSS vss 0 0V
+Xcol bl br wl pre_b wbl wbr vdd vss column
+Vwl wl 0 PWL (0s 0V 2ns 0V 2.1ns 1.1V 8ns 1.1V 8.1ns 0V 12ns 0V 12.1ns 1.1V 18ns 1.1V 18.1ns 0V 22ns 0V 22.1ns 1.1V 28ns 1.1V 28.1ns 0V 32ns 0V 32.1ns 1.1V 38ns 1.1V 38.1ns 0V)
+Vpre_b pre_b 0 PULSE(0V 1.1V 1ns 0.1ns 0.1ns 8ns 10ns)
+Vwbl wbl 0 PWL (0s 0V 2ns 0V 2.1ns 1.1V 8ns 1.1V 8.1ns 0V)
+Vwbr wbr 0 PWL (0s 0V 22ns 0V 22.1ns 1.1V 28ns 1.1V 28.1ns 0V)
+.meas tran read0_bl FIND v(bl) AT=17ns
+.meas tran read0_br FIND v(br) AT=17ns
+.meas tran read1_bl FIND v(bl) AT=37ns
+.meas tran read1_br FIND v(br) AT=37ns
+.end
+").unwrap();
+
+        let results: HashMap<String, f64> = results.into_iter().collect();
+        assert_eq!(results["read0_bl"], 0.0);
+        assert_eq!(results["read0_br"], 1.1);
+        assert_eq!(results["read1_bl"], 1.1);
+        assert_eq!(results["read1_br"], 0.0);
+    }
+
+    #[test]
+    fn test_unsupported_meas() {
+        let result = simulate("\
+VVDD vdd 0 1.1V
+VVSS vss 0 0V
+Xinv0 in out vdd vss inv
+Vin in 0 PWL (0s 0V 1ns 0V 1.1ns 1.1V)
+.meas tran delay TRIG v(in) VAL=0.55 RISE=1 TD=0 TARG v(out) VAL=0.55 FALL=1 TD=0
+.end
+");
+        assert!(result.is_err());
+    }
+}
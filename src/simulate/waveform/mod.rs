@@ -0,0 +1,235 @@
+mod rawfile;
+pub use rawfile::*;
+
+use reda_unit::{Number, Time};
+use super::Edge;
+
+/// Sampled signal of a transient simulation, voltage or current over time
+///
+/// Values between samples are linear interpolated, like spice `.meas` does.
+///
+/// # Example
+///
+/// ```ignore
+/// let raw = simulator.simulate_waveforms(&NgSpice, temp_folder)?.1;
+/// let clk = raw.waveform("clk").unwrap();
+/// let dout = raw.waveform("dout0").unwrap();
+/// let delay = clk.delay(0.55, Edge::Rise, t!(10 n), &dout, 0.55, Edge::Fall);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waveform {
+    pub name: String,
+    pub times: Vec<f64>,
+    pub values: Vec<f64>,
+}
+
+impl Waveform {
+    pub fn new(name: impl Into<String>, times: Vec<f64>, values: Vec<f64>) -> Self {
+        Self { name: name.into(), times, values }
+    }
+
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    pub fn start_time(&self) -> Option<Time> {
+        self.times.first().map(|&t| t.into())
+    }
+
+    pub fn end_time(&self) -> Option<Time> {
+        self.times.last().map(|&t| t.into())
+    }
+
+    /// Value at `time`, none out of the simulated range
+    pub fn value_at(&self, time: impl Into<Time>) -> Option<Number> {
+        self.interpolate(time.into().to_f64()).map(Number::from_f64)
+    }
+
+    /// All times the signal crosses `threshold` in direction `edge`
+    pub fn crossings(&self, threshold: impl Into<Number>, edge: Edge) -> Vec<Time> {
+        let threshold = threshold.into().to_f64();
+        self.segments()
+            .filter_map(|((t0, v0), (t1, v1))| Self::cross_segment(t0, v0, t1, v1, threshold, edge))
+            .map(Time::from)
+            .collect()
+    }
+
+    /// First crossing of `threshold` in direction `edge` after `after`
+    pub fn cross(&self, threshold: impl Into<Number>, edge: Edge, after: impl Into<Time>) -> Option<Time> {
+        let threshold = threshold.into().to_f64();
+        let after = after.into().to_f64();
+        self.segments()
+            .filter(|((_, _), (t1, _))| *t1 >= after)
+            .filter_map(|((t0, v0), (t1, v1))| Self::cross_segment(t0, v0, t1, v1, threshold, edge))
+            .find(|&t| t >= after)
+            .map(Time::from)
+    }
+
+    /// Time from the first `trig_edge` crossing of this signal after `after`,
+    /// to the next `targ_edge` crossing of `target`, like `.meas TRIG ... TARG ...`
+    pub fn delay(
+        &self,
+        trig_threshold: impl Into<Number>,
+        trig_edge: Edge,
+        after: impl Into<Time>,
+        target: &Waveform,
+        targ_threshold: impl Into<Number>,
+        targ_edge: Edge,
+    ) -> Option<Time> {
+        let trig = self.cross(trig_threshold, trig_edge, after)?;
+        let targ = target.cross(targ_threshold, targ_edge, trig)?;
+        Some(targ - trig)
+    }
+
+    /// Transition time of the first `edge` after `after` between `low` and `high`,
+    /// usually 10%/90% or 20%/80% of supply
+    pub fn slew(&self, low: impl Into<Number>, high: impl Into<Number>, edge: Edge, after: impl Into<Time>) -> Option<Time> {
+        let (low, high) = (low.into(), high.into());
+        let after = after.into();
+        match edge {
+            Edge::Rise => {
+                let start = self.cross(low, Edge::Rise, after)?;
+                let end = self.cross(high, Edge::Rise, start)?;
+                Some(end - start)
+            }
+            Edge::Fall => {
+                let start = self.cross(high, Edge::Fall, after)?;
+                let end = self.cross(low, Edge::Fall, start)?;
+                Some(end - start)
+            }
+        }
+    }
+
+    /// Min value in `[from, to]`
+    pub fn min(&self, from: impl Into<Time>, to: impl Into<Time>) -> Option<Number> {
+        self.window(from.into().to_f64(), to.into().to_f64())
+            .map(|(_, v)| v)
+            .reduce(f64::min)
+            .map(Number::from_f64)
+    }
+
+    /// Max value in `[from, to]`
+    pub fn max(&self, from: impl Into<Time>, to: impl Into<Time>) -> Option<Number> {
+        self.window(from.into().to_f64(), to.into().to_f64())
+            .map(|(_, v)| v)
+            .reduce(f64::max)
+            .map(Number::from_f64)
+    }
+
+    /// Trapezoidal integral over `[from, to]`, like charge from a current
+    pub fn integral(&self, from: impl Into<Time>, to: impl Into<Time>) -> Option<Number> {
+        let samples: Vec<(f64, f64)> = self.window(from.into().to_f64(), to.into().to_f64()).collect();
+        if samples.is_empty() {
+            return None;
+        }
+        let area = samples.windows(2)
+            .map(|w| (w[1].0 - w[0].0) * (w[0].1 + w[1].1) / 2.0)
+            .sum::<f64>();
+        Some(Number::from_f64(area))
+    }
+
+    /// Time average over `[from, to]`
+    pub fn average(&self, from: impl Into<Time>, to: impl Into<Time>) -> Option<Number> {
+        let (from, to) = (from.into(), to.into());
+        let span = (to - from).to_f64();
+        if span <= 0.0 {
+            return self.value_at(from);
+        }
+        self.integral(from, to).map(|area| Number::from_f64(area.to_f64() / span))
+    }
+
+    fn interpolate(&self, time: f64) -> Option<f64> {
+        let (&first, &last) = (self.times.first()?, self.times.last()?);
+        if time < first || time > last {
+            return None;
+        }
+        // first sample not before `time`
+        let index = self.times.partition_point(|&t| t < time);
+        if self.times[index] == time || index == 0 {
+            return Some(self.values[index]);
+        }
+        let (t0, v0) = (self.times[index - 1], self.values[index - 1]);
+        let (t1, v1) = (self.times[index], self.values[index]);
+        Some(v0 + (v1 - v0) * (time - t0) / (t1 - t0))
+    }
+
+    fn segments(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+        self.times.windows(2)
+            .zip(self.values.windows(2))
+            .map(|(t, v)| ((t[0], v[0]), (t[1], v[1])))
+    }
+
+    /// Samples in `[from, to]` with interpolated end points
+    fn window(&self, from: f64, to: f64) -> impl Iterator<Item = (f64, f64)> + '_ {
+        let from = self.times.first().map(|&t| from.max(t)).unwrap_or(from);
+        let to = self.times.last().map(|&t| to.min(t)).unwrap_or(to);
+        let inner = self.times.iter().copied()
+            .zip(self.values.iter().copied())
+            .filter(move |&(t, _)| t > from && t < to);
+        let start = (from <= to).then(|| self.interpolate(from).map(|v| (from, v))).flatten();
+        let end = (from < to).then(|| self.interpolate(to).map(|v| (to, v))).flatten();
+        start.into_iter().chain(inner).chain(end)
+    }
+
+    fn cross_segment(t0: f64, v0: f64, t1: f64, v1: f64, threshold: f64, edge: Edge) -> Option<f64> {
+        let crossed = match edge {
+            Edge::Rise => v0 < threshold && v1 >= threshold,
+            Edge::Fall => v0 > threshold && v1 <= threshold,
+        };
+        crossed.then(|| t0 + (threshold - v0) / (v1 - v0) * (t1 - t0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reda_unit::t;
+    use super::*;
+
+    fn ramp() -> Waveform {
+        // 0 -> 1 in 1ns, hold 1ns, 1 -> 0 in 2ns
+        Waveform::new("v(out)", vec![0.0, 1e-9, 2e-9, 4e-9], vec![0.0, 1.0, 1.0, 0.0])
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-15
+    }
+
+    #[test]
+    fn test_value_and_crossing() {
+        let wave = ramp();
+        assert!(close(wave.value_at(t!(0.5 n)).unwrap().to_f64(), 0.5));
+        assert!(close(wave.value_at(t!(3 n)).unwrap().to_f64(), 0.5));
+        assert!(wave.value_at(t!(5 n)).is_none());
+
+        let rises = wave.crossings(0.5, Edge::Rise);
+        assert_eq!(rises.len(), 1);
+        assert!(close(rises[0].to_f64(), 0.5e-9));
+        assert!(close(wave.cross(0.5, Edge::Fall, t!(0)).unwrap().to_f64(), 3e-9));
+        assert!(wave.cross(0.5, Edge::Rise, t!(1 n)).is_none());
+
+        assert!(close(wave.slew(0.1, 0.9, Edge::Rise, t!(0)).unwrap().to_f64(), 0.8e-9));
+        assert!(close(wave.slew(0.1, 0.9, Edge::Fall, t!(0)).unwrap().to_f64(), 1.6e-9));
+    }
+
+    #[test]
+    fn test_delay() {
+        let input = Waveform::new("v(in)", vec![0.0, 1e-9, 1.2e-9], vec![0.0, 0.0, 1.0]);
+        let delay = input.delay(0.5, Edge::Rise, t!(0), &ramp(), 0.5, Edge::Fall).unwrap();
+        assert!(close(delay.to_f64(), 3e-9 - 1.1e-9));
+    }
+
+    #[test]
+    fn test_statistics() {
+        let wave = ramp();
+        assert_eq!(wave.max(t!(0), t!(4 n)).unwrap().to_f64(), 1.0);
+        assert!(close(wave.min(t!(0.5 n), t!(3 n)).unwrap().to_f64(), 0.5));
+        // 0.5 + 1 + 1
+        assert!(close(wave.integral(t!(0), t!(4 n)).unwrap().to_f64(), 2.5e-9));
+        assert!(close(wave.average(t!(1 n), t!(2 n)).unwrap().to_f64(), 1.0));
+        assert!(close(wave.integral(t!(0.5 n), t!(1 n)).unwrap().to_f64(), 0.375e-9));
+    }
+}
@@ -0,0 +1,259 @@
+use std::path::Path;
+use crate::{ErrorContext, YouRAMResult};
+use super::{super::SimulateError, Waveform};
+
+/// Waveforms saved by `ngspice -r <rawfile>`, in ascii or binary format
+///
+/// A rawfile holds one plot per analysis, each plot has a sweep variable
+/// (`time` for transient) and the saved node voltages and branch currents.
+#[derive(Debug, Clone, Default)]
+pub struct RawFile {
+    pub plots: Vec<Plot>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Plot {
+    pub title: String,
+    /// Analysis name, like `Transient Analysis`
+    pub name: String,
+    pub variables: Vec<Variable>,
+    /// Values of each variable, in the order of `variables`
+    pub values: Vec<Vec<f64>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    /// `time`, `voltage` or `current`
+    pub kind: String,
+}
+
+impl RawFile {
+    pub fn load<P: AsRef<Path>>(path: P) -> YouRAMResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read(path).context(format!("read rawfile '{}'", path.display()))?;
+        let rawfile = Self::parse(&content)
+            .map_err(|msg| SimulateError::InvalidRawfile(path.to_path_buf(), msg))?;
+        Ok(rawfile)
+    }
+
+    /// Parse content of a rawfile, error message on a malformed one
+    pub fn parse(content: &[u8]) -> Result<Self, String> {
+        let mut plots = vec![];
+        let mut rest = content;
+        while !rest.iter().all(u8::is_ascii_whitespace) {
+            let (plot, remain) = Plot::parse(rest)?;
+            plots.push(plot);
+            rest = remain;
+        }
+        Ok(Self { plots })
+    }
+
+    /// First plot whose name contains `name`, case insensitive
+    pub fn plot(&self, name: &str) -> Option<&Plot> {
+        let name = name.to_ascii_lowercase();
+        self.plots.iter().find(|p| p.name.to_ascii_lowercase().contains(&name))
+    }
+
+    pub fn transient(&self) -> Option<&Plot> {
+        self.plot("transient")
+    }
+
+    /// Plot of `.DC`, named `DC transfer characteristic` by ngspice. Waveforms of it
+    /// hold the sweep values in `times`
+    pub fn dc(&self) -> Option<&Plot> {
+        self.plot("dc transfer")
+    }
+
+    /// Waveform `name` of the transient plot
+    pub fn waveform(&self, name: &str) -> Option<Waveform> {
+        self.transient()?.waveform(name)
+    }
+}
+
+impl Plot {
+    /// Waveform of variable `name` against the sweep variable, `out`, `v(out)` and
+    /// `V(OUT)` are the same net, and a source current is `i(vdd)` or `vdd#branch`
+    pub fn waveform(&self, name: &str) -> Option<Waveform> {
+        let target = normalize_name(name);
+        let index = self.variables.iter().position(|v| normalize_name(&v.name) == target)?;
+        Some(Waveform::new(name, self.values.first()?.clone(), self.values[index].clone()))
+    }
+
+    pub fn sweep(&self) -> Option<&[f64]> {
+        self.values.first().map(Vec::as_slice)
+    }
+
+    /// Parse one plot, return it and the content after it
+    fn parse(content: &[u8]) -> Result<(Self, &[u8]), String> {
+        let mut title = String::new();
+        let mut name = String::new();
+        let mut binary = false;
+        let mut nvars = None;
+        let mut npoints = None;
+        let mut variables = vec![];
+        let mut rest = content;
+
+        loop {
+            let line = next_line(&mut rest).ok_or("unexpected end of header")?;
+            let Some((key, value)) = line.split_once(':') else {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Err(format!("invalid header line '{}'", line));
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "title" => title = value.to_string(),
+                "plotname" => name = value.to_string(),
+                "flags" => if value.to_ascii_lowercase().contains("complex") {
+                    return Err(format!("unsupport complex plot '{}'", name));
+                }
+                "no. variables" => nvars = Some(parse_count(value)?),
+                "no. points" => npoints = Some(parse_count(value)?),
+                "variables" => {
+                    let nvars = nvars.ok_or("'Variables' before 'No. Variables'")?;
+                    for _ in 0..nvars {
+                        let line = next_line(&mut rest).ok_or("unexpected end of variables")?;
+                        let mut tokens = line.split_whitespace().skip(1);
+                        match (tokens.next(), tokens.next()) {
+                            (Some(name), Some(kind)) => variables.push(Variable { name: name.into(), kind: kind.into() }),
+                            _ => return Err(format!("invalid variable line '{}'", line)),
+                        }
+                    }
+                }
+                "values" => break,
+                "binary" => { binary = true; break }
+                _ => {}
+            }
+        }
+
+        let nvars = nvars.ok_or("missing 'No. Variables'")?;
+        let npoints = npoints.ok_or("missing 'No. Points'")?;
+        if variables.len() != nvars {
+            return Err(format!("expect {} variables, found {}", nvars, variables.len()));
+        }
+
+        let mut values = vec![Vec::with_capacity(npoints); nvars];
+        if binary {
+            let size = nvars * npoints * 8;
+            if rest.len() < size {
+                return Err(format!("expect {} bytes of values, found {}", size, rest.len()));
+            }
+            for (i, bytes) in rest[..size].chunks_exact(8).enumerate() {
+                values[i % nvars].push(f64::from_le_bytes(bytes.try_into().unwrap()));
+            }
+            rest = &rest[size..];
+        } else {
+            // "<index> <value0>" and one value each following line
+            for point in 0..npoints {
+                for (var, column) in values.iter_mut().enumerate() {
+                    let line = loop {
+                        let line = next_line(&mut rest).ok_or("unexpected end of values")?;
+                        if !line.trim().is_empty() {
+                            break line;
+                        }
+                    };
+                    let token = if var == 0 { line.split_whitespace().nth(1) } else { line.split_whitespace().next() };
+                    let value = token
+                        .and_then(|t| t.split(',').next())
+                        .and_then(|t| t.parse::<f64>().ok())
+                        .ok_or_else(|| format!("invalid value line '{}' of point {}", line, point))?;
+                    column.push(value);
+                }
+            }
+        }
+
+        Ok((Self { title, name, variables, values }, rest))
+    }
+}
+
+/// Next `\n` ended line of `rest`, header is ascii even in binary rawfile
+fn next_line<'a>(rest: &mut &'a [u8]) -> Option<&'a str> {
+    if rest.is_empty() {
+        return None;
+    }
+    let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+    let line = std::str::from_utf8(&rest[..end]).ok()?;
+    *rest = &rest[(end + 1).min(rest.len())..];
+    Some(line.trim_end_matches('\r'))
+}
+
+fn parse_count(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("invalid count '{}'", value))
+}
+
+/// `V(Out)` -> `out`, `i(vdd)` -> `vdd#branch`
+fn normalize_name(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    if let Some(inner) = name.strip_prefix("v(").and_then(|n| n.strip_suffix(')')) {
+        return inner.to_string();
+    }
+    if let Some(inner) = name.strip_prefix("i(").and_then(|n| n.strip_suffix(')')) {
+        return format!("{}#branch", inner);
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "\
+Title: * inverter
+Date: Thu Jan  1 00:00:00  2026
+Plotname: Transient Analysis
+Flags: real
+No. Variables: 3
+No. Points: 3
+Variables:
+\t0\ttime\ttime
+\t1\tv(out)\tvoltage
+\t2\tvdd#branch\tcurrent
+";
+
+    const POINTS: [[f64; 3]; 3] = [
+        [0.0, 0.0, -1e-6],
+        [1e-9, 0.55, -2e-4],
+        [2e-9, 1.1, -1e-6],
+    ];
+
+    fn check(rawfile: &RawFile) {
+        assert_eq!(rawfile.plots.len(), 1);
+        let out = rawfile.waveform("out").unwrap();
+        assert_eq!(out.times, vec![0.0, 1e-9, 2e-9]);
+        assert_eq!(out.values, vec![0.0, 0.55, 1.1]);
+        assert_eq!(rawfile.waveform("V(OUT)").unwrap().values, out.values);
+        assert_eq!(rawfile.waveform("i(vdd)").unwrap().values, vec![-1e-6, -2e-4, -1e-6]);
+        assert!(rawfile.waveform("in").is_none());
+    }
+
+    #[test]
+    fn test_parse_ascii() {
+        let mut content = format!("{}Values:\n", HEADER);
+        for (index, point) in POINTS.iter().enumerate() {
+            content += &format!(" {}\t{:e}\n", index, point[0]);
+            for value in &point[1..] {
+                content += &format!("\t{:e}\n", value);
+            }
+            content += "\n";
+        }
+        check(&RawFile::parse(content.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        let mut content = format!("{}Binary:\n", HEADER).into_bytes();
+        for value in POINTS.iter().flatten() {
+            content.extend(value.to_le_bytes());
+        }
+        // two plots in a file, like `.op` then `.tran`
+        let mut two = content.clone();
+        two.extend(&content);
+        check(&RawFile::parse(&content).unwrap());
+        assert_eq!(RawFile::parse(&two).unwrap().plots.len(), 2);
+
+        content.truncate(content.len() - 4);
+        assert!(RawFile::parse(&content).is_err());
+    }
+}
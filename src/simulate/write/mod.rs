@@ -3,23 +3,45 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use reda_unit::{Capacitance, Temperature, Time, Voltage};
 use crate::YouRAMResult;
-use crate::simulate::{Meas, SimulateError};
+use crate::simulate::{ConvergenceOptions, Meas, SimulateError};
 
 use super::SpiceExector;
 
+/// Netlist syntax accepted by a simulator backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpiceDialect {
+    #[default]
+    NgSpice,
+    /// Spectre reading a spice deck (`simulator lang=spice`)
+    Spectre,
+    /// Xyce, options are grouped by package like `.options timeint`
+    Xyce,
+}
+
 pub struct SpiceWritor {
     simulate_path: PathBuf,
     file: File,
+    dialect: SpiceDialect,
     measurements: Vec<Box<dyn Meas>>, 
 }
 
 impl SpiceWritor {
     pub fn open<P: Into<PathBuf>>(simulate_path: P) -> YouRAMResult<Self> {
+        Self::open_with_dialect(simulate_path, SpiceDialect::default())
+    }
+
+    pub fn open_with_dialect<P: Into<PathBuf>>(simulate_path: P, dialect: SpiceDialect) -> YouRAMResult<Self> {
         let simulate_path = simulate_path.into();
-        let file = File::create(&simulate_path)?;
+        let mut file = File::create(&simulate_path)?;
+        if dialect == SpiceDialect::Spectre {
+            // first line of a deck is the title, then switch spectre parser to spice syntax
+            writeln!(file, "* youram simulation deck")?;
+            writeln!(file, "simulator lang=spice")?;
+        }
         Ok(Self {
             simulate_path: simulate_path.to_path_buf(),
             file,
+            dialect,
             measurements: vec![],
         })
     }
@@ -39,13 +61,39 @@ impl SpiceWritor {
         &self.simulate_path
     }
 
+    pub fn dialect(&self) -> SpiceDialect {
+        self.dialect
+    }
+
+    /// Dialect of the commands written after, the header of `open_with_dialect` is not written
+    pub fn set_dialect(&mut self, dialect: SpiceDialect) {
+        self.dialect = dialect;
+    }
+
     pub fn write_content(&mut self, content: impl AsRef<str>) -> YouRAMResult<()> {
         write!(self.file, "{}", content.as_ref())?;
         Ok(())
     }
 
     pub fn write_include<P: AsRef<Path>>(&mut self, path: P) -> YouRAMResult<()> {
-        writeln!(self.file, ".include {}", path.as_ref().display())?;
+        match self.dialect {
+            SpiceDialect::NgSpice | SpiceDialect::Xyce => writeln!(self.file, ".include {}", path.as_ref().display())?,
+            SpiceDialect::Spectre => writeln!(self.file, ".include \"{}\"", path.as_ref().display())?,
+        }
+        Ok(())
+    }
+
+    /// Load `section` of a model library, like `.lib models.lib tt`
+    pub fn write_library<P: AsRef<Path>>(&mut self, path: P, section: impl AsRef<str>) -> YouRAMResult<()> {
+        match self.dialect {
+            SpiceDialect::NgSpice | SpiceDialect::Xyce => writeln!(self.file, ".lib {} {}", path.as_ref().display(), section.as_ref())?,
+            SpiceDialect::Spectre => writeln!(self.file, ".lib \"{}\" {}", path.as_ref().display(), section.as_ref())?,
+        }
+        Ok(())
+    }
+
+    pub fn write_param(&mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> YouRAMResult<()> {
+        writeln!(self.file, ".param {}={}", name.as_ref(), value.as_ref())?;
         Ok(())
     }
 
@@ -164,6 +212,18 @@ impl SpiceWritor {
         Ok(())
     }
 
+    /// Zero volt source from `from_net` to `to_net`, its current `i(V<probe_name>)` is
+    /// the current flowing from `from_net` to `to_net`
+    pub fn write_current_probe(
+        &mut self,
+        probe_name: impl AsRef<str>,
+        from_net: impl AsRef<str>,
+        to_net: impl AsRef<str>,
+    ) -> YouRAMResult<()> {
+        writeln!(self.file, "V{} {} {} 0", probe_name.as_ref(), from_net.as_ref(), to_net.as_ref())?;
+        Ok(())
+    }
+
     pub fn write_capacitance(
         &mut self,
         name: impl AsRef<str>,
@@ -175,13 +235,73 @@ impl SpiceWritor {
         Ok(())
     }
 
+    /// `.option` lines of a convergence retry, nothing if `options` relaxes nothing.
+    /// Xyce sets both of transient analysis in its `timeint` package
+    pub fn write_options(&mut self, options: &ConvergenceOptions) -> YouRAMResult<()> {
+        match self.dialect {
+            SpiceDialect::NgSpice | SpiceDialect::Spectre => {
+                if let Some(reltol) = options.reltol {
+                    writeln!(self.file, ".option reltol={}", reltol)?;
+                }
+                if options.gear {
+                    writeln!(self.file, ".option method=gear")?;
+                }
+            }
+            SpiceDialect::Xyce => {
+                let mut timeint = vec![];
+                if let Some(reltol) = options.reltol {
+                    timeint.push(format!("reltol={}", reltol));
+                }
+                if options.gear {
+                    timeint.push("method=gear".to_string());
+                }
+                if !timeint.is_empty() {
+                    writeln!(self.file, ".options timeint {}", timeint.join(" "))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn write_trans(&mut self, step: impl Into<Time>, start: impl Into<Time>, end: impl Into<Time>) -> YouRAMResult<()> {
         writeln!(self.file, ".TRAN {} {} {}", step.into(), end.into(), start.into())?;
         Ok(())
     }
 
+    /// Sweep the dc source `V<voltage_name>` from `start` to `stop`, a negative `step` sweeps down
+    pub fn write_dc_sweep(
+        &mut self,
+        voltage_name: impl AsRef<str>,
+        start: impl Into<Voltage>,
+        stop: impl Into<Voltage>,
+        step: impl Into<Voltage>,
+    ) -> YouRAMResult<()> {
+        writeln!(self.file, ".DC V{} {} {} {}", voltage_name.as_ref(), start.into(), stop.into(), step.into())?;
+        Ok(())
+    }
+
+    /// Initial guess of `net` for the operating point, the solution of a bistable circuit
+    /// stays on the side it starts from
+    pub fn write_nodeset(&mut self, net_name: impl AsRef<str>, voltage: impl Into<Voltage>) -> YouRAMResult<()> {
+        writeln!(self.file, ".NODESET V({})={}", net_name.as_ref(), voltage.into())?;
+        Ok(())
+    }
+
     pub fn write_measurement(&mut self, meas: Box<dyn Meas>) -> YouRAMResult<()> {
-        meas.write_command(&mut self.file)?;
+        match self.dialect {
+            SpiceDialect::NgSpice | SpiceDialect::Xyce => meas.write_command(&mut self.file)?,
+            SpiceDialect::Spectre => {
+                // spectre only accept the long form `.measure`
+                let mut command = vec![];
+                meas.write_command(&mut command)?;
+                for line in String::from_utf8_lossy(&command).lines() {
+                    match line.strip_prefix(".meas ") {
+                        Some(rest) => writeln!(self.file, ".measure {}", rest)?,
+                        None => writeln!(self.file, "{}", line)?,
+                    }
+                }
+            }
+        }
         self.measurements.push(meas);
         Ok(())
     }
@@ -211,6 +331,7 @@ mod tests {
         let mut sim = SpiceWritor {
             simulate_path: path.clone(),
             file,
+            dialect: SpiceDialect::NgSpice,
             measurements: vec![],
         };
 
@@ -227,6 +348,33 @@ mod tests {
         assert!(content.contains(".end"));
     }
 
+    #[test]
+    fn test_model_statements() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+        let file = OpenOptions::new().read(true).write(true).open(&path).unwrap();
+
+        let mut sim = SpiceWritor {
+            simulate_path: path.clone(),
+            file,
+            dialect: SpiceDialect::NgSpice,
+            measurements: vec![],
+        };
+
+        sim.write_param("vth_shift", "0.01").unwrap();
+        sim.write_library("models.lib", "tt").unwrap();
+        sim.dialect = SpiceDialect::Spectre;
+        sim.write_library("models.lib", "ss").unwrap();
+        sim.write_include("extra.sp").unwrap();
+        sim.file.flush().unwrap();
+
+        let content = read_file_to_string(&path);
+        assert!(content.contains(".param vth_shift=0.01"));
+        assert!(content.contains(".lib models.lib tt"));
+        assert!(content.contains(".lib \"models.lib\" ss"));
+        assert!(content.contains(".include \"extra.sp\""));
+    }
+
     #[test]
     fn test_voltage_sources() {
         let tmp = NamedTempFile::new().unwrap();
@@ -236,6 +384,7 @@ mod tests {
         let mut sim = SpiceWritor {
             simulate_path: path.clone(),
             file,
+            dialect: SpiceDialect::NgSpice,
             measurements: vec![],
         };
 
@@ -269,6 +418,7 @@ mod tests {
         let mut sim = SpiceWritor {
             simulate_path: path.clone(),
             file,
+            dialect: SpiceDialect::NgSpice,
             measurements: vec![],
         };
 
@@ -280,4 +430,52 @@ mod tests {
         assert!(content.contains("Xinv0 in out vdd gnd inv"));
         assert!(content.contains("Cload out gnd 0.01"));
     }
+
+    #[test]
+    fn test_dc_sweep() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+        let file = OpenOptions::new().read(true).write(true).open(&path).unwrap();
+
+        let mut sim = SpiceWritor {
+            simulate_path: path.clone(),
+            file,
+            dialect: SpiceDialect::NgSpice,
+            measurements: vec![],
+        };
+
+        sim.write_dc_sweep("bl", v!(1.1), v!(0), v!(-0.01)).unwrap();
+        sim.write_nodeset("Xbitcell.Q", v!(1.1)).unwrap();
+        sim.file.flush().unwrap();
+
+        let content = read_file_to_string(&path);
+        println!("{}", content);
+        assert!(content.contains(".DC Vbl 1.1"));
+        assert!(content.contains(".NODESET V(Xbitcell.Q)=1.1"));
+    }
+
+    #[test]
+    fn test_options() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+        let file = OpenOptions::new().read(true).write(true).open(&path).unwrap();
+
+        let mut sim = SpiceWritor {
+            simulate_path: path.clone(),
+            file,
+            dialect: SpiceDialect::NgSpice,
+            measurements: vec![],
+        };
+
+        let options = ConvergenceOptions { reltol: Some(1e-3), gear: true, tstep_scale: 1.0 };
+        sim.write_options(&options).unwrap();
+        sim.dialect = SpiceDialect::Xyce;
+        sim.write_options(&options).unwrap();
+        sim.write_options(&ConvergenceOptions::gear()).unwrap();
+        sim.file.flush().unwrap();
+
+        let content = read_file_to_string(&path);
+        assert_eq!(content, ".option reltol=0.001\n.option method=gear\n\
+            .options timeint reltol=0.001 method=gear\n.options timeint method=gear\n");
+    }
 }
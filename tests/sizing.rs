@@ -0,0 +1,97 @@
+use std::{path::{Path, PathBuf}, sync::{Arc, Mutex}, time::Duration};
+use reda_unit::t;
+use youram::{
+    charz::{BitcellSweep, SizingOptimizer},
+    pdk::{Enviroment, LeafcellSizing, Pdk},
+    simulate::SpiceCommand, ErrorContext, YouRAMResult
+};
+
+const PDK: &str = "./platforms/nangate45";
+const TEMP: &str = "./temp/sizing";
+
+/// Keep each deck, and answer every meas of the sizing benches with a passing value
+#[derive(Default)]
+struct BenchCommand {
+    decks: Mutex<Vec<String>>,
+}
+
+impl SpiceCommand for BenchCommand {
+    fn simulate_command(&self, _sim_filepath: &Path, _temp_folder: &Path) -> YouRAMResult<String> {
+        Ok("true".into())
+    }
+
+    fn execute_timeout(&self, sim_filepath: &Path, temp_folder: &Path, _timeout: Option<Duration>) -> YouRAMResult<PathBuf> {
+        let deck = std::fs::read_to_string(sim_filepath)?;
+        let results: Vec<_> = deck.lines()
+            .filter_map(|line| line.strip_prefix(".meas tran "))
+            .filter_map(|meas| meas.split_whitespace().next())
+            .map(|name| match name {
+                name if name.starts_with("read_q") => format!("{} = 0.1", name),
+                name if name.starts_with("write_br") => format!("{} = 0.4", name),
+                name if name.starts_with("wordline_charge") => format!("{} = -2e-16", name),
+                name => format!("{} = 1e-10", name),
+            })
+            .collect();
+        self.decks.lock().unwrap().push(deck);
+
+        let meas_path = self.meas_result_filepath(sim_filepath, temp_folder)?;
+        std::fs::write(&meas_path, results.join("\n") + "\n")?;
+        Ok(meas_path)
+    }
+}
+
+fn main_result() -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(TEMP)?;
+
+    let pdk = Arc::new(Pdk::load(PDK).context("load pdk")?);
+    let sizing = LeafcellSizing::load(format!("{}/leafcell_sizing.json", PDK))?;
+    let env = Enviroment::new(pdk.pvt().clone(), t!(0.1 n), 0.0.into());
+    let vdd = env.voltage().value().to_f64();
+    let sweep = BitcellSweep { access: vec![sizing.bitcell.access, sizing.bitcell.access * 1.5], ..Default::default() };
+
+    let command = Arc::new(BenchCommand::default());
+    let result = SizingOptimizer::config()
+        .pdk(pdk)
+        .env(env)
+        .sizing(sizing.clone())
+        .bitcell_sweep(sweep)
+        .command_box(Box::new(command.clone()))
+        .temp_folder(TEMP)
+        .optimize()?;
+    assert_eq!(result.sizing.bitcell.access, sizing.bitcell.access);
+    let wordline_capacitance = result.sizing.bitcell_wordline_capacitance.unwrap().value().to_f64();
+    assert!((wordline_capacitance - 2e-16 / vdd).abs() < 1e-24);
+
+    // 2 bitcell candidates and 1 sense amp candidate
+    let decks = command.decks.lock().unwrap();
+    assert_eq!(decks.len(), 3);
+
+    for (index, bitcell) in decks[..2].iter().enumerate() {
+        assert!(bitcell.contains("\nXbitcell bl br wl VDD VSS bitcell\n"), "{}", bitcell);
+        for port in ["bl", "br", "wl"] {
+            assert!(bitcell.lines().any(|line| line.starts_with(&format!("V{} {} 0 PWL", port, port))), "{}", bitcell);
+        }
+        assert!(bitcell.contains(&format!(".meas tran read_q_{} ", index)), "{}", bitcell);
+        assert!(bitcell.contains(&format!(".meas tran write_br_{} ", index)), "{}", bitcell);
+        assert!(bitcell.contains(&format!(".meas tran wordline_charge_{} INTEG i(Vwl) ", index)), "{}", bitcell);
+        assert!(bitcell.contains("v(Xbitcell.Q_bar)"), "{}", bitcell);
+    }
+
+    let sense_amp = &decks[2];
+    assert!(sense_amp.contains("\nXsense_amp bl br dout en VDD VSS sense_amp\n"), "{}", sense_amp);
+    for port in ["bl", "br"] {
+        assert!(sense_amp.lines().any(|line| line.starts_with(&format!("V{} {} 0 ", port, port))), "{}", sense_amp);
+    }
+    assert!(sense_amp.lines().any(|line| line.starts_with("Ven en 0 PWL")), "{}", sense_amp);
+    assert!(sense_amp.contains(".meas tran sense_delay_0 TRIG v(en) "), "{}", sense_amp);
+
+    Ok(())
+}
+
+#[test]
+fn main() {
+    if let Err(e) = main_result() {
+        eprintln!("Err: {}", e);
+        panic!("");
+    }
+}
@@ -0,0 +1,49 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use reda_unit::t;
+use youram::simulate::{SpiceCommand, SpiceDialect, SpiceWritor, Spectre, VoltageAtMeas};
+
+/// Stand-in for `spectre <netlist> -raw <dir> +log <log>`: it checks the deck is in spice mode,
+/// and reports every `.measure` as `<lowercase name> = 250.5m` like spectre does
+const FAKE_SPECTRE: &str = r#"#!/bin/sh
+netlist=$1
+raw=$3
+log=$5
+grep -q '^simulator lang=spice' "$netlist" || exit 1
+grep -q '^\.meas ' "$netlist" && exit 1
+stem=$(basename "$netlist" .sp)
+echo "spectre fake run" > "$log"
+awk '$1 == ".measure" { print tolower($3) " = 250.5m" }' "$netlist" > "$raw/$stem.measure"
+"#;
+
+fn write_fake_spectre(dir: &Path) -> String {
+    let path = dir.join("spectre");
+    std::fs::write(&path, FAKE_SPECTRE).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path.display().to_string()
+}
+
+#[test]
+fn test_spectre_measure() {
+    let temp = tempfile::tempdir().unwrap();
+    let command = Spectre::new(write_fake_spectre(temp.path()));
+    assert_eq!(command.dialect(), SpiceDialect::Spectre);
+
+    let sim_path = temp.path().join("read.sp");
+    let mut writor = SpiceWritor::open_with_dialect(&sim_path, command.dialect()).unwrap();
+    writor.write_dc_voltage("VDD", "VDD", 1.1).unwrap();
+    writor.write_trans(t!(1 p), t!(0 n), t!(2 n)).unwrap();
+    writor.write_measurement(Box::new(VoltageAtMeas::new("READ_Q", "q", t!(1 n)))).unwrap();
+    writor.write_end().unwrap();
+    let mut exector = writor.close().unwrap();
+
+    let results = exector.simulate(&command, temp.path()).unwrap();
+    assert!((results["READ_Q"].to_f64() - 0.2505).abs() < 1e-12);
+    assert!(temp.path().join("read.log").exists());
+
+    let boxed: Box<dyn SpiceCommand> = Box::new(command);
+    assert_eq!(
+        boxed.meas_result_filepath(&sim_path, temp.path()).unwrap(),
+        temp.path().join("read.measure"),
+    );
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+use reda_unit::t;
+use youram::{
+    circuit::{CircuitFactory, SramArg},
+    export,
+    pdk::{Enviroment, Pdk},
+    simulate::CircuitSimulator, ErrorContext
+};
+
+const PDK: &str = "./platforms/nangate45";
+const TEMP: &str = "./temp/dual_supply";
+const ADDRESS_WIDTH: usize = 2;
+const WORD_WIDTH: usize = 4;
+
+fn main_result() -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(TEMP)?;
+
+    let pdk = Arc::new(Pdk::load(PDK).context("load pdk")?);
+    let mut factory = CircuitFactory::new(pdk.clone());
+    let sram_arg = SramArg {
+        dual_supply: true,
+        ..SramArg::new(ADDRESS_WIDTH, WORD_WIDTH)
+    };
+    let sram = factory.module(sram_arg)?;
+    let sram_name = sram.read().name.to_string();
+
+    // vdd_array is propagated from sram down to the bitcell array
+    let circuit_path = format!("{}/{}.sp", TEMP, sram_name);
+    export::write_spice(sram.clone(), &circuit_path)?;
+    let netlist = std::fs::read_to_string(&circuit_path)?;
+    let subckts: Vec<_> = netlist.lines().filter(|line| line.starts_with(".SUBCKT")).collect();
+    for prefix in [format!(".SUBCKT {} ", sram_name), ".SUBCKT core".into(), ".SUBCKT bank".into()] {
+        let subckt = subckts.iter().find(|line| line.starts_with(&prefix)).ok_or(format!("no subckt '{}'", prefix))?;
+        assert!(subckt.split_whitespace().any(|port| port == "vdd_array"), "{}", subckt);
+    }
+
+    // vdd_array gets its own source, other supplies are on pvt voltage
+    let simulate_path = format!("{}/simulate.sp", TEMP);
+    let env = Enviroment::new(pdk.pvt().clone(), t!(0.1 n), 0.0.into())
+        .with_supply_voltage("vdd_array", 0.9);
+    let simulator = CircuitSimulator::create(sram, env, pdk.clone(), &simulate_path, &circuit_path)?;
+    drop(simulator);
+    let deck = std::fs::read_to_string(&simulate_path)?;
+    assert!(deck.lines().any(|line| line.starts_with("Vvdd_array vdd_array 0 ")), "{}", deck);
+    let instance = deck.lines().find(|line| line.starts_with(&format!("X{} ", sram_name))).ok_or("no sram instance")?;
+    assert!(instance.split_whitespace().any(|net| net == "vdd_array"), "{}", instance);
+    assert!(instance.split_whitespace().any(|net| net == "VDD"), "{}", instance);
+
+    Ok(())
+}
+
+#[test]
+fn main() {
+    if let Err(e) = main_result() {
+        eprintln!("Err: {}", e);
+        panic!("");
+    }
+}
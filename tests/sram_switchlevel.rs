@@ -0,0 +1,90 @@
+use std::{io::Write, path::{Path, PathBuf}, sync::Arc};
+use reda_unit::t;
+use tracing::Level;
+use youram::{
+    charz::{FunctionCharz, RandomPolicy},
+    circuit::{CircuitFactory, SramArg},
+    pdk::{Enviroment, Pdk},
+    simulate::{SpiceCommand, SwitchLevel}, ErrorContext, YouRAMResult
+};
+
+const PDK: &str = "./platforms/nangate45";
+const TEMP: &str = "./temp/switchlevel";
+const ADDRESS_WIDTH: usize = 2;
+const WORD_WIDTH: usize = 4;
+
+/// `SwitchLevel` with the first read of `dout0` flipped, so it never matches the expected value
+struct CorruptedRead {
+    vdd: f64,
+}
+
+impl SpiceCommand for CorruptedRead {
+    fn simulate_command(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<String> {
+        SwitchLevel.simulate_command(sim_filepath, temp_folder)
+    }
+
+    fn execute(&self, sim_filepath: &Path, temp_folder: &Path) -> YouRAMResult<PathBuf> {
+        let results = SwitchLevel.run(sim_filepath)?;
+
+        let result_path = self.meas_result_filepath(sim_filepath, temp_folder)?;
+        let mut file = std::fs::File::create(&result_path)?;
+        for (name, value) in results {
+            let value = if name == "dout0_0" { self.vdd - value } else { value };
+            writeln!(file, "{} = {:e}", name, value)?;
+        }
+        Ok(result_path)
+    }
+
+    fn execute_timeout(&self, sim_filepath: &Path, temp_folder: &Path, _timeout: Option<std::time::Duration>) -> YouRAMResult<PathBuf> {
+        self.execute(sim_filepath, temp_folder)
+    }
+}
+
+fn main_result() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_max_level(Level::DEBUG)
+        .with_target(false)
+        .with_file(false)
+        .with_line_number(false)
+        .init();
+
+    std::fs::create_dir_all(TEMP)?;
+
+    let pdk = Arc::new(Pdk::load(PDK).context("load pdk")?);
+    let mut factory = CircuitFactory::new(pdk.clone());
+    let sram = factory.module(SramArg::new(ADDRESS_WIDTH, WORD_WIDTH))?;
+    let pvt = pdk.pvt();
+    let env = Enviroment::new(pvt.clone(), t!(0.5 n), 0.0.into());
+
+    let pass = FunctionCharz::config()
+        .sram(sram.clone())
+        .period(t!(10. n))
+        .env(env.clone())
+        .pdk(pdk.clone())
+        .policy(RandomPolicy)
+        .command(SwitchLevel)
+        .temp_folder(TEMP)
+        .test()?;
+    assert!(pass);
+
+    let pass = FunctionCharz::config()
+        .sram(sram.clone())
+        .period(t!(10. n))
+        .env(env.clone())
+        .pdk(pdk)
+        .policy(RandomPolicy)
+        .command(CorruptedRead { vdd: env.voltage().value().to_f64() })
+        .temp_folder(TEMP)
+        .test()?;
+    assert!(!pass);
+
+    Ok(())
+}
+
+#[test]
+fn main() {
+    if let Err(e) = main_result() {
+        eprintln!("Err: {}", e);
+        panic!("");
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use reda_unit::t;
+use tracing::{error, info, Level};
+use youram::{
+    charz::VminCharz, circuit::{CircuitFactory, SramArg}, pdk::Pdk, simulate::NgSpice, ErrorContext
+};
+
+const PDK: &str = "./platforms/nangate45";
+const TEMP: &str = "./temp/vmin";
+const ADDRESS_WIDTH: usize = 2;
+const WORD_WIDTH: usize = 4;
+
+fn main_result() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_max_level(Level::DEBUG)
+        .with_target(false)
+        .with_file(false)
+        .with_line_number(false)
+        .init();
+
+    let pdk = Arc::new(Pdk::load(PDK).context("load pdk")?);
+    let mut factory = CircuitFactory::new(pdk.clone());
+    let sram = factory.module(SramArg::new(ADDRESS_WIDTH, WORD_WIDTH))?;
+    let pvt = pdk.pvt().clone();
+
+    let results = VminCharz::config()
+        .sram(sram.clone())
+        .period(t!(10. n))
+        .pvt(pvt.clone())
+        .pdk(pdk)
+        .command(NgSpice)
+        .temp_folder(TEMP)
+        .analyze()?;
+
+    info!("Vmin charz result: {:#?}", results);
+    assert_eq!(results.len(), 1);
+    for vmin in [results[0].read_vmin, results[0].write_vmin] {
+        let vmin = vmin.ok_or("fail at pvt voltage")?;
+        assert!(vmin.value().to_f64() <= pvt.voltage.value().to_f64());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn main() {
+    if let Err(e) = main_result() {
+        error!("Err: {}\n", e);
+        panic!("");
+    }
+}